@@ -50,6 +50,10 @@ pub fn manifest(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStr
             pub views: Vec<Arc<View>>,
             #[serde(default)]
             pub data_source: Option<DataSource>,
+            #[serde(default)]
+            pub declared_session_keys: Option<Vec<String>>,
+            #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+            pub default_properties: std::collections::BTreeMap<String, String>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -150,6 +154,9 @@ pub fn model(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream
             pub refresh_time: Option<String>,
             #[serde(default)]
             pub row_level_access_controls: Vec<Arc<RowLevelAccessControl>>,
+            /// The time the data of the model was last refreshed, in milliseconds since the Unix epoch
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub last_refreshed: Option<u64>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -193,6 +200,15 @@ pub fn column(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStrea
             #[deprecated]
             pub cls: Option<ColumnLevelSecurity>,
             pub column_level_access_control: Option<Arc<ColumnLevelAccessControl>>,
+            #[serde(default)]
+            pub default_value: Option<String>,
+            /// The description of the column, emitted as a comment by the generated DDL
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub description: Option<String>,
+            /// The predicate of the rows aggregated by a measure of a metric, e.g.
+            /// `status = 'paid'` for `sum(amount) FILTER (WHERE status = 'paid')`
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub filter: Option<String>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -362,6 +378,37 @@ pub fn view(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream
         pub struct View {
             pub name: String,
             pub statement: String,
+            /// The parameters referenced by the statement as `$name` placeholders
+            #[serde(default)]
+            pub parameters: Vec<ViewParameter>,
+            /// The description of the view, emitted as a comment by the generated DDL
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub description: Option<String>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+#[proc_macro]
+pub fn view_parameter(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct ViewParameter {
+            pub name: String,
+            /// A SQL expression used if the parameter isn't passed. The parameter is required if it's `None`.
+            #[serde(default)]
+            pub default_value: Option<String>,
         }
     };
     proc_macro::TokenStream::from(expanded)
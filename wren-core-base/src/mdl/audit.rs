@@ -0,0 +1,415 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::ops::ControlFlow;
+
+use crate::mdl::manifest::{Manifest, Model};
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, Ident, UnaryOperator, Value, ValueWithSpan,
+};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+/// The report of the row level access controls of the models in a manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessAudit {
+    pub models: Vec<ModelAccess>,
+}
+
+impl AccessAudit {
+    /// The models which allow all rows, i.e. the ones not [AccessStatus::Constrained].
+    pub fn flagged(&self) -> impl Iterator<Item = &ModelAccess> {
+        self.models
+            .iter()
+            .filter(|model| model.status != AccessStatus::Constrained)
+    }
+
+    pub fn get(&self, model: &str) -> Option<&ModelAccess> {
+        self.models.iter().find(|m| m.model == model)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelAccess {
+    pub model: String,
+    pub status: AccessStatus,
+    /// The names of the rules whose condition is always true
+    pub tautological_rules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessStatus {
+    /// The model has no row level access control rule
+    Unprotected,
+    /// Every rule of the model is always true, e.g. `1 = 1`
+    Tautological,
+    /// At least one rule filters the rows
+    Constrained,
+}
+
+/// A row or column level access control rule of a model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleRef {
+    pub model: String,
+    /// The column of a column level rule, or None for a row level rule
+    pub column: Option<String>,
+    pub name: String,
+}
+
+impl Manifest {
+    /// Find the access control rules referencing the session property, e.g. to rename it.
+    ///
+    /// A rule references the key if it's one of the required properties of the rule, or if
+    /// the condition of a row level rule refers to it, e.g. `@tenant_id`, the namespaced
+    /// `@auth.tenant_id` or the quoted `"@auth.tenant_id"`. The key is compared
+    /// case-insensitively with or without the leading `@`. The rules are listed in the order
+    /// of the models, the row level rules before the column level ones.
+    pub fn rules_referencing_key(&self, key: &str) -> Vec<RuleRef> {
+        let key = key.strip_prefix('@').unwrap_or(key).to_lowercase();
+        let mut rules = vec![];
+        for model in &self.models {
+            for rule in &model.row_level_access_controls {
+                let required = rule
+                    .required_properties
+                    .iter()
+                    .any(|property| property.normalized_name() == key);
+                if required || condition_references(&rule.condition, &key) {
+                    rules.push(RuleRef {
+                        model: model.name.clone(),
+                        column: None,
+                        name: rule.name.clone(),
+                    });
+                }
+            }
+            for column in &model.columns {
+                let Some(rule) = &column.column_level_access_control else {
+                    continue;
+                };
+                if rule
+                    .required_properties
+                    .iter()
+                    .any(|property| property.normalized_name() == key)
+                {
+                    rules.push(RuleRef {
+                        model: model.name.clone(),
+                        column: Some(column.name.clone()),
+                        name: rule.name.clone(),
+                    });
+                }
+            }
+        }
+        rules
+    }
+
+    /// Report whether the row level access controls of each model effectively allow all rows.
+    ///
+    /// The detection of the tautologies is best-effort. A condition is a tautology if folding
+    /// its constants gives true, e.g. `1 = 1` or `status = 'open' OR 2 > 1`. A condition
+    /// which can't be parsed is considered constrained because it fails the validation.
+    pub fn audit_access(&self) -> AccessAudit {
+        AccessAudit {
+            models: self
+                .models
+                .iter()
+                .map(|model| audit_model(model.as_ref()))
+                .collect(),
+        }
+    }
+}
+
+fn audit_model(model: &Model) -> ModelAccess {
+    let rules = &model.row_level_access_controls;
+    let tautological_rules: Vec<String> = rules
+        .iter()
+        .filter(|rule| is_tautology(&rule.condition))
+        .map(|rule| rule.name.clone())
+        .collect();
+    let status = if rules.is_empty() {
+        AccessStatus::Unprotected
+    } else if tautological_rules.len() == rules.len() {
+        AccessStatus::Tautological
+    } else {
+        AccessStatus::Constrained
+    };
+    ModelAccess {
+        model: model.name.clone(),
+        status,
+        tautological_rules,
+    }
+}
+
+fn is_tautology(condition: &str) -> bool {
+    Parser::new(&GenericDialect {})
+        .try_with_sql(condition)
+        .and_then(|mut parser| parser.parse_expr())
+        .map(|expr| fold(&expr) == Some(Constant::Bool(true)))
+        .unwrap_or(false)
+}
+
+/// Whether the condition refers to the lowercase session property. A condition which can't
+/// be parsed references nothing.
+fn condition_references(condition: &str, key: &str) -> bool {
+    let Ok(expr) = Parser::new(&GenericDialect {})
+        .try_with_sql(condition)
+        .and_then(|mut parser| parser.parse_expr())
+    else {
+        return false;
+    };
+    visit_expressions(&expr, |expr| {
+        let name = match expr {
+            Expr::Identifier(ident) => ident.value.clone(),
+            Expr::CompoundIdentifier(idents) => idents
+                .iter()
+                .map(|ident: &Ident| ident.value.as_str())
+                .collect::<Vec<_>>()
+                .join("."),
+            _ => return ControlFlow::Continue(()),
+        };
+        match name.strip_prefix('@') {
+            Some(name) if name.to_lowercase() == key => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    })
+    .is_break()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Constant {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+/// Fold the expression to a constant. `None` means the value depends on the rows.
+fn fold(expr: &Expr) -> Option<Constant> {
+    match expr {
+        Expr::Value(ValueWithSpan { value, .. }) => match value {
+            Value::Null => Some(Constant::Null),
+            Value::Boolean(b) => Some(Constant::Bool(*b)),
+            Value::Number(n, _) => n.parse().ok().map(Constant::Number),
+            Value::SingleQuotedString(s) => Some(Constant::Str(s.clone())),
+            _ => None,
+        },
+        Expr::Nested(expr) => fold(expr),
+        Expr::UnaryOp { op, expr } => match (op, fold(expr)?) {
+            (UnaryOperator::Not, Constant::Bool(b)) => Some(Constant::Bool(!b)),
+            (UnaryOperator::Minus, Constant::Number(n)) => Some(Constant::Number(-n)),
+            (UnaryOperator::Plus, Constant::Number(n)) => Some(Constant::Number(n)),
+            (_, Constant::Null) => Some(Constant::Null),
+            _ => None,
+        },
+        Expr::IsNull(expr) => fold(expr).map(|c| Constant::Bool(c == Constant::Null)),
+        Expr::IsNotNull(expr) => fold(expr).map(|c| Constant::Bool(c != Constant::Null)),
+        Expr::BinaryOp { left, op, right } => fold_binary(fold(left), op, fold(right)),
+        _ => None,
+    }
+}
+
+fn fold_binary(
+    left: Option<Constant>,
+    op: &BinaryOperator,
+    right: Option<Constant>,
+) -> Option<Constant> {
+    // a constant side decides AND and OR even if the other side isn't constant
+    match op {
+        BinaryOperator::Or => {
+            return match (left, right) {
+                (Some(Constant::Bool(true)), _) | (_, Some(Constant::Bool(true))) => {
+                    Some(Constant::Bool(true))
+                }
+                (Some(Constant::Bool(false)), Some(Constant::Bool(false))) => {
+                    Some(Constant::Bool(false))
+                }
+                _ => None,
+            }
+        }
+        BinaryOperator::And => {
+            return match (left, right) {
+                (Some(Constant::Bool(false)), _) | (_, Some(Constant::Bool(false))) => {
+                    Some(Constant::Bool(false))
+                }
+                (Some(Constant::Bool(true)), Some(Constant::Bool(true))) => {
+                    Some(Constant::Bool(true))
+                }
+                _ => None,
+            }
+        }
+        _ => {}
+    }
+    let (left, right) = (left?, right?);
+    if left == Constant::Null || right == Constant::Null {
+        return Some(Constant::Null);
+    }
+    let ordering = match (&left, &right) {
+        (Constant::Number(l), Constant::Number(r)) => l.partial_cmp(r),
+        (Constant::Str(l), Constant::Str(r)) => Some(l.cmp(r)),
+        (Constant::Bool(l), Constant::Bool(r)) => Some(l.cmp(r)),
+        _ => None,
+    };
+    match (op, &left, &right) {
+        (BinaryOperator::Plus, Constant::Number(l), Constant::Number(r)) => {
+            Some(Constant::Number(l + r))
+        }
+        (BinaryOperator::Minus, Constant::Number(l), Constant::Number(r)) => {
+            Some(Constant::Number(l - r))
+        }
+        (BinaryOperator::Multiply, Constant::Number(l), Constant::Number(r)) => {
+            Some(Constant::Number(l * r))
+        }
+        (BinaryOperator::Divide, Constant::Number(l), Constant::Number(r)) if *r != 0.0 => {
+            Some(Constant::Number(l / r))
+        }
+        (BinaryOperator::Eq, ..) => ordering.map(|o| Constant::Bool(o.is_eq())),
+        (BinaryOperator::NotEq, ..) => ordering.map(|o| Constant::Bool(o.is_ne())),
+        (BinaryOperator::Lt, ..) => ordering.map(|o| Constant::Bool(o.is_lt())),
+        (BinaryOperator::LtEq, ..) => ordering.map(|o| Constant::Bool(o.is_le())),
+        (BinaryOperator::Gt, ..) => ordering.map(|o| Constant::Bool(o.is_gt())),
+        (BinaryOperator::GtEq, ..) => ordering.map(|o| Constant::Bool(o.is_ge())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mdl::audit::{is_tautology, AccessStatus, RuleRef};
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::{ColumnLevelOperator, SessionProperty};
+
+    #[test]
+    fn test_rules_referencing_key() {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("tenant", "int").build())
+                    .column(ColumnBuilder::new("region", "varchar").build())
+                    .add_row_level_access_control("by_tenant", vec![], "tenant = @Auth.Tenant_Id")
+                    .add_row_level_access_control(
+                        "by_region",
+                        vec![SessionProperty::new_required("session_region")],
+                        "region = @session_region",
+                    )
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(
+                        ColumnBuilder::new("secret", "varchar")
+                            .column_level_access_control(
+                                "tenant_secret",
+                                vec![SessionProperty::new_required("auth.tenant_id")],
+                                ColumnLevelOperator::Equals,
+                                "1",
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let expected = vec![
+            RuleRef {
+                model: "orders".to_string(),
+                column: None,
+                name: "by_tenant".to_string(),
+            },
+            RuleRef {
+                model: "customer".to_string(),
+                column: Some("secret".to_string()),
+                name: "tenant_secret".to_string(),
+            },
+        ];
+        assert_eq!(manifest.rules_referencing_key("auth.tenant_id"), expected);
+        assert_eq!(manifest.rules_referencing_key("@AUTH.TENANT_ID"), expected);
+        assert!(manifest.rules_referencing_key("tenant").is_empty());
+    }
+
+    #[test]
+    fn test_audit_access() {
+        let model = |name: &str, rules: &[(&str, &str)]| {
+            let mut builder = ModelBuilder::new(name)
+                .table_reference(name)
+                .column(ColumnBuilder::new("id", "int").build())
+                .column(ColumnBuilder::new("region", "varchar").build());
+            for (rule_name, condition) in rules {
+                builder = builder.add_row_level_access_control(
+                    rule_name,
+                    vec![SessionProperty::new_required("session_region")],
+                    condition,
+                );
+            }
+            builder.build()
+        };
+        let manifest = ManifestBuilder::new()
+            .model(model("unprotected", &[]))
+            .model(model("always_true", &[("allow_all", "1 = 1")]))
+            .model(model(
+                "constrained",
+                &[
+                    ("by_region", "region = @session_region"),
+                    ("noop", "1 = 1 OR region = 'tw'"),
+                ],
+            ))
+            .build();
+
+        let audit = manifest.audit_access();
+        assert_eq!(
+            audit.get("unprotected").unwrap().status,
+            AccessStatus::Unprotected
+        );
+        let always_true = audit.get("always_true").unwrap();
+        assert_eq!(always_true.status, AccessStatus::Tautological);
+        assert_eq!(always_true.tautological_rules, vec!["allow_all"]);
+        let constrained = audit.get("constrained").unwrap();
+        assert_eq!(constrained.status, AccessStatus::Constrained);
+        assert_eq!(constrained.tautological_rules, vec!["noop"]);
+        assert_eq!(
+            audit
+                .flagged()
+                .map(|m| m.model.as_str())
+                .collect::<Vec<_>>(),
+            vec!["unprotected", "always_true"]
+        );
+    }
+
+    #[test]
+    fn test_is_tautology() {
+        for condition in [
+            "1 = 1",
+            "true",
+            "(2 > 1)",
+            "'a' = 'a'",
+            "NOT 1 = 2",
+            "region = 'tw' OR 1 + 1 = 2",
+            "1 = 1 AND NULL IS NULL",
+        ] {
+            assert!(is_tautology(condition), "{condition}");
+        }
+        for condition in [
+            "region = 'tw'",
+            "1 = 2",
+            "NULL = NULL",
+            "region = 'tw' AND 1 = 1",
+            "1 = 1 AND",
+        ] {
+            assert!(!is_tautology(condition), "{condition}");
+        }
+    }
+}
@@ -19,15 +19,21 @@
 
 #![allow(dead_code)]
 
+use crate::mdl::data_type::{WrenType, NESTED_TYPE_PREFIXES, WREN_TYPES};
+use crate::mdl::error::ManifestError;
 use crate::mdl::manifest::{
     Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeGrain, TimeUnit, View,
+    ViewParameter,
 };
+use crate::mdl::utils::quote_identifier;
 #[allow(deprecated)]
 use crate::mdl::{
     ColumnLevelOperator, ColumnLevelSecurity, NormalizedExpr, RowLevelAccessControl,
     RowLevelOperator, RowLevelSecurity, SessionProperty,
 };
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::ColumnLevelAccessControl;
 
@@ -53,6 +59,8 @@ impl ManifestBuilder {
                 metrics: vec![],
                 views: vec![],
                 data_source: None,
+                declared_session_keys: None,
+                default_properties: BTreeMap::new(),
             },
         }
     }
@@ -92,9 +100,32 @@ impl ManifestBuilder {
         self
     }
 
+    /// Declare a session property key that RLAC/CLAC rules are allowed to reference.
+    pub fn declared_session_key(mut self, key: &str) -> Self {
+        self.manifest
+            .declared_session_keys
+            .get_or_insert_with(Vec::new)
+            .push(key.to_string());
+        self
+    }
+
+    /// The default values of the session properties. They apply when the call doesn't
+    /// provide the property, e.g. a constant `x-wren-timezone` of the deployment.
+    pub fn default_properties(mut self, properties: HashMap<String, String>) -> Self {
+        self.manifest.default_properties.extend(properties);
+        self
+    }
+
     pub fn build(self) -> Manifest {
         self.manifest
     }
+
+    /// Build the manifest like [ManifestBuilder::build], but reject the duplicate names
+    /// checked by [Manifest::validate_names].
+    pub fn try_build(self) -> Result<Manifest, ManifestError> {
+        self.manifest.validate_names()?;
+        Ok(self.manifest)
+    }
 }
 
 pub struct ModelBuilder {
@@ -114,6 +145,7 @@ impl ModelBuilder {
                 cached: false,
                 refresh_time: None,
                 row_level_access_controls: vec![],
+                last_refreshed: None,
             },
         }
     }
@@ -153,6 +185,17 @@ impl ModelBuilder {
         self
     }
 
+    /// Record when the data of the model was last refreshed. The time is kept in
+    /// milliseconds, so the sub-millisecond part is dropped.
+    pub fn last_refreshed(mut self, last_refreshed: SystemTime) -> Self {
+        self.model.last_refreshed = Some(
+            last_refreshed
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_millis() as u64),
+        );
+        self
+    }
+
     pub fn add_row_level_access_control(
         mut self,
         name: &str,
@@ -171,6 +214,12 @@ impl ModelBuilder {
     pub fn build(self) -> Arc<Model> {
         Arc::new(self.model)
     }
+
+    /// Build the model like [ModelBuilder::build], but reject the duplicate column names.
+    pub fn try_build(self) -> Result<Arc<Model>, ManifestError> {
+        self.model.validate_columns()?;
+        Ok(self.build())
+    }
 }
 
 impl SessionProperty {
@@ -200,6 +249,9 @@ impl ColumnBuilder {
                 rls: None,
                 cls: None,
                 column_level_access_control: None,
+                default_value: None,
+                description: None,
+                filter: None,
             },
         }
     }
@@ -232,11 +284,37 @@ impl ColumnBuilder {
         self
     }
 
+    /// The name of the physical column when it differs from the name of the column, e.g.
+    /// `account_balance` stored as `c_acctbal`. The queries use the name of the column and
+    /// the generated SQL selects the physical column aliased to it. It's stored as the
+    /// expression referencing the physical column.
+    pub fn source_name(mut self, source_name: &str) -> Self {
+        self.column.expression = Some(quote_identifier(source_name).into_owned());
+        self
+    }
+
     pub fn hidden(mut self, is_hidden: bool) -> Self {
         self.column.is_hidden = is_hidden;
         self
     }
 
+    /// The literal used when the column value is null, e.g. `'unknown'` or `0`.
+    pub fn default_value(mut self, default_value: &str) -> Self {
+        self.column.default_value = Some(default_value.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.column.description = Some(description.to_string());
+        self
+    }
+
+    /// The predicate of the rows aggregated by the measure, e.g. `status = 'paid'`.
+    pub fn filter(mut self, filter: &str) -> Self {
+        self.column.filter = Some(filter.to_string());
+        self
+    }
+
     #[allow(deprecated)]
     pub fn row_level_security(mut self, name: &str, operator: RowLevelOperator) -> Self {
         self.column.rls = Some(RowLevelSecurity {
@@ -279,6 +357,51 @@ impl ColumnBuilder {
     pub fn build(self) -> Arc<Column> {
         Arc::new(self.column)
     }
+
+    /// Build the column like [ColumnBuilder::build], but reject the type that the engine
+    /// doesn't know. The closest known type is suggested for a typo.
+    ///
+    /// The type names are the ones of [WREN_TYPES], which the engine maps to the Arrow types.
+    /// The parameters of a type, e.g. `decimal(10, 2)` or `array<int>`, aren't checked.
+    pub fn try_build(self) -> Result<Arc<Column>, ManifestError> {
+        let lower = self.column.r#type.trim().to_lowercase();
+        let base_type = lower
+            .split(['(', '<', '['])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        if WrenType::from_name(base_type).is_some() || NESTED_TYPE_PREFIXES.contains(&base_type) {
+            return Ok(self.build());
+        }
+        let suggestion = WREN_TYPES
+            .iter()
+            .map(|(known, _)| known)
+            .chain(NESTED_TYPE_PREFIXES)
+            .map(|known| (edit_distance(base_type, known), *known))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known);
+        Err(ManifestError::UnknownType {
+            column: self.column.name.clone(),
+            r#type: self.column.r#type.clone(),
+            suggestion,
+        })
+    }
+}
+
+/// The Levenshtein distance between the two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 pub struct RelationshipBuilder {
@@ -336,6 +459,11 @@ impl MetricBuilder {
         }
     }
 
+    pub fn base_object(mut self, base_object: &str) -> Self {
+        self.metric.base_object = base_object.to_string();
+        self
+    }
+
     pub fn dimension(mut self, dimension: Arc<Column>) -> Self {
         self.metric.dimension.push(dimension);
         self
@@ -406,6 +534,8 @@ impl ViewBuilder {
             view: View {
                 name: name.to_string(),
                 statement: "".to_string(),
+                parameters: vec![],
+                description: None,
             },
         }
     }
@@ -415,6 +545,19 @@ impl ViewBuilder {
         self
     }
 
+    pub fn description(mut self, description: &str) -> Self {
+        self.view.description = Some(description.to_string());
+        self
+    }
+
+    pub fn parameter(mut self, name: &str, default_value: Option<&str>) -> Self {
+        self.view.parameters.push(ViewParameter {
+            name: name.to_string(),
+            default_value: default_value.map(|v| v.to_string()),
+        });
+        self
+    }
+
     pub fn build(self) -> Arc<View> {
         Arc::new(self.view)
     }
@@ -426,6 +569,8 @@ mod test {
         ColumnBuilder, ManifestBuilder, MetricBuilder, ModelBuilder, RelationshipBuilder,
         TimeGrainBuilder, ViewBuilder,
     };
+    use crate::mdl::data_type::WREN_TYPES;
+    use crate::mdl::error::ManifestError;
     use crate::mdl::manifest::DataSource::MySQL;
     use crate::mdl::manifest::{
         Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeUnit, View,
@@ -437,6 +582,7 @@ mod test {
     use std::fs;
     use std::path::PathBuf;
     use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     #[allow(deprecated)]
@@ -462,6 +608,161 @@ mod test {
         assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn test_column_try_build() {
+        let column = ColumnBuilder::new("id", "varchar").try_build().unwrap();
+        assert_eq!(column.r#type, "varchar");
+        assert!(ColumnBuilder::new("price", "DECIMAL(10, 2)")
+            .try_build()
+            .is_ok());
+        assert!(ColumnBuilder::new("tags", "array<varchar>")
+            .try_build()
+            .is_ok());
+
+        let error = ColumnBuilder::new("id", "varchr").try_build().unwrap_err();
+        assert_eq!(
+            error,
+            ManifestError::UnknownType {
+                column: "id".to_string(),
+                r#type: "varchr".to_string(),
+                suggestion: Some("varchar"),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Unknown type varchr of column id, did you mean 'varchar'?"
+        );
+        assert_eq!(
+            ColumnBuilder::new("id", "geography")
+                .try_build()
+                .unwrap_err()
+                .to_string(),
+            "Unknown type geography of column id"
+        );
+        // every type name known by the engine is accepted
+        for (name, _) in WREN_TYPES {
+            assert!(ColumnBuilder::new("c", name).try_build().is_ok(), "{name}");
+        }
+        // build stays lenient
+        assert_eq!(ColumnBuilder::new("id", "varchr").build().r#type, "varchr");
+    }
+
+    #[test]
+    fn test_freshness_report() {
+        let refreshed_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .last_refreshed(refreshed_at)
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .build(),
+            )
+            .build();
+        assert_eq!(manifest.models[0].last_refreshed(), Some(refreshed_at));
+        let now = refreshed_at + Duration::from_secs(90);
+        assert_eq!(
+            manifest.freshness_report(now),
+            vec![
+                ("customer".to_string(), Some(Duration::from_secs(90))),
+                ("orders".to_string(), None),
+            ]
+        );
+        // a refresh after now isn't a negative age
+        assert_eq!(
+            manifest.freshness_report(UNIX_EPOCH)[0],
+            ("customer".to_string(), Some(Duration::ZERO))
+        );
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(json.contains("\"lastRefreshed\":1700000000000"));
+        let deserialized: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, manifest);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let model = |name: &str| {
+            ModelBuilder::new(name)
+                .table_reference("customer")
+                .column(ColumnBuilder::new("id", "int").build())
+                .build()
+        };
+        assert_eq!(
+            ModelBuilder::new("customer")
+                .column(ColumnBuilder::new("id", "int").build())
+                .column(ColumnBuilder::new("name", "varchar").build())
+                .column(ColumnBuilder::new("id", "bigint").build())
+                .try_build()
+                .unwrap_err(),
+            ManifestError::DuplicateColumn {
+                model: "customer".to_string(),
+                column: "id".to_string(),
+            }
+        );
+        // the names are case-sensitive
+        assert!(ModelBuilder::new("customer")
+            .column(ColumnBuilder::new("id", "int").build())
+            .column(ColumnBuilder::new("ID", "int").build())
+            .try_build()
+            .is_ok());
+
+        assert_eq!(
+            ManifestBuilder::new()
+                .model(model("customer"))
+                .model(model("orders"))
+                .model(model("customer"))
+                .try_build()
+                .unwrap_err(),
+            ManifestError::DuplicateName {
+                name: "customer".to_string(),
+            }
+        );
+        assert_eq!(
+            ManifestBuilder::new()
+                .model(model("customer"))
+                .view(ViewBuilder::new("customer").statement("select 1").build())
+                .try_build()
+                .unwrap_err(),
+            ManifestError::DuplicateName {
+                name: "customer".to_string(),
+            }
+        );
+        assert!(ManifestBuilder::new()
+            .model(model("customer"))
+            .model(model("orders"))
+            .try_build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_column_source_name() {
+        let column = ColumnBuilder::new("account_balance", "double")
+            .source_name("c_acctbal")
+            .build();
+        assert_eq!(column.expression(), Some("c_acctbal"));
+        assert_eq!(column.source_name(), Some("c_acctbal".to_string()));
+
+        let column = ColumnBuilder::new("account_balance", "double")
+            .source_name("Acct Bal")
+            .build();
+        assert_eq!(column.expression(), Some(r#""Acct Bal""#));
+        assert_eq!(column.source_name(), Some("Acct Bal".to_string()));
+
+        let column = ColumnBuilder::new("total", "double")
+            .expression("price * quantity")
+            .build();
+        assert_eq!(column.source_name(), None);
+        let column = ColumnBuilder::new_calculated("name", "varchar")
+            .expression("c_name")
+            .build();
+        assert_eq!(column.source_name(), None);
+    }
+
     #[test]
     fn test_expression_empty_as_none() {
         let expected = ColumnBuilder::new("id", "integer").expression("").build();
@@ -637,7 +938,9 @@ mod test {
     #[test]
     fn test_view_roundtrip() {
         let expected = ViewBuilder::new("test")
-            .statement("SELECT * FROM test")
+            .statement("SELECT * FROM test WHERE id > $threshold")
+            .parameter("threshold", Some("100"))
+            .description("the tests above the threshold")
             .build();
 
         let json_str = serde_json::to_string(&expected).unwrap();
@@ -0,0 +1,144 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+/// The types of the type names of the columns, e.g. `varchar`. The engine maps each of them
+/// to an Arrow type, and [WREN_TYPES] is the only list of the names, so the validation of
+/// the builders and the engine know the same names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrenType {
+    Boolean,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Decimal,
+    String,
+    Float32,
+    Float64,
+    Timestamp,
+    TimestampWithTimeZone,
+    Date,
+    Time,
+    Interval,
+    Binary,
+    Null,
+}
+
+/// The type names and their types. Several names share a type if the engine has no closer
+/// one, e.g. `json` is a string.
+pub const WREN_TYPES: &[(&str, WrenType)] = &[
+    // Wren Definition Types
+    ("bool", WrenType::Boolean),
+    ("boolean", WrenType::Boolean),
+    ("tinyint", WrenType::Int8),
+    ("utinyint", WrenType::UInt8),
+    ("int2", WrenType::Int16),
+    ("smallint", WrenType::Int16),
+    ("usmallint", WrenType::UInt16),
+    ("int4", WrenType::Int32),
+    ("int", WrenType::Int32),
+    ("integer", WrenType::Int32),
+    ("uinteger", WrenType::UInt32),
+    ("int8", WrenType::Int64),
+    ("bigint", WrenType::Int64),
+    ("ubigint", WrenType::UInt64),
+    ("numeric", WrenType::Decimal),
+    ("decimal", WrenType::Decimal),
+    ("varchar", WrenType::String),
+    ("char", WrenType::String),
+    ("bpchar", WrenType::String),
+    ("text", WrenType::String),
+    ("string", WrenType::String),
+    ("name", WrenType::String),
+    ("float4", WrenType::Float32),
+    ("real", WrenType::Float32),
+    ("float", WrenType::Float32),
+    ("float8", WrenType::Float64),
+    ("double", WrenType::Float64),
+    ("timestamp", WrenType::Timestamp),
+    ("datetime", WrenType::Timestamp),
+    ("timestamptz", WrenType::TimestampWithTimeZone),
+    ("timestamp_with_timezone", WrenType::TimestampWithTimeZone),
+    ("timestamp_with_time_zone", WrenType::TimestampWithTimeZone),
+    ("timestamp with time zone", WrenType::TimestampWithTimeZone),
+    // there is no time with time zone type
+    ("time with time zone", WrenType::TimestampWithTimeZone),
+    ("date", WrenType::Date),
+    ("interval", WrenType::Interval),
+    ("json", WrenType::String),
+    ("xml", WrenType::String),
+    ("jsonb", WrenType::Binary),
+    ("oid", WrenType::Int32),
+    ("bytea", WrenType::Binary),
+    ("uuid", WrenType::String),
+    ("inet", WrenType::String),
+    ("unknown", WrenType::String),
+    // BigQuery Compatible Types
+    ("bignumeric", WrenType::Decimal),
+    ("bytes", WrenType::Binary),
+    ("binary", WrenType::Binary),
+    ("float64", WrenType::Float64),
+    ("int64", WrenType::Int64),
+    ("time", WrenType::Time),
+    ("null", WrenType::Null),
+    // Trino Compatible Types
+    ("varbinary", WrenType::Binary),
+    // DuckDB Compatible Types
+    ("blob", WrenType::Binary),
+    ("hugeint", WrenType::Int64),
+    ("uhugeint", WrenType::UInt64),
+    ("bit", WrenType::Boolean),
+    ("timestamp_ns", WrenType::Timestamp),
+    ("any", WrenType::String),
+];
+
+/// The prefixes of the nested types, e.g. `array<int>` or `struct<id int>`. The engine
+/// parses their element types itself.
+pub const NESTED_TYPE_PREFIXES: &[&str] = &["array", "list", "struct"];
+
+impl WrenType {
+    /// The type of the name, case-insensitively. The name is matched as a whole, so a name
+    /// with parameters, e.g. `decimal(10, 2)`, isn't found.
+    pub fn from_name(name: &str) -> Option<WrenType> {
+        WREN_TYPES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, wren_type)| *wren_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mdl::data_type::WrenType;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(WrenType::from_name("VARCHAR"), Some(WrenType::String));
+        assert_eq!(
+            WrenType::from_name("timestamp with time zone"),
+            Some(WrenType::TimestampWithTimeZone)
+        );
+        assert_eq!(WrenType::from_name("decimal(10, 2)"), None);
+        assert_eq!(WrenType::from_name("varchr"), None);
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt::{Display, Formatter};
+
+/// The errors of building and validating the manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// The type of the column isn't a type name of [crate::mdl::WREN_TYPES]
+    UnknownType {
+        column: String,
+        r#type: String,
+        /// The closest known type name for a typo
+        suggestion: Option<&'static str>,
+    },
+    /// Two columns of the model share a name
+    DuplicateColumn { model: String, column: String },
+    /// Two models, views or metrics share a name
+    DuplicateName { name: String },
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::UnknownType {
+                column,
+                r#type,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unknown type {} of column {column}, did you mean '{suggestion}'?",
+                r#type
+            ),
+            ManifestError::UnknownType {
+                column,
+                r#type,
+                suggestion: None,
+            } => write!(f, "Unknown type {} of column {column}", r#type),
+            ManifestError::DuplicateColumn { model, column } => {
+                write!(f, "Duplicate column {column} in model {model}")
+            }
+            ManifestError::DuplicateName { name } => {
+                write!(f, "Duplicate model, view or metric name {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
@@ -16,8 +16,12 @@
  * specific language governing permissions and limitations
  * under the License.
  */
-use std::fmt::Display;
+use crate::mdl::error::ManifestError;
+use crate::mdl::utils::{is_json_path_expression, parse_identifiers};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Write};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(not(feature = "python-binding"))]
 #[allow(deprecated)]
@@ -28,7 +32,7 @@ mod manifest_impl {
         column, column_level_access_control, column_level_operator, column_level_security,
         data_source, join_type, manifest, metric, model, normalized_expr, normalized_expr_type,
         relationship, row_level_access_control, row_level_operator, row_level_security,
-        session_property, time_grain, time_unit, view,
+        session_property, time_grain, time_unit, view, view_parameter,
     };
     use serde::{Deserialize, Serialize};
     use serde_with::serde_as;
@@ -43,6 +47,7 @@ mod manifest_impl {
     relationship!(false);
     metric!(false);
     view!(false);
+    view_parameter!(false);
     join_type!(false);
     time_grain!(false);
     time_unit!(false);
@@ -66,7 +71,7 @@ mod manifest_impl {
         column, column_level_access_control, column_level_operator, column_level_security,
         data_source, join_type, manifest, metric, model, normalized_expr, normalized_expr_type,
         relationship, row_level_access_control, row_level_operator, row_level_security,
-        session_property, time_grain, time_unit, view,
+        session_property, time_grain, time_unit, view, view_parameter,
     };
     use pyo3::pyclass;
     use serde::{Deserialize, Serialize};
@@ -82,6 +87,7 @@ mod manifest_impl {
     relationship!(true);
     metric!(true);
     view!(true);
+    view_parameter!(true);
     join_type!(true);
     time_grain!(true);
     time_unit!(true);
@@ -246,7 +252,145 @@ impl Display for JoinType {
     }
 }
 
+impl Manifest {
+    /// Return the declared session property keys. `None` means the manifest doesn't declare
+    /// them and the rules aren't checked against a declared set.
+    pub fn declared_session_keys(&self) -> Option<&[String]> {
+        self.declared_session_keys.as_deref()
+    }
+
+    /// Reject the models, views and metrics sharing a name and the duplicate columns of
+    /// the models. The objects are referenced by their names in the same schema, so a
+    /// model and a view can't share one either.
+    pub fn validate_names(&self) -> Result<(), ManifestError> {
+        let names = self
+            .models
+            .iter()
+            .map(|model| model.name.as_str())
+            .chain(self.views.iter().map(|view| view.name.as_str()))
+            .chain(self.metrics.iter().map(|metric| metric.name.as_str()));
+        if let Some(name) = first_duplicate(names) {
+            return Err(ManifestError::DuplicateName {
+                name: name.to_string(),
+            });
+        }
+        self.models
+            .iter()
+            .try_for_each(|model| model.validate_columns())
+    }
+
+    /// Return the default values of the session properties, which the properties of a call
+    /// override.
+    pub fn default_properties(&self) -> &BTreeMap<String, String> {
+        &self.default_properties
+    }
+
+    /// The time since each model was last refreshed, in the order of the models. It's
+    /// `None` if the refresh time of the model is unknown, and zero if it's after `now`.
+    pub fn freshness_report(&self, now: SystemTime) -> Vec<(String, Option<Duration>)> {
+        self.models
+            .iter()
+            .map(|model| {
+                let age = model
+                    .last_refreshed()
+                    .map(|last_refreshed| now.duration_since(last_refreshed).unwrap_or_default());
+                (model.name.clone(), age)
+            })
+            .collect()
+    }
+
+    /// A hash of the content of the manifest. Unlike [std::hash::Hash] with a std hasher,
+    /// it's stable across processes, platforms and Rust versions, so it can identify the
+    /// manifest in a persisted cache or correlate the logs. The equal manifests share the hash.
+    pub fn content_hash(&self) -> u64 {
+        // The json of a manifest is canonical, because the manifest has no map field whose
+        // order could vary. The same content is always serialized to the same bytes.
+        let json = serde_json::to_vec(self).expect("a manifest is always serializable");
+        fnv1a(&json)
+    }
+
+    /// The [Manifest::content_hash] in 16 hex digits
+    pub fn content_id(&self) -> String {
+        format!("{:016x}", self.content_hash())
+    }
+
+    /// Render a concise, human-readable outline of the manifest.
+    /// It lists the models with their column and RLAC rule counts, the views, the relationships
+    /// and the metrics.
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        let data_source = self
+            .data_source
+            .map(|ds| ds.to_string())
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let _ = writeln!(
+            summary,
+            "Manifest {}.{} ({})",
+            self.catalog, self.schema, data_source
+        );
+        let _ = writeln!(summary, "Models ({}):", self.models.len());
+        for model in &self.models {
+            let _ = writeln!(
+                summary,
+                "  {}: {} columns, {} RLAC rules",
+                model.name,
+                model.columns.len(),
+                model.row_level_access_controls.len()
+            );
+        }
+        let _ = writeln!(summary, "Views ({}):", self.views.len());
+        for view in &self.views {
+            let _ = writeln!(summary, "  {}", view.name);
+        }
+        let _ = writeln!(summary, "Relationships ({}):", self.relationships.len());
+        for relationship in &self.relationships {
+            let _ = writeln!(
+                summary,
+                "  {}: {} ({})",
+                relationship.name,
+                relationship.models.join(", "),
+                relationship.join_type
+            );
+        }
+        let _ = writeln!(summary, "Metrics ({}):", self.metrics.len());
+        for metric in &self.metrics {
+            let _ = writeln!(
+                summary,
+                "  {}: based on {}",
+                metric.name, metric.base_object
+            );
+        }
+        summary
+    }
+}
+
+/// The 64-bit FNV-1a hash. It has no seed, so the hash of the same bytes never changes.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// The first name that appears twice. The names are case-sensitive.
+fn first_duplicate<'a>(names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let mut seen = HashSet::new();
+    names.find(|name| !seen.insert(*name))
+}
+
 impl Model {
+    /// Reject the columns sharing a name, which make the references to them ambiguous.
+    pub fn validate_columns(&self) -> Result<(), ManifestError> {
+        match first_duplicate(self.columns.iter().map(|column| column.name.as_str())) {
+            Some(name) => Err(ManifestError::DuplicateColumn {
+                model: self.name.clone(),
+                column: name.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
     /// Physical columns are columns that can be selected from the model.
     /// All physical columns are visible columns, but not all visible columns are physical columns
     /// e.g. columns that are not a relationship column
@@ -270,6 +414,12 @@ impl Model {
         &self.name
     }
 
+    /// Return when the data of the model was last refreshed, if it's known
+    pub fn last_refreshed(&self) -> Option<SystemTime> {
+        self.last_refreshed
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+    }
+
     /// Return the iterator of all visible columns
     pub fn get_visible_columns(&self) -> impl Iterator<Item = Arc<Column>> + '_ {
         self.columns.iter().filter(|f| !f.is_hidden).map(Arc::clone)
@@ -282,6 +432,33 @@ impl Model {
             .map(|c| Arc::clone(&c))
     }
 
+    /// Return all the columns of the model, including the hidden ones
+    pub fn columns(&self) -> &[Arc<Column>] {
+        &self.columns
+    }
+
+    /// Get the specified column by name, including the hidden ones
+    pub fn column(&self, column_name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name == column_name)
+            .map(|c| c.as_ref())
+    }
+
+    /// Return the columns of the primary key in its order. A composite primary key lists
+    /// its columns separated by commas, e.g. `l_orderkey, l_linenumber`. It's empty if the
+    /// model has no primary key or a name of the key isn't a column of the model.
+    pub fn primary_keys(&self) -> Vec<&Column> {
+        let Some(primary_key) = self.primary_key() else {
+            return vec![];
+        };
+        primary_key
+            .split(',')
+            .map(|name| self.column(name.trim()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default()
+    }
+
     pub fn get_column(&self, column_name: &str) -> Option<Arc<Column>> {
         self.columns
             .iter()
@@ -302,6 +479,14 @@ impl Model {
     pub fn row_level_access_controls(&self) -> &[Arc<RowLevelAccessControl>] {
         &self.row_level_access_controls
     }
+
+    /// Return the iterator of the columns of the kind, including the hidden ones
+    pub fn columns_of_kind(&self, kind: ColumnKind) -> impl Iterator<Item = Arc<Column>> + '_ {
+        self.columns
+            .iter()
+            .filter(move |c| c.kind() == kind)
+            .map(Arc::clone)
+    }
 }
 
 impl PartialOrd for Model {
@@ -316,7 +501,46 @@ impl Ord for Model {
     }
 }
 
+/// The kind of a column, classified by [Column::kind].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    /// A column of the table of the model, referenced by its name or by the expression
+    /// of its name only
+    Physical,
+    /// A column computed by an expression of the physical columns
+    Expression,
+    /// A column extracting a value of a JSON column by a path, e.g. `payload->>'id'` or
+    /// `json_extract(payload, '$.id')`
+    JsonPath,
+    /// A column replaced by its default value when it's null
+    Defaulted,
+    /// A calculated field, which may reference the columns of the related models
+    Calculated,
+    /// A column of the related model by a relationship
+    Relationship,
+}
+
 impl Column {
+    /// Classify the column. A column matching several kinds is classified by the first
+    /// of relationship, calculated, defaulted, json path and expression, e.g. a calculated
+    /// field with a default value is [ColumnKind::Calculated], and a json path column with
+    /// a default value is [ColumnKind::Defaulted].
+    pub fn kind(&self) -> ColumnKind {
+        if self.relationship.is_some() {
+            ColumnKind::Relationship
+        } else if self.is_calculated {
+            ColumnKind::Calculated
+        } else if self.default_value.is_some() {
+            ColumnKind::Defaulted
+        } else if self.expression().is_some_and(is_json_path_expression) {
+            ColumnKind::JsonPath
+        } else if self.expression.is_some() && self.source_name().is_none() {
+            ColumnKind::Expression
+        } else {
+            ColumnKind::Physical
+        }
+    }
+
     /// Return the name of the column
     pub fn name(&self) -> &str {
         &self.name
@@ -327,6 +551,18 @@ impl Column {
         self.expression.as_deref()
     }
 
+    /// Return the name of the physical column if the expression only references it,
+    /// e.g. the column set by [crate::mdl::builder::ColumnBuilder::source_name].
+    pub fn source_name(&self) -> Option<String> {
+        if self.is_calculated || self.relationship.is_some() {
+            return None;
+        }
+        match parse_identifiers(self.expression()?) {
+            Ok(mut idents) if idents.len() == 1 => idents.pop().map(|ident| ident.value),
+            _ => None,
+        }
+    }
+
     pub fn column_level_access_control(&self) -> Option<Arc<ColumnLevelAccessControl>> {
         if let Some(ref cla) = &self.column_level_access_control {
             Some(Arc::clone(cla))
@@ -356,8 +592,11 @@ impl SessionProperty {
 
 #[cfg(test)]
 mod tests {
-    use crate::mdl::manifest::table_reference;
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::manifest::{table_reference, ColumnKind, Manifest};
     use serde_json::Serializer;
+    use std::fs;
+    use std::path::PathBuf;
 
     #[test]
     fn test_table_reference_serialize() {
@@ -395,4 +634,175 @@ mod tests {
             r#"{"catalog":"Catalog","schema":"Schema","table":"Table"}"#
         );
     }
+
+    #[test]
+    fn test_manifest_summary() {
+        let test_data: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+            .iter()
+            .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path()).unwrap();
+        let manifest = serde_json::from_str::<Manifest>(&mdl_json).unwrap();
+        let summary = manifest.summary();
+        for model in &manifest.models {
+            assert!(
+                summary.contains(model.name()),
+                "missing model {}",
+                model.name()
+            );
+        }
+        for view in &manifest.views {
+            assert!(
+                summary.contains(view.name()),
+                "missing view {}",
+                view.name()
+            );
+        }
+        assert!(summary.contains("Models (3):"));
+        assert!(summary.contains("Views (1):"));
+    }
+
+    #[test]
+    fn test_model_column() {
+        let model = ModelBuilder::new("customer")
+            .column(ColumnBuilder::new("c_custkey", "int").build())
+            .column(ColumnBuilder::new("c_name", "varchar").hidden(true).build())
+            .primary_key("c_custkey")
+            .build();
+        assert_eq!(model.columns().len(), 2);
+        let column = model.column("c_name").unwrap();
+        assert_eq!(column.name(), "c_name");
+        assert_eq!(column.r#type, "varchar");
+        assert!(model.column("c_address").is_none());
+        let primary_keys = model.primary_keys();
+        assert_eq!(primary_keys.len(), 1);
+        assert_eq!(primary_keys[0].name(), "c_custkey");
+
+        let model = ModelBuilder::new("orders")
+            .column(ColumnBuilder::new("o_orderkey", "int").build())
+            .build();
+        assert!(model.primary_keys().is_empty());
+
+        let lineitem = |primary_key: &str| {
+            ModelBuilder::new("lineitem")
+                .column(ColumnBuilder::new("l_orderkey", "int").build())
+                .column(ColumnBuilder::new("l_comment", "varchar").build())
+                .column(ColumnBuilder::new("l_linenumber", "int").build())
+                .primary_key(primary_key)
+                .build()
+        };
+        let model = lineitem("l_orderkey, l_linenumber");
+        let names: Vec<_> = model.primary_keys().iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["l_orderkey", "l_linenumber"]);
+        // a key with a missing column has no columns rather than a part of them
+        assert!(lineitem("l_orderkey, l_shipdate").primary_keys().is_empty());
+    }
+
+    #[test]
+    fn test_content_id() {
+        let manifest = |column_type: &str| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", column_type).build())
+                        .build(),
+                )
+                .build()
+        };
+        let json = serde_json::to_string(&manifest("int")).unwrap();
+        let deserialized: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest("int").content_id(), deserialized.content_id());
+        assert_eq!(manifest("int").content_id().len(), 16);
+        assert_ne!(
+            manifest("int").content_id(),
+            manifest("bigint").content_id()
+        );
+        // the known vector of FNV-1a
+        assert_eq!(super::fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_column_kind() {
+        let model = ModelBuilder::new("orders")
+            .table_reference("orders")
+            .column(ColumnBuilder::new("o_orderkey", "int").build())
+            .column(
+                ColumnBuilder::new("o_totalprice", "double")
+                    .source_name("totalprice")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("o_tax", "double")
+                    .expression("o_totalprice * 0.1")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("o_status", "varchar")
+                    .default_value("'unknown'")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("o_channel", "varchar")
+                    .expression("payload->>'channel'")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("o_coupon", "varchar")
+                    .expression("json_extract(payload, '$.coupon')")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("o_region", "varchar")
+                    .expression("json_extract(payload, '$.region')")
+                    .default_value("'unknown'")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("customer_name", "varchar")
+                    .calculated(true)
+                    .expression("customer.c_name")
+                    .build(),
+            )
+            .column(
+                ColumnBuilder::new("customer", "customer")
+                    .relationship("orders_customer")
+                    .build(),
+            )
+            .build();
+        let kinds: Vec<_> = model
+            .columns()
+            .iter()
+            .map(|column| (column.name(), column.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("o_orderkey", ColumnKind::Physical),
+                ("o_totalprice", ColumnKind::Physical),
+                ("o_tax", ColumnKind::Expression),
+                ("o_status", ColumnKind::Defaulted),
+                ("o_channel", ColumnKind::JsonPath),
+                ("o_coupon", ColumnKind::JsonPath),
+                ("o_region", ColumnKind::Defaulted),
+                ("customer_name", ColumnKind::Calculated),
+                ("customer", ColumnKind::Relationship),
+            ]
+        );
+        let names = |kind| {
+            model
+                .columns_of_kind(kind)
+                .map(|column| column.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(ColumnKind::Physical),
+            vec!["o_orderkey", "o_totalprice"]
+        );
+        assert_eq!(names(ColumnKind::Calculated), vec!["customer_name"]);
+        assert_eq!(names(ColumnKind::Expression), vec!["o_tax"]);
+        assert_eq!(names(ColumnKind::JsonPath), vec!["o_channel", "o_coupon"]);
+        assert_eq!(names(ColumnKind::Defaulted), vec!["o_status", "o_region"]);
+    }
 }
@@ -0,0 +1,487 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::mdl::error::ManifestError;
+use crate::mdl::manifest::{DataSource, Manifest};
+
+/// What [Manifest::merge] does when both manifests define an object of the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Fail the merge
+    #[default]
+    Error,
+    /// Keep the object of the manifest merged into
+    PreferSelf,
+    /// Keep the object of the merged manifest
+    PreferOther,
+}
+
+/// The policy of [Manifest::merge].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergePolicy {
+    /// The resolution of the models, views, metrics, relationships and default properties
+    /// defined by both manifests
+    pub on_conflict: OnConflict,
+}
+
+impl MergePolicy {
+    pub fn new(on_conflict: OnConflict) -> Self {
+        Self { on_conflict }
+    }
+}
+
+/// The errors of [Manifest::merge].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// The manifests have different catalogs or schemas
+    SchemaMismatch {
+        catalog: String,
+        schema: String,
+        other_catalog: String,
+        other_schema: String,
+    },
+    /// The manifests declare different data sources
+    DataSourceMismatch {
+        data_source: DataSource,
+        other: DataSource,
+    },
+    /// Both manifests define a model, view or metric of the name under [OnConflict::Error]
+    ObjectConflict { name: String },
+    /// Both manifests define a relationship of the name under [OnConflict::Error]
+    RelationshipConflict { name: String },
+    /// The manifests have different default values of the property under [OnConflict::Error]
+    PropertyConflict { key: String },
+    /// A relationship of the merged manifest references a model of neither manifest
+    UnknownModel { relationship: String, model: String },
+    /// The merged manifest is invalid
+    Invalid(ManifestError),
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::SchemaMismatch {
+                catalog,
+                schema,
+                other_catalog,
+                other_schema,
+            } => write!(
+                f,
+                "Can't merge the manifest of {other_catalog}.{other_schema} into the manifest of {catalog}.{schema}"
+            ),
+            MergeError::DataSourceMismatch { data_source, other } => write!(
+                f,
+                "Can't merge the manifest of the data source {other} into the manifest of {data_source}"
+            ),
+            MergeError::ObjectConflict { name } => {
+                write!(f, "The model, view or metric {name} is defined by both manifests")
+            }
+            MergeError::RelationshipConflict { name } => {
+                write!(f, "The relationship {name} is defined by both manifests")
+            }
+            MergeError::PropertyConflict { key } => write!(
+                f,
+                "The default property {key} has different values in the manifests"
+            ),
+            MergeError::UnknownModel {
+                relationship,
+                model,
+            } => write!(
+                f,
+                "The relationship {relationship} references the unknown model {model}"
+            ),
+            MergeError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MergeError::Invalid(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ManifestError> for MergeError {
+    fn from(e: ManifestError) -> Self {
+        MergeError::Invalid(e)
+    }
+}
+
+impl Manifest {
+    /// Merge the other manifest into this one, e.g. to assemble the partial manifests of
+    /// several teams.
+    ///
+    /// The manifests should share the catalog and the schema, and the data source if both
+    /// declare one. The models, views and metrics share a namespace, so a model of one
+    /// manifest conflicts with a view of the same name of the other. A conflict is resolved
+    /// by the [MergePolicy]: the losing object is dropped, and the others keep their order,
+    /// the objects of this manifest first. The declared session keys are united.
+    ///
+    /// The relationships may reference the models of either manifest, and they're validated
+    /// after the merge.
+    pub fn merge(self, other: Manifest, policy: MergePolicy) -> Result<Manifest, MergeError> {
+        if self.catalog != other.catalog || self.schema != other.schema {
+            return Err(MergeError::SchemaMismatch {
+                catalog: self.catalog,
+                schema: self.schema,
+                other_catalog: other.catalog,
+                other_schema: other.schema,
+            });
+        }
+        let data_source = match (self.data_source, other.data_source) {
+            (Some(left), Some(right)) if left != right => {
+                return Err(MergeError::DataSourceMismatch {
+                    data_source: left,
+                    other: right,
+                });
+            }
+            (left, right) => left.or(right),
+        };
+
+        let self_names = object_names(&self);
+        let other_names = object_names(&other);
+        let conflicts: BTreeSet<&str> = self_names
+            .intersection(&other_names)
+            .map(String::as_str)
+            .collect();
+        let relationship_conflicts: BTreeSet<&str> = self
+            .relationships
+            .iter()
+            .filter(|left| {
+                other
+                    .relationships
+                    .iter()
+                    .any(|right| right.name == left.name)
+            })
+            .map(|relationship| relationship.name.as_str())
+            .collect();
+        let property_conflicts: BTreeSet<&str> = self
+            .default_properties
+            .iter()
+            .filter(|(key, value)| {
+                other
+                    .default_properties
+                    .get(*key)
+                    .is_some_and(|other_value| other_value != *value)
+            })
+            .map(|(key, _)| key.as_str())
+            .collect();
+        if policy.on_conflict == OnConflict::Error {
+            if let Some(name) = conflicts.first() {
+                return Err(MergeError::ObjectConflict {
+                    name: name.to_string(),
+                });
+            }
+            if let Some(name) = relationship_conflicts.first() {
+                return Err(MergeError::RelationshipConflict {
+                    name: name.to_string(),
+                });
+            }
+            if let Some(key) = property_conflicts.first() {
+                return Err(MergeError::PropertyConflict {
+                    key: key.to_string(),
+                });
+            }
+        }
+        // drop the losing objects of the conflicts
+        let conflicts: HashSet<String> = conflicts.into_iter().map(str::to_string).collect();
+        let relationship_conflicts: HashSet<String> = relationship_conflicts
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let prefer_self = policy.on_conflict != OnConflict::PreferOther;
+
+        let mut merged = self;
+        merged.models = merge_objects(
+            merged.models,
+            other.models,
+            |model| &model.name,
+            prefer_self,
+            &conflicts,
+        );
+        merged.views = merge_objects(
+            merged.views,
+            other.views,
+            |view| &view.name,
+            prefer_self,
+            &conflicts,
+        );
+        merged.metrics = merge_objects(
+            merged.metrics,
+            other.metrics,
+            |metric| &metric.name,
+            prefer_self,
+            &conflicts,
+        );
+        merged.relationships = merge_objects(
+            merged.relationships,
+            other.relationships,
+            |relationship| &relationship.name,
+            prefer_self,
+            &relationship_conflicts,
+        );
+        merged.data_source = data_source;
+        for (key, value) in other.default_properties {
+            if !prefer_self || !merged.default_properties.contains_key(&key) {
+                merged.default_properties.insert(key, value);
+            }
+        }
+        if let Some(other_keys) = other.declared_session_keys {
+            let keys = merged.declared_session_keys.get_or_insert_with(Vec::new);
+            for key in other_keys {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        merged.validate_names()?;
+        for relationship in &merged.relationships {
+            for model in &relationship.models {
+                if !merged.models.iter().any(|m| &m.name == model) {
+                    return Err(MergeError::UnknownModel {
+                        relationship: relationship.name.clone(),
+                        model: model.clone(),
+                    });
+                }
+            }
+        }
+        Ok(merged)
+    }
+}
+
+/// The names of the models, views and metrics of the manifest
+fn object_names(manifest: &Manifest) -> HashSet<String> {
+    manifest
+        .models
+        .iter()
+        .map(|model| model.name.clone())
+        .chain(manifest.views.iter().map(|view| view.name.clone()))
+        .chain(manifest.metrics.iter().map(|metric| metric.name.clone()))
+        .collect()
+}
+
+/// Concatenate the objects of both manifests without the losing objects of the conflicts
+fn merge_objects<T>(
+    left: Vec<Arc<T>>,
+    right: Vec<Arc<T>>,
+    name: impl Fn(&T) -> &String,
+    prefer_self: bool,
+    conflicts: &HashSet<String>,
+) -> Vec<Arc<T>> {
+    let left = left
+        .into_iter()
+        .filter(|object| prefer_self || !conflicts.contains(name(object)));
+    let right = right
+        .into_iter()
+        .filter(|object| !prefer_self || !conflicts.contains(name(object)));
+    left.chain(right).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, RelationshipBuilder, ViewBuilder,
+    };
+    use crate::mdl::error::ManifestError;
+    use crate::mdl::manifest::{DataSource, JoinType, Manifest, Model};
+    use crate::mdl::merge::{MergeError, MergePolicy, OnConflict};
+
+    fn model(name: &str, column: &str) -> Arc<Model> {
+        ModelBuilder::new(name)
+            .table_reference(name)
+            .column(ColumnBuilder::new(column, "int").build())
+            .build()
+    }
+
+    fn names(manifest: &Manifest) -> Vec<&str> {
+        manifest
+            .models
+            .iter()
+            .map(|model| model.name.as_str())
+            .chain(manifest.views.iter().map(|view| view.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let sales = ManifestBuilder::new()
+            .model(model("orders", "o_orderkey"))
+            .declared_session_key("session_user")
+            .build();
+        let crm = ManifestBuilder::new()
+            .model(model("customer", "c_custkey"))
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement("select * from customer")
+                    .build(),
+            )
+            .declared_session_key("session_region")
+            .build();
+        let merged = sales.merge(crm, MergePolicy::default()).unwrap();
+        assert_eq!(names(&merged), vec!["orders", "customer", "customer_view"]);
+        assert_eq!(
+            merged.declared_session_keys(),
+            Some(&["session_user".to_string(), "session_region".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let left = || {
+            ManifestBuilder::new()
+                .model(model("orders", "o_orderkey"))
+                .model(model("customer", "c_custkey"))
+                .build()
+        };
+        // the view conflicts with the model of the same name
+        let right = || {
+            ManifestBuilder::new()
+                .view(
+                    ViewBuilder::new("orders")
+                        .statement("select * from customer")
+                        .build(),
+                )
+                .model(model("lineitem", "l_orderkey"))
+                .build()
+        };
+
+        let error = left()
+            .merge(right(), MergePolicy::new(OnConflict::Error))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            MergeError::ObjectConflict {
+                name: "orders".to_string()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "The model, view or metric orders is defined by both manifests"
+        );
+
+        let merged = left()
+            .merge(right(), MergePolicy::new(OnConflict::PreferSelf))
+            .unwrap();
+        assert_eq!(names(&merged), vec!["orders", "customer", "lineitem"]);
+        assert!(merged.views.is_empty());
+
+        let merged = left()
+            .merge(right(), MergePolicy::new(OnConflict::PreferOther))
+            .unwrap();
+        assert_eq!(names(&merged), vec!["customer", "lineitem", "orders"]);
+        assert_eq!(merged.views[0].statement, "select * from customer");
+    }
+
+    #[test]
+    fn test_merge_mismatch() {
+        let manifest = || ManifestBuilder::new().catalog("wren").schema("public");
+        let error = manifest()
+            .build()
+            .merge(
+                ManifestBuilder::new().catalog("wren").schema("crm").build(),
+                MergePolicy::default(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Can't merge the manifest of wren.crm into the manifest of wren.public"
+        );
+
+        assert_eq!(
+            manifest().data_source(DataSource::Postgres).build().merge(
+                manifest().data_source(DataSource::MySQL).build(),
+                MergePolicy::default()
+            ),
+            Err(MergeError::DataSourceMismatch {
+                data_source: DataSource::Postgres,
+                other: DataSource::MySQL,
+            })
+        );
+
+        // the merged manifest is validated as a whole
+        let duplicate = ModelBuilder::new("orders")
+            .column(ColumnBuilder::new("id", "int").build())
+            .column(ColumnBuilder::new("id", "int").build())
+            .build();
+        let error = manifest()
+            .build()
+            .merge(manifest().model(duplicate).build(), MergePolicy::default())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            MergeError::Invalid(ManifestError::DuplicateColumn {
+                model: "orders".to_string(),
+                column: "id".to_string(),
+            })
+        );
+        assert_eq!(error.to_string(), "Duplicate column id in model orders");
+    }
+
+    #[test]
+    fn test_merge_cross_manifest_relationship() {
+        let orders_customer = RelationshipBuilder::new("orders_customer")
+            .model("orders")
+            .model("customer")
+            .join_type(JoinType::ManyToOne)
+            .condition("orders.o_custkey = customer.c_custkey")
+            .build();
+        let sales = ManifestBuilder::new()
+            .model(model("orders", "o_custkey"))
+            .relationship(Arc::clone(&orders_customer))
+            .build();
+        let crm = ManifestBuilder::new()
+            .model(model("customer", "c_custkey"))
+            .build();
+        let merged = sales.merge(crm, MergePolicy::default()).unwrap();
+        assert_eq!(merged.relationships, vec![orders_customer]);
+
+        // the relationship still references a missing model
+        let sales = ManifestBuilder::new()
+            .model(model("orders", "o_custkey"))
+            .relationship(
+                RelationshipBuilder::new("orders_nation")
+                    .model("orders")
+                    .model("nation")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_nationkey = nation.n_nationkey")
+                    .build(),
+            )
+            .build();
+        let crm = ManifestBuilder::new()
+            .model(model("customer", "c_custkey"))
+            .build();
+        assert_eq!(
+            sales.merge(crm, MergePolicy::default()),
+            Err(MergeError::UnknownModel {
+                relationship: "orders_nation".to_string(),
+                model: "nation".to_string(),
+            })
+        );
+    }
+}
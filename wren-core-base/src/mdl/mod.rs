@@ -17,11 +17,17 @@
  * under the License.
  */
 
+pub mod audit;
 pub mod builder;
 pub mod cls;
+pub mod data_type;
+pub mod error;
 pub mod manifest;
+pub mod merge;
 mod py_method;
 mod utils;
 
 pub use builder::*;
+pub use data_type::*;
+pub use error::*;
 pub use manifest::*;
@@ -19,7 +19,11 @@
 
 use std::borrow::Cow;
 
-use sqlparser::{ast::Ident, dialect::GenericDialect, parser::Parser};
+use sqlparser::{
+    ast::{BinaryOperator, Expr, Ident},
+    dialect::GenericDialect,
+    parser::Parser,
+};
 
 pub(crate) fn parse_identifiers(s: &str) -> Result<Vec<Ident>, sqlparser::parser::ParserError> {
     let dialect = GenericDialect;
@@ -28,6 +32,46 @@ pub(crate) fn parse_identifiers(s: &str) -> Result<Vec<Ident>, sqlparser::parser
     Ok(idents)
 }
 
+/// The functions extracting a value of a JSON column by a path, e.g. `json_extract(payload, '$.id')`
+const JSON_PATH_FUNCTIONS: &[&str] = &[
+    "json_extract",
+    "json_extract_scalar",
+    "json_extract_path",
+    "json_extract_path_text",
+    "json_value",
+    "json_query",
+    "get_json_object",
+];
+
+/// Returns true if the expression extracts a value of a JSON column by a path, e.g.
+/// `payload->>'id'` or `json_extract(payload, '$.id')`
+pub(crate) fn is_json_path_expression(s: &str) -> bool {
+    let dialect = GenericDialect;
+    let Ok(expr) = Parser::new(&dialect)
+        .try_with_sql(s)
+        .and_then(|mut parser| parser.parse_expr())
+    else {
+        return false;
+    };
+    match expr {
+        Expr::JsonAccess { .. } => true,
+        Expr::BinaryOp { op, .. } => matches!(
+            op,
+            BinaryOperator::Arrow
+                | BinaryOperator::LongArrow
+                | BinaryOperator::HashArrow
+                | BinaryOperator::HashLongArrow
+        ),
+        Expr::Function(function) => {
+            let name = function.name.to_string();
+            JSON_PATH_FUNCTIONS
+                .iter()
+                .any(|function| name.eq_ignore_ascii_case(function))
+        }
+        _ => false,
+    }
+}
+
 pub(crate) fn parse_identifiers_normalized(
     s: &str,
     ignore_case: bool,
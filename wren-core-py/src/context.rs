@@ -21,7 +21,7 @@ use crate::remote_functions::PyRemoteFunction;
 use log::debug;
 use pyo3::types::{PyAnyMethods, PyFrozenSet, PyFrozenSetMethods, PyTuple};
 use pyo3::Python;
-use pyo3::{pyclass, pymethods, Py, PyAny, PyErr, PyResult};
+use pyo3::{pyclass, pyfunction, pymethods, Py, PyAny, PyErr, PyResult};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::ops::ControlFlow;
@@ -161,13 +161,13 @@ impl PySessionContext {
             };
             let manifest = to_manifest(mdl_base64)?;
             let properties_ref = Arc::new(properties_map);
-            match AnalyzedWrenMDL::analyze(
+            // the sessions of the same manifest and properties share the analysis
+            match runtime.block_on(mdl::cache::analyze_cached(
                 manifest,
                 Arc::clone(&properties_ref),
                 mdl::context::Mode::Unparse,
-            ) {
+            )) {
                 Ok(analyzed_mdl) => {
-                    let analyzed_mdl = Arc::new(analyzed_mdl);
                     let unparser_ctx = runtime
                         .block_on(apply_wren_on_ctx(
                             &ctx,
@@ -380,3 +380,12 @@ impl From<RemoteFunctionDto> for PyRemoteFunction {
         }
     }
 }
+
+/// Check the analysis pipeline works on a tiny in-memory manifest. It's a cheap liveness
+/// probe without depending on a real manifest.
+#[pyfunction]
+pub fn selftest() -> Result<(), CoreError> {
+    let runtime = Runtime::new().map_err(CoreError::from)?;
+    runtime.block_on(mdl::selftest())?;
+    Ok(())
+}
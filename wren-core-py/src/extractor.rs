@@ -87,6 +87,8 @@ fn extract_manifest(
         metrics: mdl.metrics().to_vec(),
         views: used_views,
         data_source: mdl.data_source(),
+        declared_session_keys: mdl.manifest.declared_session_keys.clone(),
+        default_properties: mdl.manifest.default_properties.clone(),
     })
 }
 
@@ -63,6 +63,7 @@ mod tests {
                     cached: false,
                     refresh_time: None,
                     row_level_access_controls: vec![],
+                    last_refreshed: None,
                 }),
                 Arc::from(Model {
                     name: "model_2".to_string(),
@@ -74,12 +75,15 @@ mod tests {
                     cached: false,
                     refresh_time: None,
                     row_level_access_controls: vec![],
+                    last_refreshed: None,
                 }),
             ],
             relationships: vec![],
             metrics: vec![],
             views: vec![],
             data_source: Some(BigQuery),
+            declared_session_keys: None,
+            default_properties: Default::default(),
         };
         let base64_str = to_json_base64(py_manifest).unwrap();
         let manifest = to_manifest(&base64_str).unwrap();
@@ -11,3 +11,22 @@ pub fn validate_rlac_rule(
     wren_core::logical_plan::analyze::access_control::validate_rlac_rule(rule, model)?;
     Ok(())
 }
+
+/// Validate all the rules of the model. Every failure is reported with its rule name,
+/// one per line, instead of stopping at the first invalid rule.
+#[pyfunction]
+pub fn validate_rlac_rules(
+    rules: Vec<RowLevelAccessControl>,
+    model: &Model,
+) -> Result<(), CoreError> {
+    wren_core::logical_plan::analyze::access_control::validate_rlac_rules(&rules, model)
+        .map_err(|errors| {
+            CoreError::new(
+                &errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        })
+}
@@ -9,9 +9,11 @@ from wren_core import (
     SessionContext,
     SessionProperty,
     is_backward_compatible,
+    selftest,
     to_json_base64,
     to_manifest,
     validate_rlac_rule,
+    validate_rlac_rules,
 )
 
 manifest = {
@@ -115,6 +117,10 @@ manifest = {
 manifest_str = base64.b64encode(json.dumps(manifest).encode("utf-8")).decode("utf-8")
 
 
+def test_selftest():
+    selftest()
+
+
 def test_session_context():
     session_context = SessionContext(manifest_str, None)
     sql = "SELECT * FROM my_catalog.my_schema.customer"
@@ -366,6 +372,37 @@ def test_validate_rlac_rule():
         )
 
 
+def test_validate_rlac_rules():
+    manifest = to_manifest(manifest_str)
+    model = manifest.get_model("customer")
+    if model is None:
+        raise ValueError("Model customer not found in manifest")
+    valid = RowLevelAccessControl(
+        name="valid",
+        required_properties=[SessionProperty(name="session_user", required=False)],
+        condition="c_name = @session_user",
+    )
+    unknown_column = RowLevelAccessControl(
+        name="unknown_column",
+        required_properties=[SessionProperty(name="session_user", required=False)],
+        condition="c_unknown = @session_user",
+    )
+    missing_property = RowLevelAccessControl(
+        name="missing_property",
+        required_properties=[],
+        condition="c_name = @session_user",
+    )
+
+    validate_rlac_rules([valid], model)
+
+    with pytest.raises(Exception) as e:
+        validate_rlac_rules([unknown_column, valid, missing_property], model)
+    errors = str(e.value).splitlines()
+    assert len(errors) == 2
+    assert errors[0].startswith("unknown_column: ")
+    assert errors[1].startswith("missing_property: ")
+
+
 def test_clac():
     headers = {
         "session_level": "2",
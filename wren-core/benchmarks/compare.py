@@ -20,8 +20,13 @@
 from __future__ import annotations
 
 import json
-from dataclasses import dataclass
-from typing import Dict, List, Any
+import math
+import re
+import subprocess
+import sys
+from dataclasses import asdict, dataclass, field
+from enum import Enum
+from typing import Dict, Iterator, List, Any, Optional, Tuple, Union
 from pathlib import Path
 from argparse import ArgumentParser
 
@@ -33,28 +38,84 @@ except ImportError:
     raise
 
 
+# The schema version written by the runner. Files without a `schema_version`
+# field predate versioning and are treated as version 0, which has the same shape.
+# Version 2 adds the optional `tags` of a query.
+# Version 3 adds the optional `instructions` of an iteration.
+# Version 4 adds the `suite_run` of an iteration, its repetition of the suite by `--repeat-suite`.
+CURRENT_SCHEMA_VERSION = 4
+SUPPORTED_SCHEMA_VERSIONS = (0, 1, 2, 3, 4)
+
+# The measures that can be compared. `instructions` needs a run with `--counter instructions`.
+COUNTERS = ("time", "instructions")
+
+
+class BenchmarkLoadError(Exception):
+    """Raised when a benchmark result file can't be loaded."""
+
+
+def field_of(data: Any, key: str, path: str, expected: Any, kind: str) -> Any:
+    """Read `data[key]`, naming `path.key` in the error if it's missing or of the wrong type."""
+    value = data.get(key) if isinstance(data, dict) else None
+    # bool is a subclass of int, but it's never a valid number here
+    if not isinstance(value, expected) or isinstance(value, bool):
+        raise BenchmarkLoadError(f"{path}{key} missing or not {kind}")
+    return value
+
+
 @dataclass
 class QueryResult:
     elapsed: float
+    instructions: Optional[int] = None
+    # The repetition of the suite, always 0 before version 4
+    suite_run: int = 0
 
     @classmethod
-    def load_from(cls, data: Dict[str, Any]) -> QueryResult:
-        return cls(elapsed=data["elapsed"])
+    def load_from(cls, data: Dict[str, Any], path: str = "") -> QueryResult:
+        instructions = None
+        if "instructions" in data:
+            instructions = field_of(data, "instructions", path, int, "an integer")
+        suite_run = 0
+        if "suite_run" in data:
+            suite_run = field_of(data, "suite_run", path, int, "an integer")
+        return cls(
+            elapsed=field_of(data, "elapsed", path, (int, float), "a number"),
+            instructions=instructions,
+            suite_run=suite_run,
+        )
 
 
 @dataclass
 class QueryRun:
-    query: int
+    query: Union[int, str]
     iterations: List[QueryResult]
     start_time: int
+    tags: List[str] = field(default_factory=list)
 
     @classmethod
-    def load_from(cls, data: Dict[str, Any]) -> QueryRun:
-        return cls(
-            query=data["query"],
-            iterations=[QueryResult(**iteration) for iteration in data["iterations"]],
-            start_time=data["start_time"],
+    def load_from(cls, data: Dict[str, Any], path: str = "") -> QueryRun:
+        iterations = field_of(data, "iterations", path, list, "a list")
+        if not iterations:
+            raise BenchmarkLoadError(f"{path}iterations is empty")
+        tags = data.get("tags", [])
+        if not isinstance(tags, list) or not all(isinstance(tag, str) for tag in tags):
+            raise BenchmarkLoadError(f"{path}tags not a list of strings")
+        run = cls(
+            query=field_of(data, "query", path, (int, str), "a query id"),
+            iterations=[
+                QueryResult.load_from(iteration, f"{path}iterations[{i}].")
+                for i, iteration in enumerate(iterations)
+            ],
+            start_time=field_of(data, "start_time", path, int, "an integer"),
+            tags=tags,
         )
+        if not any(math.isfinite(iteration.elapsed) for iteration in run.iterations):
+            raise BenchmarkLoadError(f"{path}iterations has no finite elapsed time")
+        return run
+
+    @property
+    def metrics(self) -> QueryMetrics:
+        return QueryMetrics.from_iterations([iteration.elapsed for iteration in self.iterations])
 
     @property
     def execution_time(self) -> float:
@@ -62,7 +123,107 @@ class QueryRun:
 
         # Use minimum execution time to account for variations / other
         # things the system was doing
-        return min(iteration.elapsed for iteration in self.iterations)
+        return self.metrics.min
+
+    @property
+    def instruction_count(self) -> Optional[int]:
+        """The minimum instructions of the iterations, or None if they weren't counted."""
+        counts = [iteration.instructions for iteration in self.iterations]
+        if any(count is None for count in counts):
+            return None
+        return min(counts)
+
+    def measure(self, counter: str, path: Path) -> float:
+        """The value compared by the counter, the execution time or the instructions."""
+        if counter == "time":
+            return self.execution_time
+        count = self.instruction_count
+        if count is None:
+            raise BenchmarkLoadError(
+                f"{path}: query {self.query} has no instruction count, "
+                "run the benchmark with `--counter instructions`"
+            )
+        return float(count)
+
+
+# The milliseconds from which `format_ms` switches to the scientific notation
+SCIENTIFIC_THRESHOLD_MS = 1e6
+
+
+def format_ms(value: float) -> str:
+    """Format the milliseconds like `format_ms` of the Rust benchmarks, e.g. `12.35`.
+
+    The values below 1 ms keep 3 significant digits, e.g. `0.003`, and the values from
+    `SCIENTIFIC_THRESHOLD_MS` are in the scientific notation, e.g. `1.23e7`. A value
+    rounded to zero is `0.00`, never `-0.00`.
+    """
+    if math.isnan(value):
+        return "NaN"
+    if math.isinf(value):
+        return str(value)
+    magnitude = abs(value)
+    if magnitude >= SCIENTIFIC_THRESHOLD_MS:
+        mantissa, exponent = f"{value:.2e}".split("e")
+        formatted = f"{mantissa}e{int(exponent)}"
+    elif 0 < magnitude < 1:
+        decimals = min(math.floor(-math.log10(magnitude)) + 3, 6)
+        formatted = f"{value:.{decimals}f}"
+        # drop the trailing zeros but keep 2 decimals, e.g. 0.00300 to 0.003
+        while len(formatted) - formatted.index(".") > 3 and formatted.endswith("0"):
+            formatted = formatted[:-1]
+    else:
+        formatted = f"{value:.2f}"
+    if formatted.startswith("-") and set(formatted[1:]) <= {"0", "."}:
+        return formatted[1:]
+    return formatted
+
+
+def format_measure(value: float, counter: str) -> str:
+    if counter == "time":
+        return f"{format_ms(value)}ms"
+    return f"{value:,.0f}"
+
+
+@dataclass
+class QueryMetrics:
+    """The statistics of the elapsed times (ms) of a query's iterations.
+
+    A non-finite time, e.g. NaN from a clock glitch, can't be ordered, so it's dropped and
+    counted by `dropped` instead of skewing the statistics.
+    """
+
+    iterations: int
+    min: float
+    max: float
+    mean: float
+    median: float
+    dropped: int = 0
+
+    @classmethod
+    def from_iterations(cls, elapsed: List[float]) -> QueryMetrics:
+        if not elapsed:
+            raise ValueError("at least one iteration is required")
+        finite = [value for value in elapsed if math.isfinite(value)]
+        if not finite:
+            raise ValueError("at least one iteration with a finite time is required")
+        ordered = sorted(finite)
+        middle = len(ordered) // 2
+        median = (
+            ordered[middle]
+            if len(ordered) % 2 == 1
+            else (ordered[middle - 1] + ordered[middle]) / 2
+        )
+        return cls(
+            iterations=len(ordered),
+            min=ordered[0],
+            max=ordered[-1],
+            mean=sum(ordered) / len(ordered),
+            median=median,
+            dropped=len(elapsed) - len(finite),
+        )
+
+    def to_dict(self) -> Dict[str, Any]:
+        return asdict(self)
 
 
 @dataclass
@@ -73,12 +234,12 @@ class Context:
     arguments: List[str]
 
     @classmethod
-    def load_from(cls, data: Dict[str, Any]) -> Context:
+    def load_from(cls, data: Dict[str, Any], path: str = "context.") -> Context:
         return cls(
-            benchmark_version=data["benchmark_version"],
-            num_cpus=data["num_cpus"],
-            start_time=data["start_time"],
-            arguments=data["arguments"],
+            benchmark_version=field_of(data, "benchmark_version", path, str, "a string"),
+            num_cpus=field_of(data, "num_cpus", path, int, "an integer"),
+            start_time=field_of(data, "start_time", path, int, "an integer"),
+            arguments=field_of(data, "arguments", path, list, "a list"),
         )
 
 
@@ -89,96 +250,536 @@ class BenchmarkRun:
 
     @classmethod
     def load_from(cls, data: Dict[str, Any]) -> BenchmarkRun:
+        if not isinstance(data, dict):
+            raise BenchmarkLoadError("the benchmark result should be a json object")
+        version = data.get("schema_version", 0)
+        if version not in SUPPORTED_SCHEMA_VERSIONS:
+            raise BenchmarkLoadError(f"unsupported benchmark schema v{version}")
         return cls(
-            context=Context.load_from(data["context"]),
-            queries=[QueryRun.load_from(result) for result in data["queries"]],
+            context=Context.load_from(data.get("context")),
+            queries=[
+                QueryRun.load_from(result, f"query[{i}].")
+                for i, result in enumerate(field_of(data, "queries", "", list, "a list"))
+            ],
         )
 
+    def with_tag(self, tag: Optional[str]) -> BenchmarkRun:
+        """Keep only the queries with the tag. All the queries are kept if the tag is None."""
+        if tag is None:
+            return self
+        return BenchmarkRun(
+            context=self.context,
+            queries=[query for query in self.queries if tag in query.tags],
+        )
+
+    @classmethod
+    def load_from_jsonl(cls, lines: List[str], path: Path) -> BenchmarkRun:
+        """Load the JSON lines written by `--jsonl`. The first line has the schema version and
+        the context, and each following line is a query of a repetition of the suite. The lines
+        of the same query are merged into one with the iterations of all the repetitions. The
+        last line of a crashed run may be cut in the middle, so it's dropped with a warning."""
+        lines = [line for line in lines if line.strip()]
+        if not lines:
+            raise BenchmarkLoadError("the json lines file is empty")
+        data = json.loads(lines[0])
+        if not isinstance(data, dict):
+            raise BenchmarkLoadError("the first json line should be a json object")
+        queries: Dict[Any, Dict[str, Any]] = {}
+        for i, line in enumerate(lines[1:]):
+            try:
+                query = json.loads(line)
+            except json.JSONDecodeError:
+                if i != len(lines) - 2:
+                    raise
+                print(f"warning: {path}: dropped the incomplete last line", file=sys.stderr)
+                continue
+            # the malformed lines are kept as they are, so `load_from` reports them
+            name = query.get("query") if isinstance(query, dict) else None
+            merged = queries.get(name) if isinstance(name, str) else None
+            if merged is not None and isinstance(merged.get("iterations"), list):
+                merged["iterations"] = merged["iterations"] + query.get("iterations", [])
+            else:
+                queries[name if isinstance(name, str) else i] = query
+        return cls.load_from({**data, "queries": list(queries.values())})
+
     @classmethod
     def load_from_file(cls, path: Path) -> BenchmarkRun:
-        with open(path, "r") as f:
-            return cls.load_from(json.load(f))
+        if path.is_dir():
+            return cls.load_from_dir(path)
+        try:
+            with open(path, "r") as f:
+                if path.suffix == ".jsonl":
+                    return cls.load_from_jsonl(f.readlines(), path)
+                return cls.load_from(json.load(f))
+        except (OSError, json.JSONDecodeError) as e:
+            raise BenchmarkLoadError(f"{path}: {e}") from e
+        except BenchmarkLoadError as e:
+            raise BenchmarkLoadError(f"{path}: {e}") from e
 
+    @classmethod
+    def load_from_dir(cls, path: Path) -> BenchmarkRun:
+        """Load the files of a directory written by `--output-dir`, one file per query.
+        The context is the one of the first file."""
+        runs = [cls.load_from_file(file) for file in sorted(path.glob("*.json"))]
+        if not runs:
+            raise BenchmarkLoadError(f"{path}: no json file in the directory")
+        return cls(
+            context=runs[0].context,
+            queries=[query for run in runs for query in run.queries],
+        )
 
-def compare(
-    baseline_path: Path,
+    @classmethod
+    def load_from_git(cls, path: Path, rev: str) -> BenchmarkRun:
+        """Load the file as it was committed at the git revision instead of the working tree."""
+        # `./` makes git resolve the path relative to the directory it runs in
+        try:
+            result = subprocess.run(
+                ["git", "-C", str(path.parent), "show", f"{rev}:./{path.name}"],
+                capture_output=True,
+                text=True,
+                check=True,
+            )
+        except FileNotFoundError as e:
+            raise BenchmarkLoadError(f"{path}: git is not available to read {rev}") from e
+        except subprocess.CalledProcessError as e:
+            raise BenchmarkLoadError(
+                f"{path}: not available at {rev}: {e.stderr.strip()}"
+            ) from e
+        try:
+            return cls.load_from(json.loads(result.stdout))
+        except json.JSONDecodeError as e:
+            raise BenchmarkLoadError(f"{path}@{rev}: {e}") from e
+        except BenchmarkLoadError as e:
+            raise BenchmarkLoadError(f"{path}@{rev}: {e}") from e
+
+
+def within_noise(
+    change: float, noise_threshold: float, difference: float = 0.0, noise_floor: float = 0.0
+) -> bool:
+    """Whether the change is noise: the ratio is within +/- `noise_threshold` of 1, or the
+    absolute difference of the measures is below `noise_floor`, e.g. 0.05ms of a 2ms query."""
+    if abs(difference) < noise_floor:
+        return True
+    return (1.0 - noise_threshold) <= change <= (1.0 + noise_threshold)
+
+
+def reported_noise_threshold(noise_threshold: float, tolerance: Optional[float]) -> float:
+    """The threshold the report classifies the changes by. The `--tolerance` in percent,
+    e.g. 10 for +/- 10%, takes precedence over `--noise-threshold` whether it's wider or
+    narrower, and `--noise-threshold` classifies only the raw change shown by `--verbose`."""
+    if tolerance is None:
+        return noise_threshold
+    return tolerance / 100
+
+
+class ChangeStatus(Enum):
+    """The status of a query in the comparison. The value is the one written to the reports."""
+
+    FASTER = "faster"
+    SLOWER = "slower"
+    NO_CHANGE = "no change"
+
+    def __str__(self) -> str:
+        return self.value
+
+    @classmethod
+    def from_str(cls, name: str) -> ChangeStatus:
+        """Parse the status from its value or its name case-insensitively, e.g. `no change`,
+        `no_change` or `NO_CHANGE`, to read back a written report."""
+        normalized = name.strip().lower().replace("_", " ")
+        for status in cls:
+            if status.value == normalized:
+                return status
+        raise ValueError(
+            f"unknown change status {name!r}, expected one of "
+            + ", ".join(status.value for status in cls)
+        )
+
+    @classmethod
+    def of(
+        cls,
+        change: float,
+        noise_threshold: float,
+        difference: float = 0.0,
+        noise_floor: float = 0.0,
+    ) -> ChangeStatus:
+        if within_noise(change, noise_threshold, difference, noise_floor):
+            return cls.NO_CHANGE
+        return cls.FASTER if change < 1.0 else cls.SLOWER
+
+    def text_of(self, change: float) -> str:
+        """The change as shown in the report, e.g. `+2.00x faster` or `1.10x slower`."""
+        if self is ChangeStatus.NO_CHANGE:
+            return "no change"
+        elif self is ChangeStatus.FASTER:
+            return f"+{(1 / change):.2f}x faster"
+        else:
+            return f"{change:.2f}x slower"
+
+
+def query_order(query: Union[int, str]) -> Tuple[Tuple[int, Union[int, str]], ...]:
+    """The sort key of a query id. The numbers in the id are compared by value, so
+    `Query 2` comes before `Query 10`, and the int and str ids can be sorted together."""
+    return tuple(
+        (0, int(part)) if part.isdecimal() else (1, part)
+        for part in re.split(r"(\d+)", str(query))
+    )
+
+
+def paired_queries(
+    baselines: List[BenchmarkRun],
+    baseline_paths: List[Path],
+    comparison: BenchmarkRun,
     comparison_path: Path,
-    noise_threshold: float,
-) -> None:
-    baseline = BenchmarkRun.load_from_file(baseline_path)
-    comparison = BenchmarkRun.load_from_file(comparison_path)
+) -> Iterator[Tuple[Tuple[QueryRun, ...], QueryRun]]:
+    """Pair the results of the same query in the runs, ordered by the query id. A query
+    missing from any run is warned about and left out of the comparison."""
+    paths = [*baseline_paths, comparison_path]
+    runs = [{query.query: query for query in run.queries} for run in [*baselines, comparison]]
+    all_ids = set().union(*runs)
+    for path, queries in zip(paths, runs):
+        missing = sorted(all_ids - queries.keys(), key=query_order)
+        if missing:
+            print(
+                f"warning: {path}: no result of query {', '.join(map(str, missing))}, "
+                "left out of the comparison",
+                file=sys.stderr,
+            )
+    # order the rows by the query id, so the report is stable whatever order the runs recorded
+    paired_ids = all_ids.intersection(*runs)
+    for query in sorted(paired_ids, key=query_order):
+        yield tuple(queries[query] for queries in runs[:-1]), runs[-1][query]
+
+
+def header_of(path: Path) -> str:
+    """The name of the directory of a result file, or the directory of the per-query files."""
+    return path.stem if path.is_dir() else path.parent.stem
+
+
+def warn_dropped_iterations(run: BenchmarkRun, path: Path) -> None:
+    for query in run.queries:
+        dropped = query.metrics.dropped
+        if dropped:
+            print(
+                f"warning: {path}: dropped {dropped} non-finite iteration(s) of query {query.query}",
+                file=sys.stderr,
+            )
+
+
+@dataclass
+class BaselineChange:
+    """The change of a query from one of the baselines."""
 
+    measure: float
+    metrics: QueryMetrics
+    change: float
+    difference: float
+    status: ChangeStatus
+    # the status by `--noise-threshold` instead of `--tolerance`, shown by `--verbose` if it differs
+    raw_status: ChangeStatus
+
+    def text(self, verbose: bool = False) -> str:
+        text = self.status.text_of(self.change)
+        if verbose and self.raw_status != self.status:
+            return f"{text} ({self.raw_status.text_of(self.change)})"
+        return text
+
+
+@dataclass
+class QueryComparison:
+    """A query of the comparison run with its changes from each of the baselines."""
+
+    query: Any
+    measure: float
+    metrics: QueryMetrics
+    baselines: List[BaselineChange]
+
+    @property
+    def changed(self) -> bool:
+        return any(baseline.status != ChangeStatus.NO_CHANGE for baseline in self.baselines)
+
+
+@dataclass
+class BaselineSummary:
+    """The summary of the paired queries of a baseline."""
+
+    header: str
+    total: float
+    average: float
+    faster: int
+    slower: int
+    no_change: int
+
+
+@dataclass
+class BenchmarkComparison:
+    """The comparison of a run against the baselines as plain data, e.g. for a notebook.
+
+    `compare` prints the report of this data, so the numbers are the same. The totals and
+    the averages cover only the queries paired with the comparison run.
+    """
+
+    comparison: str
+    counter: str
+    tolerance: Optional[float]
+    queries: List[QueryComparison]
+    baselines: List[BaselineSummary]
+    total: float
+    average: float
+
+    @classmethod
+    def compare(
+        cls,
+        baseline_paths: Union[Path, str, List[Path]],
+        comparison_path: Union[Path, str],
+        noise_threshold: float = 0.05,
+        baseline_ref: Optional[str] = None,
+        filter_tag: Optional[str] = None,
+        counter: str = "time",
+        noise_floor_ms: float = 0.0,
+        tolerance: Optional[float] = None,
+    ) -> BenchmarkComparison:
+        """Load and compare the result files. A single baseline path is the same as a list
+        of it, and multiple baselines produce an N-way comparison."""
+        if isinstance(baseline_paths, (Path, str)):
+            baseline_paths = [baseline_paths]
+        baseline_paths = [Path(path) for path in baseline_paths]
+        comparison_path = Path(comparison_path)
+
+        if baseline_ref is None:
+            baselines = [BenchmarkRun.load_from_file(path) for path in baseline_paths]
+        else:
+            baselines = [BenchmarkRun.load_from_git(path, baseline_ref) for path in baseline_paths]
+        # the summary covers only the filtered queries
+        baselines = [baseline.with_tag(filter_tag) for baseline in baselines]
+        comparison = BenchmarkRun.load_from_file(comparison_path).with_tag(filter_tag)
+        if not comparison.queries:
+            raise BenchmarkLoadError(f"{comparison_path}: no query is tagged {filter_tag}")
+
+        for path, run in [*zip(baseline_paths, baselines), (comparison_path, comparison)]:
+            warn_dropped_iterations(run, path)
+
+        # use basename as the column names
+        baseline_headers = [
+            header_of(path) if baseline_ref is None else f"{header_of(path)}@{baseline_ref}"
+            for path in baseline_paths
+        ]
+        return cls.of_runs(
+            baselines,
+            baseline_paths,
+            baseline_headers,
+            comparison,
+            comparison_path,
+            header_of(comparison_path),
+            noise_threshold,
+            counter,
+            noise_floor_ms,
+            tolerance,
+        )
+
+    @classmethod
+    def of_runs(
+        cls,
+        baselines: List[BenchmarkRun],
+        baseline_paths: List[Path],
+        baseline_headers: List[str],
+        comparison: BenchmarkRun,
+        comparison_path: Path,
+        comparison_header: str,
+        noise_threshold: float,
+        counter: str = "time",
+        noise_floor_ms: float = 0.0,
+        tolerance: Optional[float] = None,
+    ) -> BenchmarkComparison:
+        # the floor is in milliseconds, so it doesn't apply to the instruction counts
+        noise_floor = noise_floor_ms if counter == "time" else 0.0
+        reported_threshold = reported_noise_threshold(noise_threshold, tolerance)
+
+        queries = []
+        for baseline_results, comparison_result in paired_queries(
+            baselines, baseline_paths, comparison, comparison_path
+        ):
+            comparison_measure = comparison_result.measure(counter, comparison_path)
+            changes = []
+            for path, result in zip(baseline_paths, baseline_results):
+                baseline_measure = result.measure(counter, path)
+                change = comparison_measure / baseline_measure
+                difference = comparison_measure - baseline_measure
+                changes.append(
+                    BaselineChange(
+                        measure=baseline_measure,
+                        metrics=result.metrics,
+                        change=change,
+                        difference=difference,
+                        status=ChangeStatus.of(
+                            change, reported_threshold, difference, noise_floor
+                        ),
+                        raw_status=ChangeStatus.of(
+                            change, noise_threshold, difference, noise_floor
+                        ),
+                    )
+                )
+            queries.append(
+                QueryComparison(
+                    query=comparison_result.query,
+                    measure=comparison_measure,
+                    metrics=comparison_result.metrics,
+                    baselines=changes,
+                )
+            )
+
+        def average_of(total: float) -> float:
+            return total / len(queries) if queries else 0.0
+
+        summaries = []
+        for i, header in enumerate(baseline_headers):
+            statuses = [query.baselines[i].status for query in queries]
+            total = sum(query.baselines[i].measure for query in queries)
+            summaries.append(
+                BaselineSummary(
+                    header=header,
+                    total=total,
+                    average=average_of(total),
+                    faster=statuses.count(ChangeStatus.FASTER),
+                    slower=statuses.count(ChangeStatus.SLOWER),
+                    no_change=statuses.count(ChangeStatus.NO_CHANGE),
+                )
+            )
+        total = sum(query.measure for query in queries)
+        return cls(
+            comparison=comparison_header,
+            counter=counter,
+            tolerance=tolerance,
+            queries=queries,
+            baselines=summaries,
+            total=total,
+            average=average_of(total),
+        )
+
+    def to_dict(self) -> Dict[str, Any]:
+        def dict_of(items: List[Tuple[str, Any]]) -> Dict[str, Any]:
+            return {
+                key: str(value) if isinstance(value, ChangeStatus) else value
+                for key, value in items
+            }
+
+        return asdict(self, dict_factory=dict_of)
+
+
+def print_report(
+    result: BenchmarkComparison, changed_only: bool = False, verbose: bool = False
+) -> None:
+    counter = result.counter
     console = Console()
 
-    # use basename as the column names
-    baseline_header = baseline_path.parent.stem
-    comparison_header = comparison_path.parent.stem
+    baseline_headers = [baseline.header for baseline in result.baselines]
+    # the change column is named by the baseline only if there are multiple baselines
+    change_headers = (
+        ["Change"]
+        if len(baseline_headers) == 1
+        else [f"Change ({header})" for header in baseline_headers]
+    )
 
     table = Table(show_header=True, header_style="bold magenta")
     table.add_column("Query", style="dim", width=12)
-    table.add_column(baseline_header, justify="right", style="dim")
-    table.add_column(comparison_header, justify="right", style="dim")
-    table.add_column("Change", justify="right", style="dim")
-
-    faster_count = 0
-    slower_count = 0
-    no_change_count = 0
-    total_baseline_time = 0
-    total_comparison_time = 0
-
-    for baseline_result, comparison_result in zip(baseline.queries, comparison.queries):
-        assert baseline_result.query == comparison_result.query
-
-        total_baseline_time += baseline_result.execution_time
-        total_comparison_time += comparison_result.execution_time
-
-        change = comparison_result.execution_time / baseline_result.execution_time
-
-        if (1.0 - noise_threshold) <= change <= (1.0 + noise_threshold):
-            change_text = "no change"
-            no_change_count += 1
-        elif change < 1.0:
-            change_text = f"+{(1 / change):.2f}x faster"
-            faster_count += 1
-        else:
-            change_text = f"{change:.2f}x slower"
-            slower_count += 1
+    for baseline_header in baseline_headers:
+        table.add_column(baseline_header, justify="right", style="dim")
+    table.add_column(result.comparison, justify="right", style="dim")
+    for change_header in change_headers:
+        table.add_column(change_header, justify="right", style="dim")
+
+    hidden_count = 0
+    for query in result.queries:
+        if changed_only and not query.changed:
+            # keep the summary counts complete but omit the row from the detailed table
+            hidden_count += 1
+            continue
 
         table.add_row(
-            f"Q{baseline_result.query}",
-            f"{baseline_result.execution_time:.2f}ms",
-            f"{comparison_result.execution_time:.2f}ms",
-            change_text,
+            f"Q{query.query}",
+            *[format_measure(baseline.measure, counter) for baseline in query.baselines],
+            format_measure(query.measure, counter),
+            *[baseline.text(verbose) for baseline in query.baselines],
         )
 
-    console.print(table)
+    if changed_only:
+        table.title = f"{hidden_count} unchanged queries hidden"
 
-    # Calculate averages
-    avg_baseline_time = total_baseline_time / len(baseline.queries)
-    avg_comparison_time = total_comparison_time / len(comparison.queries)
+    console.print(table)
 
     # Summary table
     summary_table = Table(show_header=True, header_style="bold magenta")
     summary_table.add_column("Benchmark Summary", justify="left", style="dim")
     summary_table.add_column("", justify="right", style="dim")
 
-    summary_table.add_row(f"Total Time ({baseline_header})", f"{total_baseline_time:.2f}ms")
-    summary_table.add_row(f"Total Time ({comparison_header})", f"{total_comparison_time:.2f}ms")
-    summary_table.add_row(f"Average Time ({baseline_header})", f"{avg_baseline_time:.2f}ms")
-    summary_table.add_row(f"Average Time ({comparison_header})", f"{avg_comparison_time:.2f}ms")
-    summary_table.add_row("Queries Faster", str(faster_count))
-    summary_table.add_row("Queries Slower", str(slower_count))
-    summary_table.add_row("Queries with No Change", str(no_change_count))
+    measure_name = "Time" if counter == "time" else "Instructions"
+    for baseline in result.baselines:
+        summary_table.add_row(
+            f"Total {measure_name} ({baseline.header})",
+            format_measure(baseline.total, counter),
+        )
+        summary_table.add_row(
+            f"Average {measure_name} ({baseline.header})",
+            format_measure(baseline.average, counter),
+        )
+    summary_table.add_row(
+        f"Total {measure_name} ({result.comparison})",
+        format_measure(result.total, counter),
+    )
+    summary_table.add_row(
+        f"Average {measure_name} ({result.comparison})",
+        format_measure(result.average, counter),
+    )
+    if result.tolerance is not None:
+        summary_table.add_row("Tolerance", f"+/- {result.tolerance:g}%")
+    for baseline in result.baselines:
+        suffix = "" if len(result.baselines) == 1 else f" (vs {baseline.header})"
+        summary_table.add_row(f"Queries Faster{suffix}", str(baseline.faster))
+        summary_table.add_row(f"Queries Slower{suffix}", str(baseline.slower))
+        summary_table.add_row(f"Queries with No Change{suffix}", str(baseline.no_change))
 
     console.print(summary_table)
 
+
+def compare(
+    baseline_paths: List[Path],
+    comparison_path: Path,
+    noise_threshold: float,
+    changed_only: bool = False,
+    baseline_ref: Optional[str] = None,
+    filter_tag: Optional[str] = None,
+    counter: str = "time",
+    noise_floor_ms: float = 0.0,
+    tolerance: Optional[float] = None,
+    verbose: bool = False,
+) -> None:
+    """Print the comparison of the runs against the baselines.
+
+    A `tolerance` in percent replaces the `noise_threshold` in the report and its summary,
+    so the sub-tolerance wiggle doesn't dominate the verdict. With `verbose`, a change
+    classified differently by the tolerance still shows its raw change by the threshold.
+    """
+    result = BenchmarkComparison.compare(
+        baseline_paths,
+        comparison_path,
+        noise_threshold,
+        baseline_ref,
+        filter_tag,
+        counter,
+        noise_floor_ms,
+        tolerance,
+    )
+    print_report(result, changed_only, verbose)
+
+
 def main() -> None:
     parser = ArgumentParser()
     compare_parser = parser
     compare_parser.add_argument(
-        "baseline_path",
+        "baseline_paths",
         type=Path,
-        help="Path to the baseline summary file.",
+        nargs="+",
+        help="Paths to the baseline summary files. Multiple baselines produce an N-way report.",
     )
     compare_parser.add_argument(
         "comparison_path",
@@ -191,10 +792,70 @@ def main() -> None:
         default=0.05,
         help="The threshold for statistically insignificant results (+/- %5).",
     )
+    compare_parser.add_argument(
+        "--noise-floor-ms",
+        type=float,
+        default=0.0,
+        help="The changes of the time smaller than these milliseconds are no change, whatever the percent.",
+    )
+    compare_parser.add_argument(
+        "--tolerance",
+        type=float,
+        default=None,
+        metavar="PCT",
+        help="Report the changes within +/- PCT percent as no change, and count them so in the summary. "
+        "It takes precedence over --noise-threshold, which then only classifies the raw change of --verbose.",
+    )
+    compare_parser.add_argument(
+        "-v",
+        "--verbose",
+        action="store_true",
+        help="Show the raw change by --noise-threshold of the queries --tolerance classifies differently.",
+    )
+    compare_parser.add_argument(
+        "--changed-only",
+        action="store_true",
+        help="Omit the queries with no change from the detailed table.",
+    )
+    compare_parser.add_argument(
+        "--baseline-ref",
+        type=str,
+        default=None,
+        help="Read the baseline files as committed at this git revision instead of the working tree.",
+    )
+    compare_parser.add_argument(
+        "--filter-tag",
+        type=str,
+        default=None,
+        help="Compare only the queries with this tag.",
+    )
+    compare_parser.add_argument(
+        "--counter",
+        choices=COUNTERS,
+        default="time",
+        help="The measure to compare. `instructions` is less noisy than the time on shared runners.",
+    )
 
     options = parser.parse_args()
-
-    compare(options.baseline_path, options.comparison_path, options.noise_threshold)
+    if options.tolerance is not None and options.tolerance < 0:
+        parser.error("--tolerance should be a non-negative percent")
+
+    try:
+        compare(
+            options.baseline_paths,
+            options.comparison_path,
+            options.noise_threshold,
+            options.changed_only,
+            options.baseline_ref,
+            options.filter_tag,
+            options.counter,
+            options.noise_floor_ms,
+            options.tolerance,
+            options.verbose,
+        )
+    except BenchmarkLoadError as e:
+        print(f"error: {e}", file=sys.stderr)
+        sys.exit(1)
 
 
 
@@ -1,3 +1,4 @@
+-- tags: cte, subquery
 WITH
     "filtered_accounts" AS (
         SELECT
@@ -1,3 +1,4 @@
+-- tags: cte, subquery, union
 WITH
     "filtered_accounts" AS (
         SELECT
@@ -1,17 +1,27 @@
 use datafusion::error::Result;
 use structopt::StructOpt;
 use wren_benchmarks::tpch;
+use wren_benchmarks::util::options::Verbosity;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "TPC-H", about = "TPC-H Benchmarks.")]
-enum TpchOpt {
+struct TpchOpt {
+    #[structopt(flatten)]
+    verbosity: Verbosity,
+
+    #[structopt(subcommand)]
+    command: TpchCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum TpchCommand {
     Benchmark(tpch::run::RunOpt),
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
-    match TpchOpt::from_args() {
-        TpchOpt::Benchmark(opt) => opt.run().await,
+fn main() -> Result<()> {
+    let opt = TpchOpt::from_args();
+    opt.verbosity.init_logger();
+    match opt.command {
+        TpchCommand::Benchmark(opt) => opt.runtime()?.block_on(opt.run()),
     }
 }
@@ -1,17 +1,27 @@
 use datafusion::error::Result;
 use structopt::StructOpt;
+use wren_benchmarks::util::options::Verbosity;
 use wren_benchmarks::wren;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "WREN", about = "WREN Benchmarks.")]
-enum WrenOpt {
+struct WrenOpt {
+    #[structopt(flatten)]
+    verbosity: Verbosity,
+
+    #[structopt(subcommand)]
+    command: WrenCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum WrenCommand {
     Benchmark(wren::run::RunOpt),
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
-    match WrenOpt::from_args() {
-        WrenOpt::Benchmark(opt) => opt.run().await,
+fn main() -> Result<()> {
+    let opt = WrenOpt::from_args();
+    opt.verbosity.init_logger();
+    match opt.command {
+        WrenCommand::Benchmark(opt) => opt.runtime()?.block_on(opt.run()),
     }
 }
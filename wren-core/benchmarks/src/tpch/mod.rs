@@ -1,3 +1,4 @@
+use crate::util::tags::parse_tags;
 use datafusion::common::{plan_err, Result};
 use std::fs;
 use wren_core::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
@@ -7,6 +8,20 @@ pub mod run;
 
 /// Get the SQL statements from the specified query file
 pub fn get_query_sql(query: usize) -> Result<Vec<String>> {
+    Ok(read_query(query)?
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Get the tags declared by the `-- tags:` line of the specified query file
+pub fn get_query_tags(query: usize) -> Result<Vec<String>> {
+    Ok(parse_tags(&read_query(query)?))
+}
+
+fn read_query(query: usize) -> Result<String> {
     if query > 0 && query < 23 {
         let possibilities = vec![
             format!("queries/tpch/q{query}.sql"),
@@ -15,14 +30,7 @@ pub fn get_query_sql(query: usize) -> Result<Vec<String>> {
         let mut errors = vec![];
         for filename in possibilities {
             match fs::read_to_string(&filename) {
-                Ok(contents) => {
-                    return Ok(contents
-                        .split(';')
-                        .map(|s| s.trim())
-                        .filter(|s| !s.is_empty())
-                        .map(|s| s.to_string())
-                        .collect());
-                }
+                Ok(contents) => return Ok(contents),
                 Err(e) => errors.push(format!("{filename}: {e}")),
             };
         }
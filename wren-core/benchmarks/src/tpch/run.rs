@@ -1,6 +1,8 @@
-use crate::tpch::{get_query_sql, tpch_manifest};
+use crate::tpch::{get_query_sql, get_query_tags, tpch_manifest};
+use crate::util::counter::InstructionCounter;
 use crate::util::options::CommonOpt;
 use crate::util::run::BenchmarkRun;
+use crate::util::stats::{format_ms, Summary};
 use datafusion::common::Result;
 use datafusion::prelude::SessionContext;
 use std::collections::HashMap;
@@ -24,12 +26,26 @@ pub struct RunOpt {
     /// Path to machine readable output file
     #[structopt(parse(from_os_str), short = "o", long = "output")]
     output_path: Option<PathBuf>,
+
+    /// Directory to write one machine readable output file per query. It can be used
+    /// with or without the output file
+    #[structopt(parse(from_os_str), long = "output-dir")]
+    output_dir: Option<PathBuf>,
+
+    /// Path to machine readable output file in JSON lines, written as each query finishes
+    #[structopt(parse(from_os_str), long = "jsonl")]
+    jsonl_path: Option<PathBuf>,
 }
 
 const TPCH_QUERY_START_ID: usize = 1;
 const TPCH_QUERY_END_ID: usize = 22;
 
 impl RunOpt {
+    /// Build the runtime to run the benchmark by [CommonOpt::runtime]
+    pub fn runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        self.common.runtime()
+    }
+
     pub async fn run(self) -> Result<()> {
         println!("Running benchmarks with the following options: {self:?}");
         let query_range = match self.query {
@@ -37,19 +53,36 @@ impl RunOpt {
             None => TPCH_QUERY_START_ID..=TPCH_QUERY_END_ID,
         };
 
-        let mut benchmark_run = BenchmarkRun::new();
-        for query_id in query_range {
-            benchmark_run.start_new_case(&format!("Query {query_id}"));
-            let query_run = self.benchmark_query(query_id).await?;
-            for iter in query_run {
-                benchmark_run.write_iter(iter.elapsed);
+        let mut benchmark_run =
+            BenchmarkRun::new().with_jsonl_output(self.jsonl_path.as_ref())?;
+        let mut counter = InstructionCounter::open(self.common.counter);
+        for suite_run in 0..self.common.repeat_suite {
+            benchmark_run.start_suite_run(suite_run);
+            for query_id in query_range.clone() {
+                let tags = get_query_tags(query_id)?;
+                if !self.common.matches_tags(&tags) {
+                    continue;
+                }
+                benchmark_run
+                    .start_new_case_with_tags(&format!("Query {query_id}"), tags);
+                let query_run = self.benchmark_query(query_id, counter.as_mut()).await?;
+                for iter in query_run {
+                    benchmark_run
+                        .write_iter_with_instructions(iter.elapsed, iter.instructions);
+                }
+                benchmark_run.finish_case()?;
             }
         }
         benchmark_run.maybe_write_json(self.output_path.as_ref())?;
+        benchmark_run.maybe_write_json_per_query(self.output_dir.as_ref())?;
         Ok(())
     }
 
-    async fn benchmark_query(&self, query_id: usize) -> Result<Vec<QueryResult>> {
+    async fn benchmark_query(
+        &self,
+        query_id: usize,
+        mut counter: Option<&mut InstructionCounter>,
+    ) -> Result<Vec<QueryResult>> {
         let ctx = SessionContext::new();
         let mdl = Arc::new(AnalyzedWrenMDL::analyze(
             tpch_manifest(),
@@ -60,6 +93,9 @@ impl RunOpt {
         // run benchmark
         let mut query_results = vec![];
         for i in 0..self.iterations() {
+            if let Some(counter) = counter.as_mut() {
+                counter.start()?;
+            }
             let start = Instant::now();
             let sql = &get_query_sql(query_id)?;
             for query in sql {
@@ -74,14 +110,25 @@ impl RunOpt {
             }
 
             let elapsed = start.elapsed(); //.as_secs_f64() * 1000.0;
+            let instructions =
+                counter.as_mut().map(|counter| counter.stop()).transpose()?;
             let ms = elapsed.as_secs_f64() * 1000.0;
             millis.push(ms);
             println!("Query {query_id} iteration {i} took {ms:.1} ms");
-            query_results.push(QueryResult { elapsed });
+            query_results.push(QueryResult {
+                elapsed,
+                instructions,
+            });
         }
 
-        let avg = millis.iter().sum::<f64>() / millis.len() as f64;
-        println!("Query {query_id} avg time: {avg:.2} ms");
+        if let Some(summary) = Summary::from_samples(&millis) {
+            println!(
+                "Query {query_id} avg time: {} ms, p95: {} ms, p99: {} ms",
+                format_ms(summary.avg),
+                format_ms(summary.p95),
+                format_ms(summary.p99)
+            );
+        }
 
         Ok(query_results)
     }
@@ -93,4 +140,5 @@ impl RunOpt {
 
 struct QueryResult {
     elapsed: std::time::Duration,
+    instructions: Option<u64>,
 }
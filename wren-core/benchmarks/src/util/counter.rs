@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+/// The counter recorded alongside the elapsed time of each iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// The retired instructions of the benchmark. It's far less noisy than the wall time
+    /// on shared machines, but only available on Linux.
+    Instructions,
+}
+
+impl FromStr for Counter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "instructions" => Ok(Counter::Instructions),
+            _ => Err(format!("Unknown counter {s}. Expected instructions")),
+        }
+    }
+}
+
+/// Count the instructions retired by the current thread with `perf_event_open`.
+///
+/// The counter is inherited by the threads spawned after it's opened, e.g. the blocking
+/// threads of tokio, so their instructions are counted too. The threads running before,
+/// like the workers of a multi-thread runtime, aren't observed, so the runners use a
+/// current-thread runtime to run the whole benchmark on the calling thread when the
+/// instructions are counted.
+pub struct InstructionCounter {
+    #[cfg(target_os = "linux")]
+    counter: perf_event::Counter,
+}
+
+impl InstructionCounter {
+    /// Open the counter. It fails on the platforms other than Linux, or if the access to
+    /// the performance counters is denied, e.g. by `kernel.perf_event_paranoid`.
+    #[cfg(target_os = "linux")]
+    pub fn new() -> std::io::Result<Self> {
+        let counter = perf_event::Builder::new()
+            .kind(perf_event::events::Hardware::INSTRUCTIONS)
+            .inherit(true)
+            .build()?;
+        Ok(Self { counter })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn new() -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "instruction counting is only supported on Linux",
+        ))
+    }
+
+    /// Open the counter if it's requested. If it can't be opened, the benchmark falls back
+    /// to the wall time only.
+    pub fn open(counter: Option<Counter>) -> Option<Self> {
+        match counter? {
+            Counter::Instructions => match Self::new() {
+                Ok(counter) => Some(counter),
+                Err(e) => {
+                    eprintln!(
+                        "warning: can't count the instructions ({e}), only the elapsed time is recorded"
+                    );
+                    None
+                }
+            },
+        }
+    }
+
+    /// Reset the count and start counting
+    pub fn start(&mut self) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            self.counter.reset()?;
+            self.counter.enable()?;
+        }
+        Ok(())
+    }
+
+    /// Stop counting and return the instructions retired since [InstructionCounter::start]
+    pub fn stop(&mut self) -> std::io::Result<u64> {
+        #[cfg(target_os = "linux")]
+        {
+            self.counter.disable()?;
+            self.counter.read()
+        }
+        #[cfg(not(target_os = "linux"))]
+        Ok(0)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::io::ErrorKind;
+    use std::time::Instant;
+
+    use crate::util::counter::InstructionCounter;
+    use crate::util::run::BenchmarkRun;
+
+    /// Open the counter, or return `None` to skip the test if the access to the
+    /// performance counters is denied
+    fn open_counter() -> std::io::Result<Option<InstructionCounter>> {
+        match InstructionCounter::new() {
+            Ok(counter) => Ok(Some(counter)),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                eprintln!("skipped: can't access the performance counters ({e})");
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    #[test]
+    fn test_instruction_counter() -> std::io::Result<()> {
+        let Some(mut counter) = open_counter()? else {
+            return Ok(());
+        };
+        counter.start()?;
+        let start = Instant::now();
+        let sum = (0..10_000u64).map(std::hint::black_box).sum::<u64>();
+        let elapsed = start.elapsed();
+        let instructions = counter.stop()?;
+        assert_eq!(sum, 49_995_000);
+        assert!(instructions > 0);
+
+        let mut run = BenchmarkRun::new();
+        run.start_new_case("Query 1");
+        run.write_iter_with_instructions(elapsed, Some(instructions));
+        let json: serde_json::Value = serde_json::from_str(&run.to_json())?;
+        assert_eq!(
+            json["queries"][0]["iterations"][0]["instructions"],
+            serde_json::Value::from(instructions)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_spawned_thread() -> std::io::Result<()> {
+        let Some(mut counter) = open_counter()? else {
+            return Ok(());
+        };
+        counter.start()?;
+        let sum = std::thread::spawn(|| {
+            (0..1_000_000u64).map(std::hint::black_box).sum::<u64>()
+        })
+        .join()
+        .unwrap();
+        let instructions = counter.stop()?;
+        assert_eq!(sum, 499_999_500_000);
+        // the calling thread only spawns and joins, the loop runs on the spawned thread
+        assert!(instructions > 1_000_000, "instructions: {instructions}");
+        Ok(())
+    }
+}
@@ -1,2 +1,6 @@
+pub mod counter;
 pub mod options;
+pub mod profiler;
 pub mod run;
+pub mod stats;
+pub mod tags;
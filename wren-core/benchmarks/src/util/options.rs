@@ -1,4 +1,8 @@
+use log::LevelFilter;
 use structopt::StructOpt;
+use tokio::runtime::Runtime;
+
+use crate::util::counter::Counter;
 
 // Common benchmark options (don't use doc comments otherwise this doc
 // shows up in help files)
@@ -7,4 +11,99 @@ pub struct CommonOpt {
     /// Number of iterations of each test run
     #[structopt(short = "i", long = "iterations", default_value = "3")]
     pub iterations: usize,
+
+    /// Only run the queries with the tag
+    #[structopt(long = "filter-tag")]
+    pub filter_tag: Option<String>,
+
+    /// Record a counter of each iteration besides the elapsed time, e.g. instructions
+    #[structopt(long = "counter")]
+    pub counter: Option<Counter>,
+
+    /// Number of times to run the whole suite. Each iteration records its suite run
+    #[structopt(long = "repeat-suite", default_value = "1")]
+    pub repeat_suite: usize,
+}
+
+impl CommonOpt {
+    /// Build the runtime of the benchmark. The instructions are counted on a current-thread
+    /// runtime, because the counter can't observe the workers of a multi-thread runtime
+    /// started before it's opened. The other benchmarks run on a multi-thread runtime.
+    pub fn runtime(&self) -> std::io::Result<Runtime> {
+        match self.counter {
+            Some(Counter::Instructions) => tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build(),
+            None => tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build(),
+        }
+    }
+
+    /// Check if a query with the tags should be run
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        match &self.filter_tag {
+            Some(filter_tag) => tags.iter().any(|tag| tag == filter_tag),
+            None => true,
+        }
+    }
+}
+
+/// The verbosity of the logs. It's declared before the subcommand, e.g. `tpch -vv benchmark`,
+/// because `-q` of the subcommand is the query number.
+#[derive(Debug, StructOpt, Clone)]
+pub struct Verbosity {
+    /// Log more: -v for info, -vv for debug and -vvv for trace
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    pub verbose: u8,
+
+    /// Don't log anything
+    #[structopt(short = "q", long = "quiet", conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Verbosity {
+    /// The level of the flags, or None to keep the level of `RUST_LOG`
+    pub fn level_filter(&self) -> Option<LevelFilter> {
+        if self.quiet {
+            return Some(LevelFilter::Off);
+        }
+        match self.verbose {
+            0 => None,
+            1 => Some(LevelFilter::Info),
+            2 => Some(LevelFilter::Debug),
+            _ => Some(LevelFilter::Trace),
+        }
+    }
+
+    /// Initialize the logger by `RUST_LOG`, overridden by the level of the flags
+    pub fn init_logger(&self) {
+        let mut builder = env_logger::Builder::from_default_env();
+        if let Some(level) = self.level_filter() {
+            builder.filter_level(level);
+        }
+        builder.init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+    use structopt::StructOpt;
+
+    use crate::util::options::Verbosity;
+
+    #[test]
+    fn test_verbosity() {
+        let level = |args: &[&str]| {
+            Verbosity::from_iter(std::iter::once("bench").chain(args.iter().copied()))
+                .level_filter()
+        };
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["-v"]), Some(LevelFilter::Info));
+        assert_eq!(level(&["-vv"]), Some(LevelFilter::Debug));
+        assert_eq!(level(&["--verbose", "-vv"]), Some(LevelFilter::Trace));
+        assert_eq!(level(&["-q"]), Some(LevelFilter::Off));
+        assert!(Verbosity::from_iter_safe(["bench", "-q", "-v"]).is_err());
+    }
 }
@@ -0,0 +1,152 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// Record the duration of the phases of a run, e.g. analyzing the MDL and transforming the
+/// SQL. Each checkpoint takes the time elapsed since the previous one.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    checkpoints: Vec<(String, Duration)>,
+    last: Instant,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            checkpoints: vec![],
+            last: Instant::now(),
+        }
+    }
+
+    /// End the current phase with the name
+    pub fn checkpoint(&mut self, name: &str) {
+        let now = Instant::now();
+        self.checkpoints
+            .push((name.to_string(), now.duration_since(self.last)));
+        self.last = now;
+    }
+
+    pub fn checkpoints(&self) -> &[(String, Duration)] {
+        &self.checkpoints
+    }
+
+    /// Accumulate the durations of the other profile into the checkpoints of the same names,
+    /// e.g. to average the profiles of the iterations by [Profiler::to_string_averaged].
+    ///
+    /// The profiles should have the same checkpoints. If they don't, it warns and the
+    /// checkpoints missing in this profile are appended, so their averages are lower than
+    /// the durations of the profiles having them.
+    pub fn merge(&mut self, other: &Profiler) {
+        let names = |profiler: &Profiler| {
+            let mut names = profiler
+                .checkpoints
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            names
+        };
+        if !self.checkpoints.is_empty() && names(self) != names(other) {
+            eprintln!(
+                "warning: merging the profiles of different checkpoints: {:?} and {:?}",
+                names(self),
+                names(other)
+            );
+        }
+        for (name, duration) in other.checkpoints.iter() {
+            match self.checkpoints.iter_mut().find(|(n, _)| n == name) {
+                Some((_, total)) => *total += *duration,
+                None => self.checkpoints.push((name.clone(), *duration)),
+            }
+        }
+    }
+
+    /// Render the durations of the checkpoints divided by the count, the number of the
+    /// profiles merged by [Profiler::merge]
+    pub fn to_string_averaged(&self, count: u32) -> String {
+        self.checkpoints
+            .iter()
+            .map(|(name, duration)| format_checkpoint(name, *duration / count.max(1)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn format_checkpoint(name: &str, duration: Duration) -> String {
+    format!("{name}: {:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Render the total durations of the checkpoints
+impl Display for Profiler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let checkpoints = self
+            .checkpoints
+            .iter()
+            .map(|(name, duration)| format_checkpoint(name, *duration))
+            .collect::<Vec<_>>();
+        write!(f, "{}", checkpoints.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::util::profiler::Profiler;
+
+    fn profile(phases: &[(&str, u64)]) -> Profiler {
+        let mut profiler = Profiler::new();
+        profiler.checkpoints = phases
+            .iter()
+            .map(|(name, ms)| (name.to_string(), Duration::from_millis(*ms)))
+            .collect();
+        profiler
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut merged = Profiler::new();
+        merged.merge(&profile(&[("analyze", 10), ("transform", 30)]));
+        merged.merge(&profile(&[("analyze", 20), ("transform", 50)]));
+        assert_eq!(
+            merged.checkpoints(),
+            &[
+                ("analyze".to_string(), Duration::from_millis(30)),
+                ("transform".to_string(), Duration::from_millis(80)),
+            ]
+        );
+        assert_eq!(
+            merged.to_string_averaged(2),
+            "analyze: 15.000 ms\ntransform: 40.000 ms"
+        );
+        assert_eq!(
+            merged.to_string(),
+            "analyze: 30.000 ms\ntransform: 80.000 ms"
+        );
+
+        // the checkpoint missing in the other profiles is averaged over all of them
+        merged.merge(&profile(&[("analyze", 30), ("unparse", 6)]));
+        assert_eq!(
+            merged.to_string_averaged(3),
+            "analyze: 20.000 ms\ntransform: 26.667 ms\nunparse: 2.000 ms"
+        );
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut profiler = Profiler::new();
+        profiler.checkpoint("analyze");
+        profiler.checkpoint("transform");
+        let names: Vec<_> = profiler
+            .checkpoints()
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect();
+        assert_eq!(names, vec!["analyze", "transform"]);
+    }
+}
@@ -2,9 +2,15 @@ use datafusion::common::Result;
 use serde::{Serialize, Serializer};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::time::{Duration, SystemTime};
 
+/// The version of the json output shape. Bump it whenever the shape changes
+/// so that `compare.py` can tell the formats apart.
+pub const BENCHMARK_SCHEMA_VERSION: u64 = 4;
+
 fn serialize_start_time<S>(start_time: &SystemTime, ser: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -55,15 +61,22 @@ impl RunContext {
 }
 
 /// A single iteration of a benchmark query
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct QueryIter {
     #[serde(serialize_with = "serialize_elapsed")]
     elapsed: Duration,
+    /// The retired instructions, if they're counted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<u64>,
+    /// The 0-based repetition of the suite the iteration ran in
+    suite_run: usize,
 }
 /// A single benchmark case
 #[derive(Debug, Serialize)]
 pub struct BenchQuery {
     query: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
     iterations: Vec<QueryIter>,
     #[serde(serialize_with = "serialize_start_time")]
     start_time: SystemTime,
@@ -73,6 +86,10 @@ pub struct BenchmarkRun {
     context: RunContext,
     queries: Vec<BenchQuery>,
     current_case: Option<usize>,
+    /// The repetition of the suite the iterations are written to
+    suite_run: usize,
+    /// The JSON lines output written as the cases finish
+    jsonl: Option<BufWriter<File>>,
 }
 
 impl Default for BenchmarkRun {
@@ -88,35 +105,118 @@ impl BenchmarkRun {
             context: RunContext::new(),
             queries: vec![],
             current_case: None,
+            suite_run: 0,
+            jsonl: None,
         }
     }
+
+    /// Write the run as JSON lines into the output path if it exists. The first line has
+    /// the schema version and the context, and a line of each case is written and flushed by
+    /// [BenchmarkRun::finish_case], so a crashed run still leaves the finished cases. When the
+    /// suite is repeated, a case has a line per repetition.
+    pub fn with_jsonl_output(
+        mut self,
+        maybe_path: Option<impl AsRef<Path>>,
+    ) -> Result<Self> {
+        if let Some(path) = maybe_path {
+            let mut header = serde_json::Map::new();
+            header.insert(
+                "schema_version".to_string(),
+                Value::from(BENCHMARK_SCHEMA_VERSION),
+            );
+            header.insert(
+                "context".to_string(),
+                serde_json::to_value(&self.context).unwrap(),
+            );
+            let mut writer = BufWriter::new(File::create(path)?);
+            writeln!(writer, "{}", Value::Object(header))?;
+            writer.flush()?;
+            self.jsonl = Some(writer);
+        }
+        Ok(self)
+    }
     /// begin a new case. iterations added after this will be included in the new case
     pub fn start_new_case(&mut self, id: &str) {
+        self.start_new_case_with_tags(id, vec![])
+    }
+    /// Begin a repetition of the whole suite. The iterations written after this record
+    /// the 0-based repetition, and the cases of the earlier repetitions are continued.
+    pub fn start_suite_run(&mut self, suite_run: usize) {
+        self.suite_run = suite_run;
+    }
+    /// begin a new case with the tags of the query, which are recorded in the output.
+    /// If an earlier repetition of the suite ran the case, its iterations are appended to it.
+    pub fn start_new_case_with_tags(&mut self, id: &str, tags: Vec<String>) {
+        if self.suite_run > 0 {
+            if let Some(idx) = self.queries.iter().position(|query| query.query == id) {
+                self.current_case = Some(idx);
+                return;
+            }
+        }
         self.queries.push(BenchQuery {
             query: id.to_owned(),
+            tags,
             iterations: vec![],
             start_time: SystemTime::now(),
         });
-        if let Some(c) = self.current_case.as_mut() {
-            *c += 1;
-        } else {
-            self.current_case = Some(0);
-        }
+        self.current_case = Some(self.queries.len() - 1);
     }
     /// Write a new iteration to the current case
     pub fn write_iter(&mut self, elapsed: Duration) {
+        self.write_iter_with_instructions(elapsed, None)
+    }
+    /// Write a new iteration with the retired instructions to the current case
+    pub fn write_iter_with_instructions(
+        &mut self,
+        elapsed: Duration,
+        instructions: Option<u64>,
+    ) {
         if let Some(idx) = self.current_case {
-            self.queries[idx].iterations.push(QueryIter { elapsed })
+            self.queries[idx].iterations.push(QueryIter {
+                elapsed,
+                instructions,
+                suite_run: self.suite_run,
+            })
         } else {
             panic!("no cases existed yet");
         }
     }
 
+    /// Mark the current case of the repetition as finished. Its line with the iterations of
+    /// the repetition is appended to the JSON lines output if it's enabled, so the earlier
+    /// repetitions are kept if a later one crashes.
+    pub fn finish_case(&mut self) -> Result<()> {
+        let (Some(writer), Some(idx)) = (self.jsonl.as_mut(), self.current_case) else {
+            return Ok(());
+        };
+        let query = &self.queries[idx];
+        let line = BenchQuery {
+            query: query.query.clone(),
+            tags: query.tags.clone(),
+            iterations: query
+                .iterations
+                .iter()
+                .filter(|iteration| iteration.suite_run == self.suite_run)
+                .cloned()
+                .collect(),
+            start_time: query.start_time,
+        };
+        writeln!(writer, "{}", serde_json::to_string(&line).unwrap())?;
+        writer.flush()?;
+        Ok(())
+    }
+
     /// Stringify data into formatted json
     pub fn to_json(&self) -> String {
+        self.to_json_of(&self.queries)
+    }
+
+    /// Stringify the queries with the schema version and the context of the run
+    fn to_json_of(&self, queries: &[BenchQuery]) -> String {
         let mut output = HashMap::<&str, Value>::new();
+        output.insert("schema_version", Value::from(BENCHMARK_SCHEMA_VERSION));
         output.insert("context", serde_json::to_value(&self.context).unwrap());
-        output.insert("queries", serde_json::to_value(&self.queries).unwrap());
+        output.insert("queries", serde_json::to_value(queries).unwrap());
         serde_json::to_string_pretty(&output).unwrap()
     }
 
@@ -127,4 +227,181 @@ impl BenchmarkRun {
         };
         Ok(())
     }
+
+    /// Write each query as a json file named by the query into the directory if it exists,
+    /// e.g. `Query_1.json`. Each file is a complete output of a run with the single query.
+    pub fn maybe_write_json_per_query(
+        &self,
+        maybe_dir: Option<impl AsRef<Path>>,
+    ) -> Result<()> {
+        if let Some(dir) = maybe_dir {
+            std::fs::create_dir_all(dir.as_ref())?;
+            for query in &self.queries {
+                let path = dir
+                    .as_ref()
+                    .join(format!("{}.json", file_stem(&query.query)));
+                std::fs::write(path, self.to_json_of(std::slice::from_ref(query)))?;
+            }
+        };
+        Ok(())
+    }
+}
+
+/// The file name of the query id, e.g. `Query_1` for `Query 1`
+fn file_stem(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::util::run::{BenchmarkRun, BENCHMARK_SCHEMA_VERSION};
+
+    #[test]
+    fn test_write_jsonl_of_stopped_run() -> datafusion::common::Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!("wren-benchmarks-{}.jsonl", std::process::id()));
+        {
+            let mut run = BenchmarkRun::new().with_jsonl_output(Some(&path))?;
+            run.start_new_case("Query 1");
+            run.write_iter(Duration::from_millis(10));
+            run.finish_case()?;
+            // the run stops in the middle of the second case
+            run.start_new_case("Query 2");
+            run.write_iter(Duration::from_millis(20));
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["schema_version"], BENCHMARK_SCHEMA_VERSION);
+        assert!(lines[0]["context"]["benchmark_version"].is_string());
+        assert_eq!(lines[1]["query"], "Query 1");
+        assert_eq!(lines[1]["iterations"][0]["elapsed"], 10.0);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_jsonl_of_each_repetition() -> datafusion::common::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "wren-benchmarks-repeat-{}.jsonl",
+            std::process::id()
+        ));
+        let lines = || -> datafusion::common::Result<Vec<serde_json::Value>> {
+            Ok(std::fs::read_to_string(&path)?
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect())
+        };
+        let mut run = BenchmarkRun::new().with_jsonl_output(Some(&path))?;
+        for suite_run in 0..2 {
+            run.start_suite_run(suite_run);
+            for id in ["Query 1", "Query 2"] {
+                run.start_new_case(id);
+                run.write_iter(Duration::from_millis(10));
+                run.finish_case()?;
+            }
+            // the repetition is written before the next one starts
+            assert_eq!(lines()?.len(), 1 + 2 * (suite_run + 1));
+        }
+
+        let lines = lines()?;
+        let cases: Vec<_> = lines[1..]
+            .iter()
+            .map(|line| {
+                let suite_runs: Vec<_> = line["iterations"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|iteration| iteration["suite_run"].as_u64().unwrap())
+                    .collect();
+                (line["query"].as_str().unwrap().to_string(), suite_runs)
+            })
+            .collect();
+        assert_eq!(
+            cases,
+            vec![
+                ("Query 1".to_string(), vec![0]),
+                ("Query 2".to_string(), vec![0]),
+                ("Query 1".to_string(), vec![1]),
+                ("Query 2".to_string(), vec![1]),
+            ]
+        );
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_suite() {
+        let (iterations, repeat_suite) = (3, 2);
+        let mut run = BenchmarkRun::new();
+        for suite_run in 0..repeat_suite {
+            run.start_suite_run(suite_run);
+            for id in ["Query 1", "Query 2"] {
+                run.start_new_case(id);
+                for _ in 0..iterations {
+                    run.write_iter(Duration::from_millis(10));
+                }
+            }
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&run.to_json()).unwrap();
+        let queries = json["queries"].as_array().unwrap();
+        assert_eq!(queries.len(), 2);
+        for query in queries {
+            let suite_runs: Vec<_> = query["iterations"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|iteration| iteration["suite_run"].as_u64().unwrap())
+                .collect();
+            assert_eq!(suite_runs.len(), iterations * repeat_suite);
+            assert_eq!(suite_runs, vec![0, 0, 0, 1, 1, 1]);
+        }
+    }
+
+    #[test]
+    fn test_write_json_per_query() -> datafusion::common::Result<()> {
+        let dir = std::env::temp_dir()
+            .join(format!("wren-benchmarks-output-dir-{}", std::process::id()));
+        let mut run = BenchmarkRun::new();
+        for id in ["Query 1", "Query 2"] {
+            run.start_new_case(id);
+            run.write_iter(Duration::from_millis(10));
+            run.write_iter(Duration::from_millis(20));
+        }
+        run.maybe_write_json_per_query(Some(&dir))?;
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<std::io::Result<_>>()?;
+        files.sort();
+        assert_eq!(files, vec!["Query_1.json", "Query_2.json"]);
+        for id in ["Query 1", "Query 2"] {
+            let path = dir.join(format!("{}.json", id.replace(' ', "_")));
+            let json: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(path)?).unwrap();
+            assert_eq!(json["schema_version"], BENCHMARK_SCHEMA_VERSION);
+            assert!(json["context"]["benchmark_version"].is_string());
+            let queries = json["queries"].as_array().unwrap();
+            assert_eq!(queries.len(), 1);
+            assert_eq!(queries[0]["query"], id);
+            assert_eq!(queries[0]["iterations"].as_array().unwrap().len(), 2);
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,169 @@
+/// The summary of the elapsed times of the iterations of a query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub avg: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarize the samples. The non-finite samples are ignored, so `None` is returned if
+    /// there is no finite sample.
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        let mut sorted: Vec<f64> =
+            samples.iter().copied().filter(|s| s.is_finite()).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        // sum the mean of each sample to avoid overflowing to the infinity
+        let len = sorted.len() as f64;
+        let avg = sorted.iter().map(|s| s / len).sum::<f64>();
+        Some(Self {
+            min: sorted[0],
+            avg: avg.clamp(sorted[0], sorted[sorted.len() - 1]),
+            p95: percentile(&sorted, 95)?,
+            p99: percentile(&sorted, 99)?,
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// The nearest-rank percentile of the sorted samples, e.g. `percentile(&sorted, 95)` for p95.
+/// The percent above 100 is treated as 100. Return `None` if there is no sample.
+pub fn percentile(sorted: &[f64], percent: u32) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.get(percentile_index(sorted.len(), percent)).copied()
+}
+
+/// The index of the nearest-rank percentile in the sorted samples of the length, i.e.
+/// `ceil(len * percent / 100) - 1` clamped into the samples. It's computed by the integer
+/// math in u128, so it's exact and can't overflow for any length.
+pub fn percentile_index(len: usize, percent: u32) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    let percent = u128::from(percent.min(100));
+    let rank = (len as u128 * percent).div_ceil(100);
+    // the rank is at most len, so the cast back can't truncate
+    usize::try_from(rank.max(1)).unwrap_or(len) - 1
+}
+
+/// The milliseconds from which [format_ms] switches to the scientific notation
+pub const SCIENTIFIC_THRESHOLD_MS: f64 = 1e6;
+
+/// The most decimals of a sub-millisecond value, i.e. nanoseconds
+const MAX_DECIMALS: usize = 6;
+
+/// Format the milliseconds for the reports, e.g. `12.35`. The values below 1 ms keep 3
+/// significant digits, e.g. `0.003`, and the values from [SCIENTIFIC_THRESHOLD_MS] are in
+/// the scientific notation, e.g. `1.23e7`. A value rounded to zero is `0.00`, never `-0.00`.
+pub fn format_ms(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let abs = value.abs();
+    let formatted = if abs >= SCIENTIFIC_THRESHOLD_MS {
+        format!("{value:.2e}")
+    } else if abs > 0.0 && abs < 1.0 {
+        let decimals = ((-abs.log10()).floor() as usize + 3).min(MAX_DECIMALS);
+        let mut formatted = format!("{value:.decimals$}");
+        // drop the trailing zeros but keep 2 decimals, e.g. 0.00300 to 0.003
+        while formatted.len() - formatted.find('.').unwrap_or(0) > 3
+            && formatted.ends_with('0')
+        {
+            formatted.pop();
+        }
+        formatted
+    } else {
+        format!("{value:.2}")
+    };
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => {
+            unsigned.to_string()
+        }
+        _ => formatted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::util::stats::{format_ms, percentile, percentile_index, Summary};
+
+    #[test]
+    fn test_percentile() {
+        let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 0), Some(1.0));
+        assert_eq!(percentile(&sorted, 95), Some(95.0));
+        assert_eq!(percentile(&sorted, 99), Some(99.0));
+        assert_eq!(percentile(&sorted, 100), Some(100.0));
+        assert_eq!(percentile(&sorted, 200), Some(100.0));
+        assert_eq!(percentile(&[3.0], 95), Some(3.0));
+        assert_eq!(percentile(&[], 95), None);
+
+        assert_eq!(percentile_index(usize::MAX, 100), usize::MAX - 1);
+        assert_eq!(percentile_index(usize::MAX, 95), usize::MAX / 100 * 95 + 14);
+        assert_eq!(percentile_index(0, 95), 0);
+    }
+
+    #[test]
+    fn test_format_ms() {
+        assert_eq!(format_ms(12.345), "12.35");
+        assert_eq!(format_ms(0.003), "0.003");
+        assert_eq!(format_ms(0.5), "0.50");
+        assert_eq!(format_ms(0.012345), "0.0123");
+        assert_eq!(format_ms(12345.0), "12345.00");
+        assert_eq!(format_ms(12345678.0), "1.23e7");
+        // rendered as -0.00 by `{:.2}`
+        assert_eq!(format_ms(-0.0), "0.00");
+        assert_eq!(format_ms(-0.001), "-0.001");
+        assert_eq!(format_ms(-0.0000001), "0.00");
+        assert_eq!(format_ms(f64::NAN), "NaN");
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary =
+            Summary::from_samples(&[4.0, f64::NAN, 1.0, 3.0, f64::INFINITY, 2.0])
+                .unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                min: 1.0,
+                avg: 2.5,
+                p95: 4.0,
+                p99: 4.0,
+                max: 4.0,
+            }
+        );
+        assert_eq!(Summary::from_samples(&[f64::NAN]), None);
+        assert_eq!(Summary::from_samples(&[]), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_percentile_index_in_bounds(len in 1..=usize::MAX, percent in 0..=100u32) {
+            let index = percentile_index(len, percent);
+            prop_assert!(index < len);
+            prop_assert!(index <= percentile_index(len, percent + 1));
+        }
+
+        #[test]
+        fn test_summary_ordered(samples in proptest::collection::vec(any::<f64>(), 1..500)) {
+            if let Some(summary) = Summary::from_samples(&samples) {
+                prop_assert!(summary.min <= summary.p95);
+                prop_assert!(summary.p95 <= summary.p99);
+                prop_assert!(summary.p99 <= summary.max);
+                prop_assert!(summary.min <= summary.avg && summary.avg <= summary.max);
+            } else {
+                prop_assert!(samples.iter().all(|s| !s.is_finite()));
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+/// Parse the tags of a query file. The tags are declared by a comment line like
+/// `-- tags: join, subquery`, and a file without the line has no tags.
+pub fn parse_tags(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("--"))
+        .filter_map(|comment| comment.trim().strip_prefix("tags:"))
+        .flat_map(|tags| tags.split(','))
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+
+    use crate::util::options::CommonOpt;
+    use crate::util::tags::parse_tags;
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            parse_tags("-- tags: join, subquery\nselect 1"),
+            vec!["join", "subquery"]
+        );
+        assert_eq!(parse_tags("  --tags:cte,\nselect 1"), vec!["cte"]);
+        assert!(parse_tags("-- the tags of the query\nselect 1").is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_tag() {
+        let queries = [
+            (1, "-- tags: join, subquery\nselect 1"),
+            (2, "-- tags: cte\nselect 2"),
+            (3, "-- tags: join\nselect 3"),
+        ];
+        let kept = |args: &[&str]| {
+            let options = CommonOpt::from_iter(
+                std::iter::once("bench").chain(args.iter().copied()),
+            );
+            queries
+                .iter()
+                .filter(|(_, contents)| options.matches_tags(&parse_tags(contents)))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kept(&["--filter-tag", "join"]), vec![1, 3]);
+        assert_eq!(kept(&["--filter-tag", "union"]), Vec::<i32>::new());
+        assert_eq!(kept(&[]), vec![1, 2, 3]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::util::tags::parse_tags;
 use datafusion::common::{plan_err, Result};
 use std::fs;
 use wren_core::mdl::manifest::Manifest;
@@ -6,6 +7,20 @@ pub mod run;
 
 /// Get the SQL statements from the specified query file
 pub fn get_query_sql(query_id: usize) -> Result<Vec<String>> {
+    Ok(read_query(query_id)?
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Get the tags declared by the `-- tags:` line of the specified query file
+pub fn get_query_tags(query_id: usize) -> Result<Vec<String>> {
+    Ok(parse_tags(&read_query(query_id)?))
+}
+
+fn read_query(query_id: usize) -> Result<String> {
     let possibilities = vec![
         format!("queries/q{query_id}.sql"),
         format!("benchmarks/queries/q{query_id}.sql"),
@@ -13,14 +28,7 @@ pub fn get_query_sql(query_id: usize) -> Result<Vec<String>> {
     let mut errors = vec![];
     for filename in possibilities {
         match fs::read_to_string(&filename) {
-            Ok(contents) => {
-                return Ok(contents
-                    .split(';')
-                    .map(|s| s.trim())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.to_string())
-                    .collect());
-            }
+            Ok(contents) => return Ok(contents),
             Err(e) => errors.push(format!("{filename}: {e}")),
         };
     }
@@ -1,7 +1,10 @@
+use crate::util::counter::InstructionCounter;
 use crate::util::options::CommonOpt;
 use crate::util::run::BenchmarkRun;
+use crate::util::stats::{format_ms, Summary};
 use crate::wren::get_manifest;
 use crate::wren::get_query_sql;
+use crate::wren::get_query_tags;
 use datafusion::common::Result;
 use datafusion::prelude::SessionContext;
 use std::collections::HashMap;
@@ -25,12 +28,26 @@ pub struct RunOpt {
     /// Path to machine readable output file
     #[structopt(parse(from_os_str), short = "o", long = "output")]
     output_path: Option<PathBuf>,
+
+    /// Directory to write one machine readable output file per query. It can be used
+    /// with or without the output file
+    #[structopt(parse(from_os_str), long = "output-dir")]
+    output_dir: Option<PathBuf>,
+
+    /// Path to machine readable output file in JSON lines, written as each query finishes
+    #[structopt(parse(from_os_str), long = "jsonl")]
+    jsonl_path: Option<PathBuf>,
 }
 
 const QUERY_START_ID: usize = 1;
 const QUERY_END_ID: usize = 2;
 
 impl RunOpt {
+    /// Build the runtime to run the benchmark by [CommonOpt::runtime]
+    pub fn runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        self.common.runtime()
+    }
+
     pub async fn run(self) -> Result<()> {
         println!("Running benchmarks with the following options: {self:?}");
         let query_range = match self.query {
@@ -38,20 +55,37 @@ impl RunOpt {
             None => QUERY_START_ID..=QUERY_END_ID,
         };
 
-        let mut benchmark_run = BenchmarkRun::new();
+        let mut benchmark_run =
+            BenchmarkRun::new().with_jsonl_output(self.jsonl_path.as_ref())?;
+        let mut counter = InstructionCounter::open(self.common.counter);
 
-        for query_id in query_range {
-            benchmark_run.start_new_case(&format!("Query {query_id}"));
-            let query_run = self.benchmark_query(query_id).await?;
-            for iter in query_run {
-                benchmark_run.write_iter(iter.elapsed);
+        for suite_run in 0..self.common.repeat_suite {
+            benchmark_run.start_suite_run(suite_run);
+            for query_id in query_range.clone() {
+                let tags = get_query_tags(query_id)?;
+                if !self.common.matches_tags(&tags) {
+                    continue;
+                }
+                benchmark_run
+                    .start_new_case_with_tags(&format!("Query {query_id}"), tags);
+                let query_run = self.benchmark_query(query_id, counter.as_mut()).await?;
+                for iter in query_run {
+                    benchmark_run
+                        .write_iter_with_instructions(iter.elapsed, iter.instructions);
+                }
+                benchmark_run.finish_case()?;
             }
         }
         benchmark_run.maybe_write_json(self.output_path.as_ref())?;
+        benchmark_run.maybe_write_json_per_query(self.output_dir.as_ref())?;
         Ok(())
     }
 
-    async fn benchmark_query(&self, query_id: usize) -> Result<Vec<QueryResult>> {
+    async fn benchmark_query(
+        &self,
+        query_id: usize,
+        mut counter: Option<&mut InstructionCounter>,
+    ) -> Result<Vec<QueryResult>> {
         let ctx = SessionContext::new();
 
         // run benchmark
@@ -66,6 +100,9 @@ impl RunOpt {
                 Arc::new(HashMap::default()),
                 Mode::Unparse,
             )?);
+            if let Some(counter) = counter.as_mut() {
+                counter.start()?;
+            }
             let start = Instant::now();
             let sql = &get_query_sql(query_id)?;
             for query in sql {
@@ -80,17 +117,28 @@ impl RunOpt {
             }
 
             let elapsed = start.elapsed(); //.as_secs_f64() * 1000.0;
+            let instructions =
+                counter.as_mut().map(|counter| counter.stop()).transpose()?;
             let ms = elapsed.as_secs_f64() * 1000.0;
             println!("Query {query_id} iteration {i} took {ms:.1} ms");
-            query_results.push(QueryResult { elapsed });
+            query_results.push(QueryResult {
+                elapsed,
+                instructions,
+            });
         }
 
-        let avg = query_results
+        let millis: Vec<f64> = query_results
             .iter()
             .map(|r| r.elapsed.as_secs_f64() * 1000.0)
-            .sum::<f64>()
-            / query_results.len() as f64;
-        println!("Query {query_id} avg time: {avg:.2} ms");
+            .collect();
+        if let Some(summary) = Summary::from_samples(&millis) {
+            println!(
+                "Query {query_id} avg time: {} ms, p95: {} ms, p99: {} ms",
+                format_ms(summary.avg),
+                format_ms(summary.p95),
+                format_ms(summary.p99)
+            );
+        }
 
         Ok(query_results)
     }
@@ -102,4 +150,5 @@ impl RunOpt {
 
 struct QueryResult {
     elapsed: std::time::Duration,
+    instructions: Option<u64>,
 }
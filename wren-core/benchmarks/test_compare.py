@@ -0,0 +1,446 @@
+#!/usr/bin/env python
+#
+# Licensed to the Apache Software Foundation (ASF) under one or more
+# contributor license agreements.  See the NOTICE file distributed with
+# this work for additional information regarding copyright ownership.
+# The ASF licenses this file to You under the Apache License, Version 2.0
+# (the "License"); you may not use this file except in compliance with
+# the License.  You may obtain a copy of the License at
+#
+#    http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+#
+
+# The tests of compare.py. Run them in the venv of `./bench.sh venv`:
+#   python -m unittest test_compare.py
+
+from __future__ import annotations
+
+import contextlib
+import io
+import json
+import math
+import os
+import subprocess
+import sys
+import tempfile
+import unittest
+from pathlib import Path
+from typing import Any, Dict, List, Optional
+from unittest import mock
+
+from compare import (
+    BenchmarkComparison,
+    BenchmarkLoadError,
+    BenchmarkRun,
+    ChangeStatus,
+    QueryMetrics,
+    compare,
+    format_ms,
+    main,
+    reported_noise_threshold,
+)
+
+
+CONTEXT = {
+    "benchmark_version": "0.1.0",
+    "num_cpus": 1,
+    "start_time": 0,
+    "arguments": [],
+}
+
+
+def run_of(
+    times: Dict[Any, List[float]],
+    instructions: Optional[Dict[Any, List[int]]] = None,
+    tags: Optional[Dict[Any, List[str]]] = None,
+) -> Dict[str, Any]:
+    """A benchmark result of the current schema with the elapsed times of the queries."""
+    queries = []
+    for query, elapsed in times.items():
+        iterations = [{"elapsed": value, "suite_run": 0} for value in elapsed]
+        if instructions is not None:
+            for iteration, count in zip(iterations, instructions[query]):
+                iteration["instructions"] = count
+        queries.append({"query": query, "iterations": iterations, "start_time": 0})
+        if tags is not None:
+            queries[-1]["tags"] = tags.get(query, [])
+    return {"schema_version": 4, "context": CONTEXT, "queries": queries}
+
+
+class CompareTestCase(unittest.TestCase):
+    def setUp(self) -> None:
+        directory = tempfile.TemporaryDirectory()
+        self.addCleanup(directory.cleanup)
+        self.root = Path(directory.name)
+
+    def write(self, name: str, data: Dict[str, Any]) -> Path:
+        """Write the result as `<name>/results.json`, so the report names it by `name`."""
+        path = self.root / name / "results.json"
+        path.parent.mkdir(parents=True, exist_ok=True)
+        path.write_text(json.dumps(data))
+        return path
+
+    def printed(self, *args: Any, **kwargs: Any) -> str:
+        output = io.StringIO()
+        # wide enough that the tables don't wrap the cells
+        with contextlib.redirect_stdout(output), mock.patch.dict(os.environ, {"COLUMNS": "200"}):
+            compare(*args, **kwargs)
+        return output.getvalue()
+
+
+class BenchmarkComparisonTest(CompareTestCase):
+    def test_compare(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0, 12.0], 2: [20.0]}))
+        comparison = self.write("branch", run_of({1: [5.0, 6.0], 2: [21.0]}))
+
+        result = BenchmarkComparison.compare(baseline, comparison)
+        self.assertEqual(result.comparison, "branch")
+        self.assertEqual([query.query for query in result.queries], [1, 2])
+        self.assertEqual(
+            [query.baselines[0].status for query in result.queries],
+            [ChangeStatus.FASTER, ChangeStatus.NO_CHANGE],
+        )
+        self.assertEqual(result.queries[0].metrics.mean, 5.5)
+        (summary,) = result.baselines
+        self.assertEqual(summary.header, "main")
+        self.assertEqual((summary.total, summary.average), (30.0, 15.0))
+        self.assertEqual((result.total, result.average), (26.0, 13.0))
+        self.assertEqual((summary.faster, summary.slower, summary.no_change), (1, 0, 1))
+
+        data = result.to_dict()
+        self.assertEqual(data["queries"][0]["baselines"][0]["status"], "faster")
+        self.assertEqual(data["baselines"][0]["total"], 30.0)
+
+    def test_compare_matches_printed_report(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0], 2: [20.0]}))
+        comparison = self.write("branch", run_of({1: [5.0], 2: [21.0]}))
+
+        result = BenchmarkComparison.compare(baseline, comparison)
+        printed = self.printed([baseline], comparison, 0.05)
+        self.assertIn(f"{result.baselines[0].total:.2f}ms", printed)
+        self.assertIn(f"{result.baselines[0].average:.2f}ms", printed)
+        self.assertIn(f"{result.total:.2f}ms", printed)
+        self.assertIn(f"{result.average:.2f}ms", printed)
+        self.assertIn("+2.00x faster", printed)
+
+    def test_compare_multiple_baselines(self) -> None:
+        main = self.write("main", run_of({1: [10.0]}))
+        release = self.write("release", run_of({1: [20.0]}))
+        comparison = self.write("branch", run_of({1: [10.0]}))
+
+        result = BenchmarkComparison.compare([main, release], comparison)
+        self.assertEqual([summary.header for summary in result.baselines], ["main", "release"])
+        (query,) = result.queries
+        self.assertEqual([baseline.change for baseline in query.baselines], [1.0, 0.5])
+        self.assertEqual(
+            [baseline.status for baseline in query.baselines],
+            [ChangeStatus.NO_CHANGE, ChangeStatus.FASTER],
+        )
+
+    def test_compare_instructions(self) -> None:
+        baseline = self.write("main", run_of({1: [1.0]}, instructions={1: [1000]}))
+        comparison = self.write("branch", run_of({1: [1.0]}, instructions={1: [2000]}))
+
+        # the floor of milliseconds doesn't apply to the instruction counts
+        result = BenchmarkComparison.compare(
+            baseline, comparison, counter="instructions", noise_floor_ms=5000.0
+        )
+        self.assertEqual(result.counter, "instructions")
+        self.assertEqual((result.baselines[0].total, result.total), (1000.0, 2000.0))
+        self.assertEqual(result.queries[0].baselines[0].status, ChangeStatus.SLOWER)
+
+    def test_compare_noise_floor(self) -> None:
+        baseline = self.write("main", run_of({1: [2.0]}))
+        comparison = self.write("branch", run_of({1: [2.05]}))
+
+        result = BenchmarkComparison.compare(baseline, comparison, noise_threshold=0.01)
+        self.assertEqual(result.queries[0].baselines[0].status, ChangeStatus.SLOWER)
+        result = BenchmarkComparison.compare(
+            baseline, comparison, noise_threshold=0.01, noise_floor_ms=0.1
+        )
+        self.assertEqual(result.queries[0].baselines[0].status, ChangeStatus.NO_CHANGE)
+
+    def test_compare_totals_of_paired_queries(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0], 2: [20.0], 3: [30.0]}))
+        comparison = self.write("branch", run_of({1: [10.0], 2: [20.0]}))
+
+        # query 3 has nothing to compare with, so it's out of the totals and the averages
+        result = BenchmarkComparison.compare(baseline, comparison)
+        self.assertEqual(len(result.queries), 2)
+        self.assertEqual((result.baselines[0].total, result.baselines[0].average), (30.0, 15.0))
+        self.assertEqual((result.total, result.average), (30.0, 15.0))
+
+
+    def test_compare_numeric_order(self) -> None:
+        times = {"Query 10": [10.0], "Query 2": [20.0], 1: [30.0]}
+        baseline = self.write("main", run_of(times))
+        comparison = self.write("branch", run_of(times))
+
+        result = BenchmarkComparison.compare(baseline, comparison)
+        self.assertEqual([query.query for query in result.queries], [1, "Query 2", "Query 10"])
+
+    def test_compare_missing_query(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0], 2: [20.0], 3: [30.0]}))
+        comparison = self.write("branch", run_of({1: [10.0], 3: [30.0], 4: [40.0]}))
+
+        stderr = io.StringIO()
+        with contextlib.redirect_stderr(stderr):
+            result = BenchmarkComparison.compare(baseline, comparison)
+        # the queries are paired by the id, not by the position
+        self.assertEqual([query.query for query in result.queries], [1, 3])
+        self.assertEqual([query.measure for query in result.queries], [10.0, 30.0])
+        self.assertIn(f"{baseline}: no result of query 4", stderr.getvalue())
+        self.assertIn(f"{comparison}: no result of query 2", stderr.getvalue())
+
+
+class ToleranceTest(CompareTestCase):
+    def test_tolerance_takes_precedence(self) -> None:
+        self.assertEqual(reported_noise_threshold(0.05, None), 0.05)
+        # whether it's wider or narrower than the noise threshold
+        self.assertEqual(reported_noise_threshold(0.05, 10), 0.1)
+        self.assertEqual(reported_noise_threshold(0.05, 1), 0.01)
+
+    def test_tolerance_reclassifies_borderline_queries(self) -> None:
+        baseline = self.write("main", run_of({1: [100.0], 2: [100.0], 3: [100.0]}))
+        comparison = self.write("branch", run_of({1: [103.0], 2: [108.0], 3: [120.0]}))
+
+        summary = BenchmarkComparison.compare(baseline, comparison, 0.02).baselines[0]
+        self.assertEqual((summary.slower, summary.no_change), (3, 0))
+        result = BenchmarkComparison.compare(baseline, comparison, 0.02, tolerance=10)
+        self.assertEqual((result.baselines[0].slower, result.baselines[0].no_change), (1, 2))
+        self.assertEqual(result.tolerance, 10)
+        # the raw status is still by the noise threshold
+        self.assertEqual(
+            [query.baselines[0].raw_status for query in result.queries],
+            [ChangeStatus.SLOWER] * 3,
+        )
+
+    def test_narrower_tolerance(self) -> None:
+        baseline = self.write("main", run_of({1: [100.0]}))
+        comparison = self.write("branch", run_of({1: [103.0]}))
+
+        # within the default noise threshold of 5%, but not the tolerance of 1%
+        (query,) = BenchmarkComparison.compare(baseline, comparison, tolerance=1).queries
+        self.assertEqual(query.baselines[0].status, ChangeStatus.SLOWER)
+        self.assertEqual(query.baselines[0].raw_status, ChangeStatus.NO_CHANGE)
+        self.assertEqual(query.baselines[0].text(verbose=True), "1.03x slower (no change)")
+
+    def test_verbose_raw_change(self) -> None:
+        baseline = self.write("main", run_of({1: [100.0]}))
+        comparison = self.write("branch", run_of({1: [103.0]}))
+
+        printed = self.printed([baseline], comparison, 0.01, tolerance=5)
+        self.assertNotIn("1.03x slower", printed)
+        printed = self.printed([baseline], comparison, 0.01, tolerance=5, verbose=True)
+        self.assertIn("no change (1.03x slower)", printed)
+        self.assertIn("+/- 5%", printed)
+
+
+class ReportTest(CompareTestCase):
+    def test_changed_only(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0], 2: [20.0], 3: [30.0]}))
+        comparison = self.write("branch", run_of({1: [5.0], 2: [20.0], 3: [30.0]}))
+
+        printed = self.printed([baseline], comparison, 0.05, changed_only=True)
+        self.assertIn("2 unchanged queries hidden", printed)
+        self.assertIn("Q1", printed)
+        self.assertNotIn("Q2", printed)
+        self.assertNotIn("Q3", printed)
+        # the summary still counts the hidden queries
+        self.assertIn("60.00ms", printed)
+        self.assertRegex(printed, r"Queries with No Change\W+2")
+
+    def test_multiple_baselines(self) -> None:
+        main_path = self.write("main", run_of({1: [10.0]}))
+        release = self.write("release", run_of({1: [20.0]}))
+        comparison = self.write("branch", run_of({1: [10.0]}))
+
+        printed = self.printed([main_path, release], comparison, 0.05)
+        self.assertIn("Change (main)", printed)
+        self.assertIn("Change (release)", printed)
+        self.assertIn("+2.00x faster", printed)
+        self.assertIn("vs release", printed)
+
+    def test_stable_order(self) -> None:
+        baseline = self.write("main", run_of({2: [20.0], 1: [10.0]}))
+        comparison = self.write("branch", run_of({1: [10.0], 2: [20.0]}))
+
+        printed = self.printed([baseline], comparison, 0.05)
+        self.assertEqual(printed, self.printed([baseline], comparison, 0.05))
+        self.assertLess(printed.index("Q1"), printed.index("Q2"))
+
+    def test_filter_tag(self) -> None:
+        tags = {1: ["cte"], 2: [], 3: ["cte", "union"]}
+        baseline = self.write("main", run_of({1: [10.0], 2: [20.0], 3: [30.0]}, tags=tags))
+        comparison = self.write("branch", run_of({1: [10.0], 2: [20.0], 3: [30.0]}, tags=tags))
+
+        result = BenchmarkComparison.compare(baseline, comparison, filter_tag="cte")
+        self.assertEqual([query.query for query in result.queries], [1, 3])
+        self.assertEqual(result.baselines[0].total, 40.0)
+        with self.assertRaisesRegex(BenchmarkLoadError, "no query is tagged join"):
+            BenchmarkComparison.compare(baseline, comparison, filter_tag="join")
+
+    def test_counter(self) -> None:
+        baseline = self.write("main", run_of({1: [1.0]}, instructions={1: [1000]}))
+        comparison = self.write("branch", run_of({1: [1.0]}, instructions={1: [2000]}))
+
+        printed = self.printed([baseline], comparison, 0.05, counter="instructions")
+        self.assertIn("Total Instructions (main)", printed)
+        self.assertIn("1,000", printed)
+        self.assertIn("2.00x slower", printed)
+        without_counts = self.write("old", run_of({1: [1.0]}))
+        with self.assertRaisesRegex(BenchmarkLoadError, "has no instruction count"):
+            BenchmarkComparison.compare(without_counts, comparison, counter="instructions")
+
+    def test_noise_floor(self) -> None:
+        baseline = self.write("main", run_of({1: [2.0]}))
+        comparison = self.write("branch", run_of({1: [2.05]}))
+
+        self.assertIn("1.02x slower", self.printed([baseline], comparison, 0.01))
+        printed = self.printed([baseline], comparison, 0.01, noise_floor_ms=0.1)
+        self.assertIn("no change", printed)
+        self.assertNotIn("slower", printed)
+
+    def test_baseline_ref(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0]}))
+        comparison = self.write("branch", run_of({1: [10.0]}))
+        git = ["git", "-C", str(baseline.parent)]
+        subprocess.run([*git, "init", "-q"], check=True)
+        subprocess.run([*git, "add", baseline.name], check=True)
+        subprocess.run(
+            [*git, "-c", "user.name=test", "-c", "user.email=test@test", "commit", "-qm", "base"],
+            check=True,
+        )
+        # the working tree changes after the commit
+        baseline.write_text(json.dumps(run_of({1: [5.0]})))
+
+        result = BenchmarkComparison.compare(baseline, comparison, baseline_ref="HEAD")
+        self.assertEqual(result.baselines[0].header, "main@HEAD")
+        self.assertEqual(result.baselines[0].total, 10.0)
+        self.assertEqual(BenchmarkComparison.compare(baseline, comparison).baselines[0].total, 5.0)
+        with self.assertRaisesRegex(BenchmarkLoadError, "not available at missing"):
+            BenchmarkComparison.compare(baseline, comparison, baseline_ref="missing")
+
+    def test_dropped_iterations(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0, math.nan]}))
+        comparison = self.write("branch", run_of({1: [10.0]}))
+
+        stderr = io.StringIO()
+        with contextlib.redirect_stderr(stderr):
+            result = BenchmarkComparison.compare(baseline, comparison)
+        self.assertIn("dropped 1 non-finite iteration(s) of query 1", stderr.getvalue())
+        self.assertEqual(result.queries[0].baselines[0].metrics.dropped, 1)
+
+    def test_negative_tolerance(self) -> None:
+        baseline = self.write("main", run_of({1: [10.0]}))
+        comparison = self.write("branch", run_of({1: [10.0]}))
+
+        argv = ["compare.py", str(baseline), str(comparison), "--tolerance", "-1"]
+        with mock.patch.object(sys, "argv", argv), contextlib.redirect_stderr(io.StringIO()):
+            with self.assertRaises(SystemExit):
+                main()
+
+
+class LoadTest(CompareTestCase):
+    def load(self, data: Any) -> BenchmarkRun:
+        path = self.root / "results.json"
+        path.write_text(json.dumps(data))
+        return BenchmarkRun.load_from_file(path)
+
+    def test_schema_version(self) -> None:
+        # the files before the versioning have the same shape
+        data = run_of({1: [10.0]})
+        del data["schema_version"]
+        self.assertEqual(self.load(data).queries[0].execution_time, 10.0)
+        with self.assertRaisesRegex(BenchmarkLoadError, "unsupported benchmark schema v99"):
+            self.load({**run_of({1: [10.0]}), "schema_version": 99})
+
+    def test_malformed(self) -> None:
+        data = run_of({1: [10.0], 2: [20.0]})
+        del data["queries"][1]["iterations"][0]["elapsed"]
+        with self.assertRaisesRegex(
+            BenchmarkLoadError, r"query\[1\]\.iterations\[0\]\.elapsed missing or not a number"
+        ):
+            self.load(data)
+        with self.assertRaisesRegex(BenchmarkLoadError, "context.num_cpus missing"):
+            self.load({**data, "context": {**CONTEXT, "num_cpus": "1"}})
+        path = self.root / "broken.json"
+        path.write_text("{")
+        with self.assertRaisesRegex(BenchmarkLoadError, "broken.json"):
+            BenchmarkRun.load_from_file(path)
+
+    def test_non_finite(self) -> None:
+        metrics = QueryMetrics.from_iterations([3.0, math.nan, 1.0, math.inf])
+        self.assertEqual((metrics.iterations, metrics.dropped), (2, 2))
+        self.assertEqual((metrics.min, metrics.max, metrics.median), (1.0, 3.0, 2.0))
+        with self.assertRaisesRegex(BenchmarkLoadError, "no finite elapsed time"):
+            self.load(run_of({1: [math.nan]}))
+
+    def test_dir(self) -> None:
+        directory = self.root / "per-query"
+        directory.mkdir()
+        for query, elapsed in [(1, 10.0), (2, 20.0)]:
+            (directory / f"Query_{query}.json").write_text(json.dumps(run_of({query: [elapsed]})))
+
+        run = BenchmarkRun.load_from_file(directory)
+        self.assertEqual([query.query for query in run.queries], [1, 2])
+        (self.root / "empty").mkdir()
+        with self.assertRaisesRegex(BenchmarkLoadError, "no json file"):
+            BenchmarkRun.load_from_file(self.root / "empty")
+
+    def test_jsonl(self) -> None:
+        header = {"schema_version": 4, "context": CONTEXT}
+
+        def line_of(query: str, elapsed: float, suite_run: int) -> str:
+            iterations = [{"elapsed": elapsed, "suite_run": suite_run}]
+            return json.dumps({"query": query, "iterations": iterations, "start_time": 0})
+
+        lines = [
+            json.dumps(header),
+            line_of("q1", 10.0, 0),
+            line_of("q2", 20.0, 0),
+            # the second repetition of the suite
+            line_of("q1", 8.0, 1),
+            # cut by a crash
+            '{"query": "q2", "iter',
+        ]
+        path = self.root / "results.jsonl"
+        path.write_text("\n".join(lines))
+
+        stderr = io.StringIO()
+        with contextlib.redirect_stderr(stderr):
+            run = BenchmarkRun.load_from_file(path)
+        self.assertIn("dropped the incomplete last line", stderr.getvalue())
+        self.assertEqual([query.query for query in run.queries], ["q1", "q2"])
+        q1 = run.queries[0]
+        self.assertEqual([iteration.suite_run for iteration in q1.iterations], [0, 1])
+        self.assertEqual(q1.execution_time, 8.0)
+
+
+class FormatTest(unittest.TestCase):
+    def test_format_ms(self) -> None:
+        self.assertEqual(format_ms(12.345), "12.35")
+        self.assertEqual(format_ms(0.003), "0.003")
+        self.assertEqual(format_ms(0.0001234), "0.000123")
+        self.assertEqual(format_ms(12345678.0), "1.23e7")
+        self.assertEqual(format_ms(-0.001), "-0.001")
+        self.assertEqual(format_ms(-0.0), "0.00")
+        self.assertEqual(format_ms(math.nan), "NaN")
+
+    def test_change_status(self) -> None:
+        for status in ChangeStatus:
+            self.assertEqual(ChangeStatus.from_str(str(status)), status)
+            self.assertEqual(ChangeStatus.from_str(status.name), status)
+        self.assertEqual(ChangeStatus.from_str("No_Change"), ChangeStatus.NO_CHANGE)
+        with self.assertRaisesRegex(ValueError, "unknown change status 'same'"):
+            ChangeStatus.from_str("same")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -1,10 +1,12 @@
 use std::{
     collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
     ops::ControlFlow,
     sync::Arc,
 };
 
 use datafusion::{
+    arrow::datatypes::DataType,
     common::{plan_err, Result, Spans},
     error::DataFusionError,
     prelude::Expr,
@@ -24,7 +26,7 @@ use wren_core_base::mdl::RowLevelAccessControl;
 use wren_core_base::mdl::{Column, Model, SessionProperty};
 
 use crate::{
-    logical_plan::utils::from_qualified_name,
+    logical_plan::utils::{from_qualified_name, try_map_data_type},
     mdl::{context::SessionPropertiesRef, Dataset, SessionStateRef},
     AnalyzedWrenMDL,
 };
@@ -43,10 +45,14 @@ pub fn collect_condition(
         .build()?;
     let expr = parser.parse_expr()?;
     let _ = visit_expressions(&expr, |expr| {
+        if let Some(session_property) = session_property_name(expr) {
+            session_properties.insert(session_property);
+            return ControlFlow::Continue(());
+        }
         // TODO: consider CompoundIdentifier and CompoundFieldAccess
         if let ast::Expr::Identifier(ast::Ident { value, .. }) = expr {
             if !value.starts_with("@") {
-                if model.get_column(value).is_none() {
+                if model.column(value).is_none() {
                     error = Some(plan_err!(
                         "The column {} is not in the model {}",
                         value,
@@ -59,14 +65,6 @@ pub fn collect_condition(
                     name: value.to_string(),
                     spans: Spans::new(),
                 }));
-            } else {
-                let session_property = value
-                    .trim_start_matches("@")
-                    .to_string()
-                    .to_ascii_lowercase();
-                if !session_properties.contains(&session_property) {
-                    session_properties.insert(session_property);
-                }
             }
         }
         ControlFlow::Continue(())
@@ -87,12 +85,24 @@ pub fn collect_condition(
 /// Check if the properties used in the condition are defined in the session properties.
 #[allow(dead_code)]
 pub fn validate_rlac_rule(rule: &RowLevelAccessControl, model: &Model) -> Result<()> {
+    validate_rlac_rule_with_declared_keys(rule, model, None)
+}
+
+/// Validate the definition of row level access control rules like [validate_rlac_rule].
+/// If `declared_session_keys` is provided, also check if the required properties of the rule
+/// are declared. It's used to catch a typo'd key that would never match any session property.
+pub fn validate_rlac_rule_with_declared_keys(
+    rule: &RowLevelAccessControl,
+    model: &Model,
+    declared_session_keys: Option<&[String]>,
+) -> Result<()> {
     let RowLevelAccessControl {
         condition,
         required_properties,
         name,
     } = rule;
     let (_, session_properties) = collect_condition(model, condition)?;
+    check_condition_types(model, rule)?;
 
     let required_properties: Vec<_> = required_properties
         .iter()
@@ -114,6 +124,147 @@ pub fn validate_rlac_rule(rule: &RowLevelAccessControl, model: &Model) -> Result
             name
         );
     }
+    if let Some(declared_session_keys) = declared_session_keys {
+        validate_declared_session_keys(
+            name,
+            &rule.required_properties,
+            declared_session_keys,
+        )?;
+    }
+    Ok(())
+}
+
+/// Check if the literals compared with the columns in the condition match the declared
+/// types of the columns, e.g. `id = 'abc'` can never be true for an integer column `id`.
+fn check_condition_types(model: &Model, rule: &RowLevelAccessControl) -> Result<()> {
+    let dialect = GenericDialect {};
+    let mut parser = DFParserBuilder::new(&rule.condition)
+        .with_dialect(&dialect)
+        .build()?;
+    let expr = parser.parse_expr()?;
+    let mut error = None;
+    let _ = visit_expressions(&expr, |expr| {
+        let ast::Expr::BinaryOp { left, op, right } = expr else {
+            return ControlFlow::Continue(());
+        };
+        if !matches!(
+            op,
+            ast::BinaryOperator::Eq
+                | ast::BinaryOperator::NotEq
+                | ast::BinaryOperator::Lt
+                | ast::BinaryOperator::LtEq
+                | ast::BinaryOperator::Gt
+                | ast::BinaryOperator::GtEq
+        ) {
+            return ControlFlow::Continue(());
+        }
+        let (ident, value) = match (left.as_ref(), right.as_ref()) {
+            (ast::Expr::Identifier(ident), ast::Expr::Value(value))
+            | (ast::Expr::Value(value), ast::Expr::Identifier(ident)) => {
+                (ident, &value.value)
+            }
+            _ => return ControlFlow::Continue(()),
+        };
+        let Some(column) = model.column(&ident.value) else {
+            return ControlFlow::Continue(());
+        };
+        let Ok(data_type) = try_map_data_type(&column.r#type) else {
+            return ControlFlow::Continue(());
+        };
+        if !is_comparable(&data_type, value) {
+            error = Some(plan_err!(
+                "The column {} of type {} can't be compared with {} in `{}` rule",
+                column.name(),
+                column.r#type,
+                value,
+                rule.name
+            ));
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    });
+    error.unwrap_or(Ok(()))
+}
+
+/// Check if the literal could be coerced to the type of the column.
+fn is_comparable(data_type: &DataType, value: &ast::Value) -> bool {
+    match value {
+        ast::Value::Boolean(_) => matches!(data_type, DataType::Boolean),
+        ast::Value::Number(_, _) => !matches!(data_type, DataType::Boolean),
+        ast::Value::SingleQuotedString(value) => {
+            if data_type.is_numeric() {
+                value.trim().parse::<f64>().is_ok()
+            } else if matches!(data_type, DataType::Boolean) {
+                value.parse::<bool>().is_ok()
+            } else {
+                true
+            }
+        }
+        _ => true,
+    }
+}
+
+/// The validation failure of a rule reported by [validate_rlac_rules].
+#[derive(Debug)]
+pub struct RuleError {
+    pub rule_name: String,
+    pub error: DataFusionError,
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule_name, self.error)
+    }
+}
+
+/// Validate all the row level access control rules of the model like [validate_rlac_rule].
+/// It doesn't stop at the first invalid rule. Every failure is returned with the rule name.
+pub fn validate_rlac_rules(
+    rules: &[RowLevelAccessControl],
+    model: &Model,
+) -> Result<(), Vec<RuleError>> {
+    let errors: Vec<_> = rules
+        .iter()
+        .filter_map(|rule| {
+            validate_rlac_rule(rule, model)
+                .err()
+                .map(|error| RuleError {
+                    rule_name: rule.name.clone(),
+                    error,
+                })
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Check if all the required properties of a RLAC or CLAC rule are declared in the manifest.
+/// The keys are compared case-insensitively.
+pub fn validate_declared_session_keys(
+    rule_name: &str,
+    required_properties: &[SessionProperty],
+    declared_session_keys: &[String],
+) -> Result<()> {
+    let undeclared: Vec<_> = required_properties
+        .iter()
+        .map(|property| property.normalized_name())
+        .filter(|name| {
+            !declared_session_keys
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(name))
+        })
+        .map(|name| format!("@{name}"))
+        .collect();
+    if !undeclared.is_empty() {
+        return plan_err!(
+            "The session property {} is used for `{}` rule, but not declared in the manifest",
+            undeclared.join(", "),
+            rule_name
+        );
+    }
     Ok(())
 }
 
@@ -124,12 +275,25 @@ pub fn build_filter_expression(
     properties: &SessionPropertiesRef,
     rule: &RowLevelAccessControl,
 ) -> Result<Expr> {
+    let expr = resolve_rule_condition(properties, rule)?;
+    // The condition could contains the hidden columns, so we need to build the shcmea with hidden columns
+    let df_schema = Dataset::Model(Arc::clone(&model)).to_qualified_schema(false)?;
+    session_state
+        .read()
+        .create_logical_expr(&expr.to_string(), &df_schema)
+}
+
+/// Replace the session properties in the condition of the rule by their values or defaults.
+pub(crate) fn resolve_rule_condition(
+    properties: &SessionPropertiesRef,
+    rule: &RowLevelAccessControl,
+) -> Result<ast::Expr> {
     let RowLevelAccessControl {
         condition,
         required_properties,
         ..
     } = rule;
-    let mut error: Option<Result<Expr, DataFusionError>> = None;
+    let mut error: Option<Result<ast::Expr, DataFusionError>> = None;
     let dialect = GenericDialect {};
     let mut parser = DFParserBuilder::new(condition)
         .with_dialect(&dialect)
@@ -137,57 +301,53 @@ pub fn build_filter_expression(
     let mut expr = parser.parse_expr()?;
 
     let _ = visit_expressions_mut(&mut expr, |expr| {
-        if let ast::Expr::Identifier(ast::Ident { value, .. }) = expr {
-            if value.starts_with("@") {
-                let property_name =
-                    value.trim_start_matches("@").to_string().to_lowercase();
-                let Some(property_value) = properties.get(&property_name).or_else(|| {
-                    required_properties
-                        .iter()
-                        .filter(|r| !r.required && r.normalized_name().eq(&property_name))
-                        .map(|r| &r.default_expr)
-                        .next()
-                }) else {
-                    error = Some(plan_err!(
-                        "The session property {} is required for `{}` rule but not found in the session properties",
-                        property_name,
-                        rule.name
-                    ));
-                    return ControlFlow::Break(());
-                };
+        if let Some(property_name) = session_property_name(expr) {
+            let Some(property_value) = properties.get(&property_name).or_else(|| {
+                required_properties
+                    .iter()
+                    .filter(|r| !r.required && r.normalized_name().eq(&property_name))
+                    .map(|r| &r.default_expr)
+                    .next()
+            }) else {
+                error = Some(plan_err!(
+                    "The session property {} is required for `{}` rule but not found in the session properties",
+                    property_name,
+                    rule.name
+                ));
+                return ControlFlow::Break(());
+            };
 
-                let Some(property_value) = property_value else {
-                    error = Some(plan_err!(
-                        "The session property {} is required for `{}` rule and should not be null",
-                        property_name,
-                        rule.name
-                    ));
-                    return ControlFlow::Break(());
-                };
+            let Some(property_value) = property_value else {
+                error = Some(plan_err!(
+                    "The session property {} is required for `{}` rule and should not be null",
+                    property_name,
+                    rule.name
+                ));
+                return ControlFlow::Break(());
+            };
 
-                if property_value.trim().is_empty() {
+            if property_value.trim().is_empty() {
+                error = Some(plan_err!(
+                    "The session property {} is required for `{}` rule and should not be empty",
+                    property_name,
+                    rule.name
+                ));
+                return ControlFlow::Break(());
+            }
+
+            match parse_expr(property_value) {
+                Ok(parsed_expr) => {
+                    *expr = parsed_expr.expr;
+                }
+                Err(e) => {
                     error = Some(plan_err!(
-                        "The session property {} is required for `{}` rule and should not be empty",
+                        "The session property {} is required for `{}` rule but not valid: {}",
                         property_name,
-                        rule.name
+                        rule.name,
+                        e
                     ));
                     return ControlFlow::Break(());
                 }
-
-                match parse_expr(property_value) {
-                    Ok(parsed_expr) => {
-                        *expr = parsed_expr.expr;
-                    }
-                    Err(e) => {
-                        error = Some(plan_err!(
-                            "The session property {} is required for `{}` rule but not valid: {}",
-                            property_name,
-                            rule.name,
-                            e
-                        ));
-                        return ControlFlow::Break(());
-                    }
-                }
             }
         }
         ControlFlow::Continue(())
@@ -196,11 +356,26 @@ pub fn build_filter_expression(
     if let Some(error) = error {
         return error;
     }
-    // The condition could contains the hidden columns, so we need to build the shcmea with hidden columns
-    let df_schema = Dataset::Model(Arc::clone(&model)).to_qualified_schema(false)?;
-    session_state
-        .read()
-        .create_logical_expr(&expr.to_string(), &df_schema)
+    Ok(expr.expr)
+}
+
+/// Return the normalized name if the expression refers to a session property.
+///
+/// A session property is referred by an identifier starting with `@`. The name could be
+/// namespaced by dots, e.g. `@auth.tenant_id`, which is parsed as a compound identifier,
+/// or be quoted as a whole, e.g. `"@auth.tenant_id"`. Both are resolved to the flat key
+/// `auth.tenant_id` of the session properties.
+fn session_property_name(expr: &ast::Expr) -> Option<String> {
+    let name = match expr {
+        ast::Expr::Identifier(ast::Ident { value, .. }) => value.clone(),
+        ast::Expr::CompoundIdentifier(idents) => idents
+            .iter()
+            .map(|ident| ident.value.as_str())
+            .collect::<Vec<_>>()
+            .join("."),
+        _ => return None,
+    };
+    name.strip_prefix("@").map(|name| name.to_lowercase())
 }
 
 fn parse_expr(expr: &str) -> Result<ExprWithAlias> {
@@ -326,8 +501,10 @@ pub(crate) fn validate_clac_rule(
         if let Some(analyzed_mdl) = analyzed_mdl {
             let qualified_col =
                 from_qualified_name(&analyzed_mdl.wren_mdl, model_name, column.name());
-            let Some(required_fields) =
-                analyzed_mdl.lineage.required_fields_map.get(&qualified_col)
+            let Some(required_fields) = analyzed_mdl
+                .lineage()?
+                .required_fields_map
+                .get(&qualified_col)
             else {
                 return plan_err!("Required fields not found for {}", qualified_col);
             };
@@ -395,7 +572,10 @@ mod test {
         collect_condition, validate_rule,
     };
 
-    use super::{build_filter_expression, validate_rlac_rule};
+    use super::{
+        build_filter_expression, validate_rlac_rule,
+        validate_rlac_rule_with_declared_keys, validate_rlac_rules,
+    };
 
     #[test]
     pub fn test_collect_condition() -> Result<()> {
@@ -803,6 +983,58 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    pub fn test_dotted_session_property() -> Result<()> {
+        let ctx = SessionContext::new();
+        let state = ctx.state_ref();
+        let model = ModelBuilder::new("m1")
+            .column(ColumnBuilder::new("id", "int").build())
+            .column(ColumnBuilder::new("area_code", "varchar").build())
+            .build();
+
+        let (_, session_properties) = collect_condition(
+            &model,
+            "id = @auth.tenant_id AND area_code = \"@jwt.claims.region\"",
+        )?;
+        let session_properties: HashSet<_> = session_properties.into_iter().collect();
+        assert_eq!(
+            session_properties,
+            HashSet::from([
+                "auth.tenant_id".to_string(),
+                "jwt.claims.region".to_string()
+            ])
+        );
+
+        let headers = Arc::new(build_headers(&[
+            ("auth.tenant_id".to_string(), Some("1".to_string())),
+            ("jwt.claims.region".to_string(), Some("'tw'".to_string())),
+        ]));
+        let rule = RowLevelAccessControl {
+            condition: "id = @auth.tenant_id AND area_code = \"@jwt.claims.region\""
+                .to_string(),
+            required_properties: vec![
+                SessionProperty::new_required("auth.tenant_id"),
+                SessionProperty::new_required("jwt.claims.region"),
+            ],
+            name: "test".to_string(),
+        };
+        let expr = build_filter_expression(&state, Arc::clone(&model), &headers, &rule)?;
+        assert_snapshot!(expr_to_sql(&expr)?, @"m1.id = 1 AND m1.area_code = 'tw'");
+
+        // the dotted key is resolved exactly, the prefix isn't a match
+        let headers = Arc::new(build_headers(&[
+            ("auth".to_string(), Some("1".to_string())),
+            ("jwt.claims.region".to_string(), Some("'tw'".to_string())),
+        ]));
+        match build_filter_expression(&state, Arc::clone(&model), &headers, &rule) {
+            Err(error) => {
+                assert_snapshot!(error.to_string(), @"Error during planning: The session property auth.tenant_id is required for `test` rule but not found in the session properties");
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
     #[test]
     pub fn test_validate_rlac_rule() -> Result<()> {
         let model = ModelBuilder::new("m1")
@@ -868,6 +1100,106 @@ mod test {
             _ => panic!("should be error"),
         }
 
+        // the literal is checked against the type of the column
+        let rule = RowLevelAccessControl {
+            condition: "id = '1' AND name = 'abc'".to_string(),
+            required_properties: vec![],
+            name: "test".to_string(),
+        };
+        validate_rlac_rule(&rule, &model)?;
+
+        let rule = RowLevelAccessControl {
+            condition: "name = @session_name OR 'abc' = id".to_string(),
+            required_properties: vec![SessionProperty::new_required("SESSION_NAME")],
+            name: "test".to_string(),
+        };
+        match validate_rlac_rule(&rule, &model) {
+            Err(error) => {
+                assert_snapshot!(error.message(), @"The column id of type int can't be compared with 'abc' in `test` rule");
+            }
+            _ => panic!("should be error"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validate_rlac_rule_with_declared_keys() -> Result<()> {
+        let model = ModelBuilder::new("m1")
+            .column(ColumnBuilder::new("id", "int").build())
+            .build();
+        let declared_session_keys = vec!["session_id".to_string()];
+
+        let rule = RowLevelAccessControl {
+            condition: "id = @session_id".to_string(),
+            required_properties: vec![SessionProperty::new_required("SESSION_ID")],
+            name: "test".to_string(),
+        };
+        validate_rlac_rule_with_declared_keys(
+            &rule,
+            &model,
+            Some(&declared_session_keys),
+        )?;
+
+        let rule = RowLevelAccessControl {
+            condition: "id = @sesion_id".to_string(),
+            required_properties: vec![SessionProperty::new_required("sesion_id")],
+            name: "test".to_string(),
+        };
+        // without the declared keys, the typo can't be detected
+        validate_rlac_rule(&rule, &model)?;
+        match validate_rlac_rule_with_declared_keys(
+            &rule,
+            &model,
+            Some(&declared_session_keys),
+        ) {
+            Err(error) => {
+                assert_snapshot!(error.message(), @"The session property @sesion_id is used for `test` rule, but not declared in the manifest");
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validate_rlac_rules() -> Result<()> {
+        let model = ModelBuilder::new("m1")
+            .column(ColumnBuilder::new("id", "int").build())
+            .build();
+        let rule =
+            |name: &str, condition: &str, properties: &[&str]| RowLevelAccessControl {
+                condition: condition.to_string(),
+                required_properties: properties
+                    .iter()
+                    .map(|p| SessionProperty::new_required(p))
+                    .collect(),
+                name: name.to_string(),
+            };
+
+        let valid = vec![
+            rule("r1", "id = @session_id", &["session_id"]),
+            rule("r2", "id > 0", &[]),
+        ];
+        assert!(validate_rlac_rules(&valid, &model).is_ok());
+
+        let mixed = vec![
+            rule("r1", "id = @session_id", &["session_id"]),
+            rule("unknown_column", "name = @session_id", &["session_id"]),
+            rule("r2", "id > 0", &[]),
+            rule("missing_property", "id = @session_id", &[]),
+        ];
+        let Err(errors) = validate_rlac_rules(&mixed, &model) else {
+            panic!("should be error");
+        };
+        let messages = errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot!(messages, @r"
+        unknown_column: Error during planning: The column name is not in the model m1
+        missing_property: Error during planning: The session property @session_id is used for `missing_property` rule, but not found in the session properties
+        ");
         Ok(())
     }
 }
@@ -37,15 +37,15 @@ impl AnalyzerRule for ExpandWrenViewRule {
         let plan = plan
             .transform_up_with_subqueries(|plan| match &plan {
                 LogicalPlan::TableScan(table_scan) => {
+                    let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+                    let table_name = table_scan.table_name.table();
+                    // the metric is registered as a view of its aggregation
                     if belong_to_mdl(
-                        &self.analyzed_wren_mdl.wren_mdl(),
+                        &wren_mdl,
                         table_scan.table_name.clone(),
                         Arc::clone(&self.session_state),
-                    ) && self
-                        .analyzed_wren_mdl
-                        .wren_mdl()
-                        .get_view(table_scan.table_name.table())
-                        .is_some()
+                    ) && (wren_mdl.get_view(table_name).is_some()
+                        || wren_mdl.get_metric(table_name).is_some())
                     {
                         if let Some(logical_plan) = table_scan.source.get_logical_plan() {
                             let subquery =
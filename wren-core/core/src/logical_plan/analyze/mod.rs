@@ -3,6 +3,7 @@ pub mod expand_view;
 pub mod model_anlayze;
 pub mod model_generation;
 pub mod plan;
+pub mod plan_node_limit;
 mod relation_chain;
 mod scope;
 
@@ -262,6 +262,21 @@ impl ModelAnalyzeRule {
                     })?;
                 Ok(Transformed::no(plan))
             }
+            // A LATERAL subquery is an input of the join instead of an expression, so it's
+            // analyzed in the current scope. The columns of the preceding tables it references
+            // are required by their models.
+            LogicalPlan::Subquery(Subquery {
+                outer_ref_columns, ..
+            }) => {
+                outer_ref_columns.iter().try_for_each(|expr| {
+                    self.collect_required_column(
+                        expr.clone(),
+                        scope_manager,
+                        current_scope_id,
+                    )
+                })?;
+                Ok(Transformed::no(plan))
+            }
             _ => Ok(Transformed::no(plan)),
         }
     }
@@ -11,6 +11,7 @@ use datafusion::common::{
     DFSchemaRef, TableReference,
 };
 use datafusion::error::{DataFusionError, Result};
+use datafusion::functions::core::expr_fn::coalesce;
 use datafusion::logical_expr::expr::WildcardOptions;
 use datafusion::logical_expr::utils::find_aggregate_exprs;
 use datafusion::logical_expr::{
@@ -29,8 +30,8 @@ use crate::mdl::context::SessionPropertiesRef;
 use crate::mdl::lineage::DatasetLink;
 use crate::mdl::manifest::{JoinType, Model};
 use crate::mdl::utils::{
-    create_remote_expr_for_model, create_wren_calculated_field_expr,
-    create_wren_expr_for_model, is_dag, quoted,
+    create_default_value_expr, create_remote_expr_for_model,
+    create_wren_calculated_field_expr, create_wren_expr_for_model, is_dag, quoted,
 };
 use crate::mdl::Dataset;
 use crate::mdl::{AnalyzedWrenMDL, ColumnReference, SessionStateRef};
@@ -218,7 +219,7 @@ impl ModelPlanNodeBuilder {
 
                 let Some(column_graph) = self
                     .analyzed_wren_mdl
-                    .lineage()
+                    .lineage()?
                     .required_dataset_topo
                     .get(&qualified_column)
                 else {
@@ -263,7 +264,7 @@ impl ModelPlanNodeBuilder {
                         )));
                 } else {
                     merge_graph(&mut self.directed_graph, column_graph)?;
-                    if self.is_contain_calculation_source(&qualified_column) {
+                    if self.is_contain_calculation_source(&qualified_column)? {
                         collect_partial_model_plan_for_calculation(
                             Arc::clone(&self.analyzed_wren_mdl),
                             Arc::clone(&self.session_state),
@@ -448,9 +449,10 @@ impl ModelPlanNodeBuilder {
         !find_aggregate_exprs(&[expr]).is_empty()
     }
 
-    fn is_contain_calculation_source(&self, qualified_column: &DFColumn) -> bool {
-        self.analyzed_wren_mdl
-            .lineage()
+    fn is_contain_calculation_source(&self, qualified_column: &DFColumn) -> Result<bool> {
+        Ok(self
+            .analyzed_wren_mdl
+            .lineage()?
             .required_fields_map
             .get(qualified_column)
             .map(|required_columns| {
@@ -462,7 +464,7 @@ impl ModelPlanNodeBuilder {
                         .is_some()
                 })
             })
-            .unwrap_or_default()
+            .unwrap_or_default())
     }
 
     fn create_partial_calculation(
@@ -474,7 +476,7 @@ impl ModelPlanNodeBuilder {
     ) -> Result<WrenPlan> {
         let Some(column_graph) = self
             .analyzed_wren_mdl
-            .lineage()
+            .lineage()?
             .required_dataset_topo
             .get(qualified_column)
         else {
@@ -490,7 +492,7 @@ impl ModelPlanNodeBuilder {
 
         let mut partial_model_required_fields = HashMap::new();
 
-        if self.is_contain_calculation_source(qualified_column) {
+        if self.is_contain_calculation_source(qualified_column)? {
             collect_partial_model_plan_for_calculation(
                 Arc::clone(&self.analyzed_wren_mdl),
                 Arc::clone(&self.session_state),
@@ -568,7 +570,7 @@ fn collect_partial_model_plan_for_calculation(
     required_fields: &mut HashMap<TableReference, BTreeSet<OrdExpr>>,
 ) -> Result<()> {
     let Some(set) = analyzed_wren_mdl
-        .lineage()
+        .lineage()?
         .required_fields_map
         .get(qualified_column)
     else {
@@ -612,7 +614,7 @@ fn collect_partial_model_required_fields(
     required_fields: &mut HashMap<TableReference, BTreeSet<OrdExpr>>,
 ) -> Result<()> {
     let Some(set) = analyzed_wren_mdl
-        .lineage()
+        .lineage()?
         .required_fields_map
         .get(qualified_column)
     else {
@@ -656,7 +658,7 @@ fn collect_model_required_fields(
     required_fields: &mut HashMap<TableReference, BTreeSet<OrdExpr>>,
 ) -> Result<()> {
     let Some(set) = analyzed_wren_mdl
-        .lineage()
+        .lineage()?
         .required_fields_map
         .get(qualified_column)
     else {
@@ -770,6 +772,10 @@ fn get_remote_column_exp(
             session_state_ref,
         )?
     };
+    let expr = match create_default_value_expr(column)? {
+        Some(default_value) => coalesce(vec![expr, default_value]),
+        None => expr,
+    };
     Ok(expr.alias(column.name.clone()))
 }
 
@@ -0,0 +1,46 @@
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::Result;
+use datafusion::config::ConfigOptions;
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::optimizer::AnalyzerRule;
+
+/// [PlanNodeLimitRule] aborts the analysis if the expanded plan has more nodes than the limit.
+/// The nodes of the subqueries are counted as well. A deep or wide manifest (e.g. views of views)
+/// can be expanded to an enormous plan, so the check should be placed after [super::model_generation::ModelGenerationRule].
+/// The limit is set by [crate::mdl::AnalyzeOptions::with_max_plan_nodes].
+#[derive(Debug)]
+pub struct PlanNodeLimitRule {
+    max_plan_nodes: usize,
+}
+
+impl PlanNodeLimitRule {
+    pub fn new(max_plan_nodes: usize) -> Self {
+        Self { max_plan_nodes }
+    }
+}
+
+impl AnalyzerRule for PlanNodeLimitRule {
+    fn analyze(&self, plan: LogicalPlan, _: &ConfigOptions) -> Result<LogicalPlan> {
+        let mut count = 0;
+        plan.apply_with_subqueries(|_| {
+            count += 1;
+            if count > self.max_plan_nodes {
+                Ok(TreeNodeRecursion::Stop)
+            } else {
+                Ok(TreeNodeRecursion::Continue)
+            }
+        })?;
+        if count > self.max_plan_nodes {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "The expanded plan exceeds the limit of {} nodes",
+                self.max_plan_nodes
+            )));
+        }
+        Ok(plan)
+    }
+
+    fn name(&self) -> &str {
+        "PlanNodeLimitRule"
+    }
+}
@@ -6,7 +6,6 @@ use crate::mdl::{Dataset, SessionStateRef};
 use datafusion::arrow::datatypes::{
     DataType, Field, IntervalUnit, Schema, SchemaBuilder, SchemaRef, TimeUnit,
 };
-use datafusion::common::plan_err;
 use datafusion::common::tree_node::{
     Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
 };
@@ -14,11 +13,14 @@ use datafusion::common::types::{
     logical_binary, logical_boolean, logical_date, logical_float16, logical_float32,
     logical_float64, logical_string,
 };
+use datafusion::common::{plan_err, DFSchemaRef};
 use datafusion::datasource::DefaultTableSource;
 use datafusion::error::Result;
 use datafusion::logical_expr::sqlparser::ast::ArrayElemTypeDef;
 use datafusion::logical_expr::sqlparser::dialect::GenericDialect;
-use datafusion::logical_expr::{builder::LogicalTableSource, Expr, TableSource};
+use datafusion::logical_expr::{
+    builder::LogicalTableSource, Expr, LogicalPlan, TableSource,
+};
 use datafusion::logical_expr::{Coercion, TypeSignatureClass};
 use datafusion::sql::sqlparser::ast;
 use datafusion::sql::sqlparser::parser::Parser;
@@ -29,6 +31,7 @@ use petgraph::Graph;
 use std::collections::{BTreeMap, HashSet};
 use std::str::FromStr;
 use std::{collections::HashMap, sync::Arc};
+use wren_core_base::mdl::WrenType;
 
 fn create_list_type(array_type: &str) -> Result<DataType> {
     // Workaround for the array type without an element type
@@ -118,72 +121,9 @@ pub fn map_data_type(data_type: &str) -> Result<DataType> {
     if lower_data_type.starts_with("struct") {
         return create_struct_type(lower_data_type);
     }
-    let result = match lower_data_type {
-        // Wren Definition Types
-        "bool" | "boolean" => DataType::Boolean,
-        "tinyint" => DataType::Int8,
-        "utinyint" => DataType::UInt8,
-        "int2" => DataType::Int16,
-        "smallint" => DataType::Int16,
-        "usmallint" => DataType::UInt16,
-        "int4" => DataType::Int32,
-        "int" => DataType::Int32,
-        "integer" => DataType::Int32,
-        "uinteger" => DataType::UInt32,
-        "int8" => DataType::Int64,
-        "bigint" => DataType::Int64,
-        "ubigint" => DataType::UInt64,
-        "numeric" => DataType::Decimal128(38, 10), // set the default precision and scale
-        "decimal" => DataType::Decimal128(38, 10),
-        "varchar" => DataType::Utf8,
-        "char" => DataType::Utf8,
-        "bpchar" => DataType::Utf8, // we don't have a BPCHAR type, so we map it to Utf8
-        "text" => DataType::Utf8,
-        "string" => DataType::Utf8,
-        "name" => DataType::Utf8,
-        "float4" => DataType::Float32,
-        "real" => DataType::Float32,
-        "float" => DataType::Float32,
-        "float8" => DataType::Float64,
-        "double" => DataType::Float64,
-        "timestamp" | "datetime" => DataType::Timestamp(TimeUnit::Nanosecond, None), // chose the smallest time unit
-        "timestamptz"
-        | "timestamp_with_timezone"
-        | "timestamp_with_time_zone"
-        | "timestamp with time zone"
-        | "time with time zone" => {
-            // time with time zone isn't equal to timestamp with time zone but
-            // we don't have a time with time zone type, so we map it to timestamp with time zone
-            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
-        }
-        "date" => DataType::Date32,
-        "interval" => DataType::Interval(IntervalUnit::DayTime),
-        "json" => DataType::Utf8, // we don't have a JSON type, so we map it to Utf8
-        "xml" => DataType::Utf8,  // we don't have a XML type, so we map it to Utf8
-        "jsonb" => DataType::Binary, // we don't have a JSONB type, so we map it to Binary
-        "oid" => DataType::Int32,
-        "bytea" => DataType::Binary,
-        "uuid" => DataType::Utf8, // we don't have a UUID type, so we map it to Utf8
-        "inet" => DataType::Utf8, // we don't have a INET type, so we map it to Utf8
-        "unknown" => DataType::Utf8, // we don't have a UNKNOWN type, so we map it to Utf8
-        // BigQuery Compatible Types
-        "bignumeric" => DataType::Decimal128(38, 10), // set the default precision and scale
-        "bytes" => DataType::Binary,
-        "binary" => DataType::Binary,
-        "float64" => DataType::Float64,
-        "int64" => DataType::Int64,
-        "time" => DataType::Time32(TimeUnit::Nanosecond), // chose the smallest time unit
-        "null" => DataType::Null,
-        // Trino Compatible Types
-        "varbinary" => DataType::Binary,
-        // DuckDB Compatible Types
-        "blob" => DataType::Binary,
-        "hugeint" => DataType::Int64, // we don't have a HUGEINT type, so we map it to Int64
-        "uhugeint" => DataType::UInt64, // we don't have a UHUINT type, so we map it to UInt64
-        "bit" => DataType::Boolean, // we don't have a BIT type, so we map it to Boolean
-        "timestamp_ns" => DataType::Timestamp(TimeUnit::Nanosecond, None),
-        "any" => DataType::Utf8, // we don't have an ANY type, so we map it to Utf8
-        _ => {
+    let result = match WrenType::from_name(lower_data_type) {
+        Some(wren_type) => data_type_of(wren_type),
+        None => {
             debug!("try parse by arrow {lower_data_type}");
             // the from_str is case sensitive, so we need to use the original string
             DataType::from_str(data_type)?
@@ -192,6 +132,34 @@ pub fn map_data_type(data_type: &str) -> Result<DataType> {
     Ok(result)
 }
 
+/// The Arrow type of a type name of [wren_core_base::mdl::WREN_TYPES]
+fn data_type_of(wren_type: WrenType) -> DataType {
+    match wren_type {
+        WrenType::Boolean => DataType::Boolean,
+        WrenType::Int8 => DataType::Int8,
+        WrenType::UInt8 => DataType::UInt8,
+        WrenType::Int16 => DataType::Int16,
+        WrenType::UInt16 => DataType::UInt16,
+        WrenType::Int32 => DataType::Int32,
+        WrenType::UInt32 => DataType::UInt32,
+        WrenType::Int64 => DataType::Int64,
+        WrenType::UInt64 => DataType::UInt64,
+        WrenType::Decimal => DataType::Decimal128(38, 10), // set the default precision and scale
+        WrenType::String => DataType::Utf8,
+        WrenType::Float32 => DataType::Float32,
+        WrenType::Float64 => DataType::Float64,
+        WrenType::Timestamp => DataType::Timestamp(TimeUnit::Nanosecond, None), // chose the smallest time unit
+        WrenType::TimestampWithTimeZone => {
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+        }
+        WrenType::Date => DataType::Date32,
+        WrenType::Time => DataType::Time32(TimeUnit::Nanosecond), // chose the smallest time unit
+        WrenType::Interval => DataType::Interval(IntervalUnit::DayTime),
+        WrenType::Binary => DataType::Binary,
+        WrenType::Null => DataType::Null,
+    }
+}
+
 pub fn get_coercion_type_signature(data_type: &DataType) -> Result<Coercion> {
     match data_type {
         DataType::Boolean => Ok(Coercion::new_exact(TypeSignatureClass::Native(
@@ -400,6 +368,77 @@ pub fn rebase_column(expr: &Expr, base_reference: &str) -> Result<Expr> {
         .data()
 }
 
+/// Qualify the unqualified columns of the plan by the relation providing them,
+///
+/// e.g. `SELECT a, b + 1 AS c FROM t WHERE b > 1` will be transformed to
+/// `SELECT t.a, t.b + 1 AS c FROM t WHERE t.b > 1`. The expressions of every node are
+/// qualified, e.g. the filters, the sorts, the aggregations and the join conditions, but
+/// the extension nodes plan their expressions themselves and are kept as they are.
+///
+/// A column provided by an unaliased derived table has no relation, and a column provided
+/// by several inputs of a join is ambiguous, so they're kept as they are. An output
+/// expression renamed by the qualification, e.g. `sum(b)`, is aliased to its original name,
+/// so the columns of the parent nodes still reference it.
+pub fn qualify_columns(plan: LogicalPlan) -> Result<LogicalPlan> {
+    plan.transform_up_with_subqueries(|plan| {
+        if plan.inputs().is_empty() || matches!(plan, LogicalPlan::Extension(_)) {
+            return Ok(Transformed::no(plan));
+        }
+        let schemas: Vec<DFSchemaRef> = plan
+            .inputs()
+            .iter()
+            .map(|input| Arc::clone(input.schema()))
+            .collect();
+        // the expressions of the other nodes don't name the columns of their output
+        let keep_names = matches!(
+            plan,
+            LogicalPlan::Projection(_)
+                | LogicalPlan::Aggregate(_)
+                | LogicalPlan::Window(_)
+        );
+        let transformed = plan.map_expressions(|expr| {
+            if !keep_names || matches!(expr, Expr::Column(_) | Expr::Alias(_)) {
+                return qualify_expr(expr, &schemas);
+            }
+            let name = expr.schema_name().to_string();
+            Ok(qualify_expr(expr, &schemas)?.update_data(|expr| {
+                if expr.schema_name().to_string() == name {
+                    expr
+                } else {
+                    expr.alias(name)
+                }
+            }))
+        })?;
+        if transformed.transformed {
+            transformed.map_data(|plan| plan.recompute_schema())
+        } else {
+            Ok(transformed)
+        }
+    })
+    .data()
+}
+
+/// Qualify the unqualified columns of the expression found in exactly one of the schemas
+fn qualify_expr(expr: Expr, schemas: &[DFSchemaRef]) -> Result<Transformed<Expr>> {
+    expr.transform_up(|expr| match expr {
+        Expr::Column(column) if column.relation.is_none() => {
+            let mut qualifiers = schemas.iter().filter_map(|schema| {
+                schema
+                    .qualified_field_with_unqualified_name(&column.name)
+                    .ok()
+                    .map(|(qualifier, _)| qualifier.cloned())
+            });
+            match (qualifiers.next(), qualifiers.next()) {
+                (Some(Some(qualifier)), None) => Ok(Transformed::yes(Expr::Column(
+                    datafusion::common::Column::new(Some(qualifier), column.name),
+                ))),
+                _ => Ok(Transformed::no(Expr::Column(column))),
+            }
+        }
+        _ => Ok(Transformed::no(expr)),
+    })
+}
+
 /// Eliminate the ambiguous columns in the expressions. If there are columns with the same name,
 /// only the first one will be kept.
 pub fn eliminate_ambiguous_columns(expr: Vec<Expr>) -> Vec<Expr> {
@@ -420,10 +459,91 @@ pub fn eliminate_ambiguous_columns(expr: Vec<Expr>) -> Vec<Expr> {
 #[cfg(test)]
 mod test {
     use crate::logical_plan::utils::{
-        create_list_type, create_struct_type, try_map_data_type,
+        create_list_type, create_struct_type, map_data_type, qualify_columns,
+        try_map_data_type,
+    };
+    use datafusion::arrow::datatypes::{
+        DataType, Field, Fields, IntervalUnit, Schema, TimeUnit,
     };
-    use datafusion::arrow::datatypes::{DataType, Field, Fields, IntervalUnit, TimeUnit};
-    use datafusion::common::Result;
+    use datafusion::common::{Column, Result};
+    use datafusion::functions_aggregate::expr_fn::sum;
+    use datafusion::logical_expr::{
+        lit, table_scan, Aggregate, Expr, Filter, LogicalPlan, Projection, Sort,
+    };
+    use insta::assert_snapshot;
+    use std::sync::Arc;
+    use wren_core_base::mdl::WREN_TYPES;
+
+    #[test]
+    fn test_qualify_columns() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        let scan = table_scan(Some("t"), &schema, None)?.build()?;
+        // build the projection directly because the builder would qualify the columns
+        let plan = LogicalPlan::Projection(Projection::try_new(
+            vec![
+                Expr::Column(Column::from_name("a")),
+                (Expr::Column(Column::from_name("b")) + lit(1)).alias("c"),
+                lit(1).alias("one"),
+            ],
+            Arc::new(scan),
+        )?);
+        assert_snapshot!(plan, @r"
+        Projection: a, b + Int32(1) AS c, Int32(1) AS one
+          TableScan: t
+        ");
+        assert_snapshot!(qualify_columns(plan)?, @r"
+        Projection: t.a, t.b + Int32(1) AS c, Int32(1) AS one
+          TableScan: t
+        ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_qualify_columns_of_every_node() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        let column = |name: &str| Expr::Column(Column::from_name(name));
+        let scan = table_scan(Some("t"), &schema, None)?.build()?;
+        // build the nodes directly because the builder would qualify the columns
+        let filter =
+            LogicalPlan::Filter(Filter::try_new(column("b").gt(lit(1)), Arc::new(scan))?);
+        let aggregate = LogicalPlan::Aggregate(Aggregate::try_new(
+            Arc::new(filter),
+            vec![column("a")],
+            vec![sum(column("b"))],
+        )?);
+        let sort = LogicalPlan::Sort(Sort {
+            expr: vec![column("a").sort(true, false)],
+            input: Arc::new(aggregate),
+            fetch: None,
+        });
+        let plan = LogicalPlan::Projection(Projection::try_new(
+            vec![column("a"), column("sum(b)")],
+            Arc::new(sort),
+        )?);
+        assert_snapshot!(plan, @r"
+        Projection: a, sum(b)
+          Sort: a ASC NULLS LAST
+            Aggregate: groupBy=[[a]], aggr=[[sum(b)]]
+              Filter: b > Int32(1)
+                TableScan: t
+        ");
+        // the aggregation keeps its name, so the projection still references it
+        assert_snapshot!(qualify_columns(plan)?, @r"
+        Projection: t.a, sum(b)
+          Sort: t.a ASC NULLS LAST
+            Aggregate: groupBy=[[t.a]], aggr=[[sum(t.b) AS sum(b)]]
+              Filter: t.b > Int32(1)
+                TableScan: t
+        ");
+
+        Ok(())
+    }
 
     #[test]
     pub fn test_map_data_type() -> Result<()> {
@@ -522,6 +642,11 @@ mod test {
             );
         });
 
+        // the type names accepted by `ColumnBuilder::try_build` are mapped without the fallback
+        for (name, _) in WREN_TYPES {
+            assert!(map_data_type(name).is_ok(), "{name}");
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,1136 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime};
+
+use crate::mdl::clock::{Clock, SystemClock};
+use crate::mdl::context::{Mode, SessionPropertiesRef};
+use crate::mdl::function::RemoteFunction;
+use crate::mdl::manifest::Manifest;
+use crate::mdl::utils::normalize_sql;
+use crate::mdl::{AnalyzeOptions, AnalyzedWrenMDL};
+use datafusion::common::{exec_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::SessionContext;
+use datafusion::sql::parser::Statement as DFStatement;
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+
+/// The default number of analyzed MDLs kept in [ANALYZED_MDL_CACHE].
+pub const DEFAULT_ANALYZED_MDL_CACHE_CAPACITY: usize = 64;
+
+/// A bounded, thread-safe cache. When the capacity is reached, the oldest inserted entry is evicted,
+/// or the least frequently hit one if [Cache::with_lfu_eviction] is set. If a TTL is set, the entries older than the TTL are treated as absent. The age of an entry
+/// is measured by the [Clock] of the cache.
+pub struct Cache<K, V> {
+    capacity: usize,
+    ttl: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    /// Whether to track the access time and the hit count of the entries.
+    /// The tracking makes [Cache::get] take the write lock, so it's disabled by default.
+    track_metadata: bool,
+    /// Whether to evict the entry with the fewest hits instead of the oldest one.
+    lfu_eviction: bool,
+    inner: RwLock<CacheInner<K, V>>,
+    /// The computations of [Cache::get_or_compute] in progress
+    in_flight: Mutex<InFlightComputations<K, V>>,
+}
+
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: SystemTime,
+    last_access: SystemTime,
+    hit_count: u64,
+}
+
+/// The metadata of a cache entry for observability.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMeta<K> {
+    pub key: K,
+    pub inserted_at: SystemTime,
+    pub last_access: SystemTime,
+    pub hit_count: u64,
+}
+
+struct CacheInner<K, V> {
+    entries: HashMap<K, CacheEntry<V>>,
+    /// The keys in insertion order, used to pick the eviction victim.
+    order: VecDeque<K>,
+}
+
+/// The outcome of a computation, shared by its waiters. It's `None` until it's finished.
+type Outcome<V> = Option<std::result::Result<V, Arc<DataFusionError>>>;
+
+struct InFlightComputations<K, V> {
+    slots: HashMap<K, InFlight<V>>,
+    /// The id of the next computation, so a waiter doesn't touch the slot of a later one
+    next_id: u64,
+}
+
+struct InFlight<V> {
+    id: u64,
+    /// The callers waiting for the computation and not cancelled yet
+    waiters: usize,
+    outcome: watch::Receiver<Outcome<V>>,
+    task: AbortHandle,
+}
+
+/// A caller of [Cache::get_or_compute]. It's dropped when the caller finishes or is
+/// cancelled, and the last one aborts the computation if it's still running.
+struct Waiter<'a, K: Hash + Eq, V> {
+    cache: &'a Cache<K, V>,
+    key: &'a K,
+    id: u64,
+}
+
+impl<K: Hash + Eq, V> Drop for Waiter<'_, K, V> {
+    fn drop(&mut self) {
+        let mut in_flight = self.cache.in_flight.lock();
+        let Some(slot) = in_flight
+            .slots
+            .get_mut(self.key)
+            .filter(|slot| slot.id == self.id)
+        else {
+            return;
+        };
+        slot.waiters -= 1;
+        if slot.waiters == 0 {
+            slot.task.abort();
+            in_flight.slots.remove(self.key);
+        }
+    }
+}
+
+impl<K, V> CacheInner<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.order.retain(|k| k != key);
+        Some(entry.value)
+    }
+}
+
+/// The entries aren't printed, e.g. in the `Debug` output of [AnalyzeOptions].
+impl<K, V> std::fmt::Debug for Cache<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.inner.read().entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+            clock: Arc::new(SystemClock),
+            track_metadata: false,
+            lfu_eviction: false,
+            inner: RwLock::new(CacheInner {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            in_flight: Mutex::new(InFlightComputations {
+                slots: HashMap::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Expire the entries after the given TTL.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Use the given clock to measure the age of the entries instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Track the access time and the hit count of the entries for [Cache::entry_metadata].
+    pub fn with_metadata_tracking(mut self) -> Self {
+        self.track_metadata = true;
+        self
+    }
+
+    /// Evict the entry with the fewest hits when the capacity is exceeded. The oldest one
+    /// is evicted among the entries with the same hits, and the inserted entry is never
+    /// the victim. The hits are counted by the metadata tracking, so it's enabled too.
+    pub fn with_lfu_eviction(mut self) -> Self {
+        self.track_metadata = true;
+        self.lfu_eviction = true;
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn is_expired(&self, entry: &CacheEntry<V>) -> bool {
+        let Some(ttl) = self.ttl else {
+            return false;
+        };
+        // If the clock goes backwards, the entry is treated as fresh.
+        self.clock
+            .now()
+            .duration_since(entry.inserted_at)
+            .is_ok_and(|age| age >= ttl)
+    }
+
+    /// Get the value of the key. The expired entry is removed and `None` is returned.
+    pub fn get(&self, key: &K) -> Option<V> {
+        if !self.track_metadata {
+            let inner = self.inner.read();
+            let entry = inner.entries.get(key)?;
+            if !self.is_expired(entry) {
+                return Some(entry.value.clone());
+            }
+        }
+        let mut inner = self.inner.write();
+        // check again because another thread may have replaced the entry
+        if self.is_expired(inner.entries.get(key)?) {
+            inner.remove(key);
+            return None;
+        }
+        let entry = inner.entries.get_mut(key)?;
+        if self.track_metadata {
+            entry.last_access = self.clock.now();
+            entry.hit_count += 1;
+        }
+        Some(entry.value.clone())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner
+            .read()
+            .entries
+            .get(key)
+            .is_some_and(|entry| !self.is_expired(entry))
+    }
+
+    /// Insert the value and evict the oldest entries if the capacity is exceeded.
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.write();
+        self.insert_entry(&mut inner, key, value);
+    }
+
+    /// Insert the value only if the key is absent or expired, and return whether it's inserted.
+    /// When several threads compute the value of a missing key, the first insert wins and
+    /// keeps its insertion time.
+    pub fn insert_if_absent(&self, key: K, value: V) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+        let mut inner = self.inner.write();
+        if inner
+            .entries
+            .get(&key)
+            .is_some_and(|entry| !self.is_expired(entry))
+        {
+            return false;
+        }
+        self.insert_entry(&mut inner, key, value);
+        true
+    }
+
+    fn insert_entry(&self, inner: &mut CacheInner<K, V>, key: K, value: V) {
+        let now = self.clock.now();
+        let entry = CacheEntry {
+            value,
+            inserted_at: now,
+            last_access: now,
+            hit_count: 0,
+        };
+        if inner.entries.insert(key.clone(), entry).is_none() {
+            inner.order.push_back(key.clone());
+        }
+        while inner.entries.len() > self.capacity {
+            if self.lfu_eviction {
+                let victim = inner
+                    .order
+                    .iter()
+                    .filter(|k| **k != key)
+                    .min_by_key(|k| inner.entries.get(*k).map_or(0, |e| e.hit_count))
+                    .cloned();
+                match victim {
+                    Some(victim) => {
+                        inner.remove(&victim);
+                    }
+                    None => break,
+                }
+            } else {
+                let Some(oldest) = inner.order.pop_front() else {
+                    break;
+                };
+                inner.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Remove the entry of the given key and return its value if it existed.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.inner.write().remove(key)
+    }
+
+    /// Mutate the value of the key in place under the write lock, and return whether the
+    /// key existed. The expired entry is removed instead. The update doesn't count as a hit
+    /// and keeps the insertion time of the entry.
+    pub fn update(&self, key: &K, f: impl FnOnce(&mut V)) -> bool {
+        let mut inner = self.inner.write();
+        let Some(entry) = inner.entries.get(key) else {
+            return false;
+        };
+        if self.is_expired(entry) {
+            inner.remove(key);
+            return false;
+        }
+        if let Some(entry) = inner.entries.get_mut(key) {
+            f(&mut entry.value);
+        }
+        true
+    }
+
+    /// Keep only the entries for which the predicate returns `true`.
+    /// The write lock is held once for the whole operation.
+    pub fn retain(&self, f: impl Fn(&K, &V) -> bool) {
+        let mut inner = self.inner.write();
+        let CacheInner { entries, order } = &mut *inner;
+        entries.retain(|k, entry| f(k, &entry.value));
+        order.retain(|k| entries.contains_key(k));
+    }
+
+    /// Return the metadata of the entries in insertion order.
+    /// If the metadata tracking is disabled, `last_access` is the insertion time and `hit_count` is 0.
+    pub fn entry_metadata(&self) -> Vec<EntryMeta<K>> {
+        let inner = self.inner.read();
+        inner
+            .order
+            .iter()
+            .filter_map(|key| {
+                inner.entries.get(key).map(|entry| EntryMeta {
+                    key: key.clone(),
+                    inserted_at: entry.inserted_at,
+                    last_access: entry.last_access,
+                    hit_count: entry.hit_count,
+                })
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.write();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Get the value of the key, or compute and insert it if it's absent. The concurrent
+    /// callers of a missing key share a single computation, run as a tokio task, so it
+    /// should be called in a tokio runtime.
+    ///
+    /// A caller is cancelled by dropping its future, e.g. when the client disconnects. The
+    /// computation is aborted once all its callers are cancelled, and the next caller
+    /// computes the value from scratch. A failed computation isn't cached either: its
+    /// callers get the error and the next caller computes again.
+    pub async fn get_or_compute<F>(&self, key: K, compute: F) -> Result<V>
+    where
+        F: Future<Output = Result<V>> + Send + 'static,
+    {
+        let (id, mut outcome) = {
+            let mut in_flight = self.in_flight.lock();
+            // check under the lock, so a computation finished meanwhile isn't repeated
+            if let Some(value) = self.get(&key) {
+                return Ok(value);
+            }
+            match in_flight.slots.get_mut(&key) {
+                Some(slot) => {
+                    slot.waiters += 1;
+                    (slot.id, slot.outcome.clone())
+                }
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    let task = tokio::spawn(async move {
+                        let outcome = compute.await.map_err(Arc::new);
+                        let _ = sender.send(Some(outcome));
+                    });
+                    let id = in_flight.next_id;
+                    in_flight.next_id += 1;
+                    in_flight.slots.insert(
+                        key.clone(),
+                        InFlight {
+                            id,
+                            waiters: 1,
+                            outcome: receiver.clone(),
+                            task: task.abort_handle(),
+                        },
+                    );
+                    (id, receiver)
+                }
+            }
+        };
+        let _waiter = Waiter {
+            cache: self,
+            key: &key,
+            id,
+        };
+        // the sender is dropped without an outcome if the computation panicked
+        let outcome = outcome
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|outcome| outcome.clone());
+
+        // the first waiter to finish caches the value and frees the slot
+        let mut in_flight = self.in_flight.lock();
+        if in_flight.slots.get(&key).is_some_and(|slot| slot.id == id) {
+            in_flight.slots.remove(&key);
+            if let Some(Ok(value)) = &outcome {
+                self.insert(key.clone(), value.clone());
+            }
+        }
+        drop(in_flight);
+        match outcome {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(e)) => Err(DataFusionError::Shared(e)),
+            None => exec_err!("The computation of the cache entry panicked"),
+        }
+    }
+}
+
+/// The key of [ANALYZED_MDL_CACHE]. An analyzed MDL depends on the manifest,
+/// the session properties and the mode it's analyzed for, and on the functions registered
+/// by the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnalyzedMDLCacheKey {
+    pub manifest_hash: u64,
+    pub properties_hash: u64,
+    pub mode: Mode,
+    pub functions_hash: u64,
+}
+
+impl AnalyzedMDLCacheKey {
+    /// Build the key without registered functions
+    pub fn new(
+        manifest: &Manifest,
+        properties: &SessionPropertiesRef,
+        mode: Mode,
+    ) -> Self {
+        Self {
+            manifest_hash: manifest.content_hash(),
+            properties_hash: hash_properties(properties),
+            mode,
+            functions_hash: hash_functions(&[]),
+        }
+    }
+
+    /// Key the analysis by the signatures of the registered functions, so the callers
+    /// registering different functions don't share an entry.
+    pub fn with_functions(mut self, functions: &[RemoteFunction]) -> Self {
+        self.functions_hash = hash_functions(functions);
+        self
+    }
+}
+
+pub static ANALYZED_MDL_CACHE: LazyLock<
+    Cache<AnalyzedMDLCacheKey, Arc<AnalyzedWrenMDL>>,
+> = LazyLock::new(|| Cache::new(DEFAULT_ANALYZED_MDL_CACHE_CAPACITY));
+
+/// Analyze the manifest or reuse the analyzed MDL cached for the same manifest, properties and mode.
+/// The concurrent callers of the same key share a single analysis by [Cache::get_or_compute].
+pub async fn analyze_cached(
+    manifest: Manifest,
+    properties: SessionPropertiesRef,
+    mode: Mode,
+) -> Result<Arc<AnalyzedWrenMDL>> {
+    analyze_cached_with_functions(manifest, properties, mode, &[]).await
+}
+
+/// Like [analyze_cached], but the functions are registered for every transformation of the
+/// analyzed MDL. The cached analysis is shared only with the callers registering the
+/// functions of the same signatures.
+pub async fn analyze_cached_with_functions(
+    manifest: Manifest,
+    properties: SessionPropertiesRef,
+    mode: Mode,
+    functions: &[RemoteFunction],
+) -> Result<Arc<AnalyzedWrenMDL>> {
+    let key =
+        AnalyzedMDLCacheKey::new(&manifest, &properties, mode).with_functions(functions);
+    let options = AnalyzeOptions::new()
+        .with_properties(properties)
+        .with_mode(mode)
+        .with_remote_functions(functions.to_vec());
+    ANALYZED_MDL_CACHE
+        .get_or_compute(key, async move {
+            AnalyzedWrenMDL::analyze_with_options(manifest, &options).map(Arc::new)
+        })
+        .await
+}
+
+/// The key of a [PlanCache]. The plan depends on the analyzed MDL, i.e. the manifest, the
+/// properties and the mode, on the registered functions, on the session it's planned in and
+/// on the SQL normalized by [PlanCacheKey::new].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanCacheKey {
+    pub manifest_hash: u64,
+    pub properties_hash: u64,
+    pub mode: Mode,
+    pub functions_hash: u64,
+    pub session_hash: u64,
+    /// A plan checked against a limit isn't reused for a stricter one
+    pub max_plan_nodes: Option<usize>,
+    pub sql: String,
+}
+
+impl PlanCacheKey {
+    /// Build the key of the SQL normalized by [normalize_sql], so `select  a from t` and
+    /// `SELECT a\nFROM t` share the same key.
+    pub fn new(
+        analyzed_mdl: &AnalyzedWrenMDL,
+        ctx: &SessionContext,
+        properties: &SessionPropertiesRef,
+        mode: Mode,
+        sql: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            manifest_hash: analyzed_mdl.wren_mdl.manifest_hash(),
+            properties_hash: hash_properties(properties),
+            mode,
+            functions_hash: hash_functions(&[]),
+            session_hash: hash_session(ctx),
+            max_plan_nodes: analyzed_mdl.max_plan_nodes(),
+            sql: normalize_sql(sql)?,
+        })
+    }
+
+    /// Key the plan by the signatures of the registered functions
+    pub fn with_functions(mut self, functions: &[RemoteFunction]) -> Self {
+        self.functions_hash = hash_functions(functions);
+        self
+    }
+}
+
+/// The cache of the optimized plans consulted by the transformation before planning the SQL.
+/// The hit count of the entries is tracked, so [Cache::entry_metadata] tells how many times
+/// the planning was skipped.
+pub type PlanCache = Cache<PlanCacheKey, Arc<LogicalPlan>>;
+
+/// The plan cache is disabled by default.
+static PLAN_CACHE: LazyLock<RwLock<Option<Arc<PlanCache>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+/// Enable the plan cache with the given capacity. `None` disables and drops the cache.
+pub fn set_plan_cache_capacity(capacity: Option<usize>) {
+    *PLAN_CACHE.write() = capacity.map(new_plan_cache);
+}
+
+fn new_plan_cache(capacity: usize) -> Arc<PlanCache> {
+    Arc::new(PlanCache::new(capacity).with_metadata_tracking())
+}
+
+/// Get the plan cache if it's enabled.
+pub fn plan_cache() -> Option<Arc<PlanCache>> {
+    PLAN_CACHE.read().clone()
+}
+
+/// The key of a [ParseCache], i.e. the name of the parser dialect and the raw SQL.
+pub type ParseCacheKey = (String, String);
+
+/// The cache of the parsed statements consulted at the top of the transformation, so a hot
+/// query isn't parsed again before its plan is looked up. The entries are evicted by the
+/// hit count, so a burst of distinct queries doesn't push out the hot ones.
+pub type ParseCache = Cache<ParseCacheKey, Arc<DFStatement>>;
+
+/// The parse cache is disabled by default.
+static PARSE_CACHE: LazyLock<RwLock<Option<Arc<ParseCache>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+/// Enable the parse cache with the given capacity. `None` disables and drops the cache.
+pub fn set_parse_cache_capacity(capacity: Option<usize>) {
+    *PARSE_CACHE.write() = capacity.map(new_parse_cache);
+}
+
+fn new_parse_cache(capacity: usize) -> Arc<ParseCache> {
+    Arc::new(ParseCache::new(capacity).with_lfu_eviction())
+}
+
+/// Get the parse cache if it's enabled.
+pub fn parse_cache() -> Option<Arc<ParseCache>> {
+    PARSE_CACHE.read().clone()
+}
+
+/// Drop all the analyzed MDLs of the given manifest, regardless of the properties and mode.
+pub fn invalidate_manifest(manifest: &Manifest) {
+    let manifest_hash = manifest.content_hash();
+    ANALYZED_MDL_CACHE.retain(|key, _| key.manifest_hash != manifest_hash);
+}
+
+/// Drop all the analyzed MDLs of the given mode and keep the ones of the other modes.
+pub fn clear_for_mode(mode: Mode) {
+    retain_other_modes(&ANALYZED_MDL_CACHE, mode);
+}
+
+fn retain_other_modes<V: Clone>(cache: &Cache<AnalyzedMDLCacheKey, V>, mode: Mode) {
+    cache.retain(|key, _| key.mode != mode);
+}
+
+/// The properties are stored in a [HashMap] whose iteration order isn't stable.
+/// Sort them before hashing to get the same hash for the same set of properties.
+fn hash_properties(properties: &SessionPropertiesRef) -> u64 {
+    let mut entries: Vec<_> = properties.iter().collect();
+    entries.sort();
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash what the planning reads from the session besides the MDL: the config, the
+/// registered functions and the tables of its catalogs. The names are sorted, because the
+/// iteration order of the registries isn't stable.
+fn hash_session(ctx: &SessionContext) -> u64 {
+    let state = ctx.state_ref();
+    let state = state.read();
+    let mut hasher = DefaultHasher::new();
+    let mut options: Vec<_> = state
+        .config_options()
+        .entries()
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect();
+    options.sort();
+    options.hash(&mut hasher);
+
+    let mut functions: Vec<_> = state
+        .scalar_functions()
+        .iter()
+        .map(|(name, udf)| (name, udf.signature()))
+        .chain(
+            state
+                .aggregate_functions()
+                .iter()
+                .map(|(name, udaf)| (name, udaf.signature())),
+        )
+        .chain(
+            state
+                .window_functions()
+                .iter()
+                .map(|(name, udwf)| (name, udwf.signature())),
+        )
+        .collect();
+    functions.sort_by_key(|(name, _)| *name);
+    functions.hash(&mut hasher);
+
+    let catalog_list = state.catalog_list();
+    let mut catalog_names = catalog_list.catalog_names();
+    catalog_names.sort();
+    for catalog_name in catalog_names {
+        let Some(catalog) = catalog_list.catalog(&catalog_name) else {
+            continue;
+        };
+        let mut schema_names = catalog.schema_names();
+        schema_names.sort();
+        for schema_name in schema_names {
+            let Some(schema) = catalog.schema(&schema_name) else {
+                continue;
+            };
+            let mut table_names = schema.table_names();
+            table_names.sort();
+            (&catalog_name, &schema_name, table_names).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Hash the signatures of the functions, i.e. the kinds, names and types. The descriptions
+/// and parameter names don't change the analysis. The order of registration doesn't matter.
+fn hash_functions(functions: &[RemoteFunction]) -> u64 {
+    let mut signatures: Vec<_> = functions
+        .iter()
+        .map(|function| {
+            (
+                function.function_type.to_string(),
+                function.name.to_lowercase(),
+                &function.return_type,
+                &function.param_types,
+            )
+        })
+        .collect();
+    signatures.sort();
+    let mut hasher = DefaultHasher::new();
+    signatures.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::cache::{
+        analyze_cached_with_functions, retain_other_modes, AnalyzedMDLCacheKey, Cache,
+        PlanCacheKey, ANALYZED_MDL_CACHE,
+    };
+    use crate::mdl::clock::FixedClock;
+    use crate::mdl::context::Mode;
+    use crate::mdl::function::{FunctionType, RemoteFunction};
+    use crate::mdl::{
+        create_wren_ctx, transform_sql_with_ctx, AnalyzeOptions, AnalyzedWrenMDL,
+    };
+    use datafusion::arrow::array::RecordBatch;
+    use datafusion::arrow::datatypes::Schema;
+    use datafusion::common::plan_err;
+    use datafusion::prelude::SessionConfig;
+    use insta::assert_snapshot;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn test_eviction() {
+        let cache = Cache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(3, "c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some("b"));
+        assert_eq!(cache.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn test_lfu_eviction() {
+        let cache = Cache::new(2).with_lfu_eviction();
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+        // the oldest entry is hit, so the other one is evicted
+        cache.insert(3, "c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        // the new entry isn't evicted though it has no hits
+        cache.insert(4, "d");
+        assert_eq!(cache.get(&3), None);
+        assert_eq!(cache.get(&4), Some("d"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let cache = Cache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.remove(&1), Some("a"));
+        assert_eq!(cache.remove(&1), None);
+        assert_eq!(cache.len(), 1);
+
+        // the removed key shouldn't count toward the eviction
+        cache.insert(3, "c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&2), Some("b"));
+        assert_eq!(cache.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn test_update() {
+        let cache = Cache::new(2);
+        cache.insert("hits", 1);
+        assert!(cache.update(&"hits", |count| *count += 1));
+        assert!(cache.update(&"hits", |count| *count += 1));
+        assert_eq!(cache.get(&"hits"), Some(3));
+        assert!(!cache.update(&"misses", |count| *count += 1));
+        assert_eq!(cache.get(&"misses"), None);
+
+        let clock = Arc::new(FixedClock::new(SystemTime::UNIX_EPOCH));
+        let cache = Cache::new(2)
+            .with_ttl(Duration::from_secs(10))
+            .with_clock(clock.clone());
+        cache.insert("hits", 1);
+        clock.advance(Duration::from_secs(10));
+        assert!(!cache.update(&"hits", |count| *count += 1));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_cancelled() -> datafusion::error::Result<()> {
+        let cache = Arc::new(Cache::new(2));
+        let (started_tx, started_rx) = oneshot::channel();
+        let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+        let computation = async move {
+            let _dropped = dropped_tx;
+            let _ = started_tx.send(());
+            std::future::pending::<datafusion::error::Result<&str>>().await
+        };
+        let first = {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move { cache.get_or_compute(1, computation).await })
+        };
+        started_rx.await.unwrap();
+        // the second caller shares the computation instead of starting its own
+        let second = {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move { cache.get_or_compute(1, async { Ok("b") }).await })
+        };
+        while cache
+            .in_flight
+            .lock()
+            .slots
+            .get(&1)
+            .map(|slot| slot.waiters)
+            != Some(2)
+        {
+            tokio::task::yield_now().await;
+        }
+
+        first.abort();
+        second.abort();
+        assert!(first.await.unwrap_err().is_cancelled());
+        assert!(second.await.unwrap_err().is_cancelled());
+        // the computation is aborted once all the callers are cancelled
+        assert!(dropped_rx.await.is_err());
+        assert!(cache.in_flight.lock().slots.is_empty());
+        assert!(cache.is_empty());
+
+        assert_eq!(
+            cache.get_or_compute(1, async { Ok("fresh") }).await?,
+            "fresh"
+        );
+        assert_eq!(cache.get(&1), Some("fresh"));
+
+        // the failure isn't cached
+        assert!(cache
+            .get_or_compute(2, async { plan_err!("failed") })
+            .await
+            .is_err());
+        assert_eq!(cache.get_or_compute(2, async { Ok("ok") }).await?, "ok");
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_if_absent() {
+        let cache = Arc::new(Cache::new(8));
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let cache = Arc::clone(&cache);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    cache.insert_if_absent(1, i).then_some(i)
+                })
+            })
+            .collect();
+        let winners: Vec<_> = handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(cache.get(&1), Some(winners[0]));
+        assert_eq!(cache.len(), 1);
+
+        // an expired entry is replaced
+        let clock = Arc::new(FixedClock::new(SystemTime::UNIX_EPOCH));
+        let cache = Cache::new(8)
+            .with_ttl(Duration::from_secs(10))
+            .with_clock(clock.clone());
+        assert!(cache.insert_if_absent(1, "a"));
+        assert!(!cache.insert_if_absent(1, "b"));
+        clock.advance(Duration::from_secs(10));
+        assert!(cache.insert_if_absent(1, "c"));
+        assert_eq!(cache.get(&1), Some("c"));
+    }
+
+    #[test]
+    fn test_retain() {
+        let cache = Cache::new(8);
+        let key = |manifest_hash, mode| AnalyzedMDLCacheKey {
+            manifest_hash,
+            properties_hash: 0,
+            mode,
+            functions_hash: 0,
+        };
+        cache.insert(key(1, Mode::Unparse), "a");
+        cache.insert(key(1, Mode::LocalRuntime), "b");
+        cache.insert(key(2, Mode::Unparse), "c");
+
+        cache.retain(|k, _| k.manifest_hash != 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&key(1, Mode::Unparse)), None);
+        assert_eq!(cache.get(&key(1, Mode::LocalRuntime)), None);
+        assert_eq!(cache.get(&key(2, Mode::Unparse)), Some("c"));
+
+        cache.retain(|_, v| *v != "c");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_clear_for_mode() {
+        // a local cache, so clearing a mode doesn't drop the entries of the other tests
+        let cache = Cache::new(8);
+        let key = |manifest_hash, mode| AnalyzedMDLCacheKey {
+            manifest_hash,
+            properties_hash: 0,
+            mode,
+            functions_hash: 0,
+        };
+        cache.insert(key(1, Mode::Unparse), "a");
+        cache.insert(key(2, Mode::Unparse), "b");
+        cache.insert(key(1, Mode::LocalRuntime), "c");
+
+        retain_other_modes(&cache, Mode::Unparse);
+        assert!(!cache.contains_key(&key(1, Mode::Unparse)));
+        assert!(!cache.contains_key(&key(2, Mode::Unparse)));
+        assert_eq!(cache.get(&key(1, Mode::LocalRuntime)), Some("c"));
+    }
+
+    #[test]
+    fn test_ttl_with_fixed_clock() {
+        let clock = Arc::new(FixedClock::new(SystemTime::UNIX_EPOCH));
+        let cache = Cache::new(8)
+            .with_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        cache.insert(1, "a");
+
+        clock.advance(Duration::from_secs(59));
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!cache.contains_key(&1));
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some("b"));
+        // the expired entry is removed when it's read
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_metadata() {
+        let start = SystemTime::UNIX_EPOCH;
+        let clock = Arc::new(FixedClock::new(start));
+        let cache = Cache::new(8)
+            .with_clock(clock.clone())
+            .with_metadata_tracking();
+        cache.insert(1, "a");
+        clock.advance(Duration::from_secs(1));
+        cache.insert(2, "b");
+
+        clock.advance(Duration::from_secs(1));
+        cache.get(&1);
+        clock.advance(Duration::from_secs(1));
+        cache.get(&1);
+        // a miss doesn't touch any entry
+        cache.get(&3);
+
+        let metadata = cache.entry_metadata();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata[0].key, 1);
+        assert_eq!(metadata[0].inserted_at, start);
+        assert_eq!(metadata[0].last_access, start + Duration::from_secs(3));
+        assert_eq!(metadata[0].hit_count, 2);
+        assert_eq!(metadata[1].key, 2);
+        assert_eq!(metadata[1].inserted_at, start + Duration::from_secs(1));
+        assert_eq!(metadata[1].last_access, start + Duration::from_secs(1));
+        assert_eq!(metadata[1].hit_count, 0);
+
+        // without the tracking, the access isn't recorded
+        let cache = Cache::new(8).with_clock(clock.clone());
+        cache.insert(1, "a");
+        cache.get(&1);
+        assert_eq!(cache.entry_metadata()[0].hit_count, 0);
+    }
+
+    #[test]
+    fn test_plan_cache_key() -> datafusion::error::Result<()> {
+        let analyzed_mdl = AnalyzedWrenMDL::default();
+        let ctx = create_wren_ctx(None);
+        let properties = Arc::new(std::collections::HashMap::new());
+        let key =
+            |sql| PlanCacheKey::new(&analyzed_mdl, &ctx, &properties, Mode::Unparse, sql);
+        assert_eq!(
+            key("select a,  b from t where a = 'x  y'")?,
+            key("SELECT a, b\nFROM t -- comment\n  WHERE a = 'x  y'")?
+        );
+        // the identifiers and the literals are kept as they are
+        assert_ne!(key("select a from t")?, key("select A from t")?);
+        assert_ne!(key("select 'a' from t")?, key("select 'A' from t")?);
+        assert_ne!(
+            key("select a from t")?,
+            PlanCacheKey::new(
+                &analyzed_mdl,
+                &ctx,
+                &properties,
+                Mode::LocalRuntime,
+                "select a from t"
+            )?
+        );
+
+        // the session the plan is built in is a part of the key
+        let other_ctx = create_wren_ctx(Some(
+            SessionConfig::new().with_default_catalog_and_schema("other", "public"),
+        ));
+        assert_ne!(
+            key("select a from t")?,
+            PlanCacheKey::new(
+                &analyzed_mdl,
+                &other_ctx,
+                &properties,
+                Mode::Unparse,
+                "select a from t"
+            )?
+        );
+        let ctx_with_table = create_wren_ctx(None);
+        ctx_with_table
+            .register_batch("t", RecordBatch::new_empty(Arc::new(Schema::empty())))?;
+        assert_ne!(
+            key("select a from t")?,
+            PlanCacheKey::new(
+                &analyzed_mdl,
+                &ctx_with_table,
+                &properties,
+                Mode::Unparse,
+                "select a from t"
+            )?
+        );
+
+        // so is the limit of the plan size
+        let limited = AnalyzedWrenMDL::analyze_with_options(
+            ManifestBuilder::default().build(),
+            &AnalyzeOptions::new().with_max_plan_nodes(10),
+        )?;
+        assert_ne!(
+            key("select a from t")?,
+            PlanCacheKey::new(
+                &limited,
+                &ctx,
+                &properties,
+                Mode::Unparse,
+                "select a from t"
+            )?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_key_with_functions() -> datafusion::error::Result<()> {
+        let function = |name: &str, return_type: &str| RemoteFunction {
+            function_type: FunctionType::Scalar,
+            name: name.to_string(),
+            return_type: return_type.to_string(),
+            param_names: None,
+            param_types: Some(vec![Some("int".to_string())]),
+            description: None,
+        };
+        let manifest = crate::mdl::builder::ManifestBuilder::new()
+            .catalog("test_cache_key_with_functions")
+            .build();
+        let properties = Arc::new(std::collections::HashMap::new());
+        let analyze = |functions: &[RemoteFunction]| {
+            let manifest = manifest.clone();
+            let properties = Arc::clone(&properties);
+            let functions = functions.to_vec();
+            async move {
+                analyze_cached_with_functions(
+                    manifest,
+                    properties,
+                    Mode::Unparse,
+                    &functions,
+                )
+                .await
+            }
+        };
+        let set_a = [function("add_one", "int"), function("to_text", "varchar")];
+        let set_b = [
+            function("add_one", "bigint"),
+            function("to_text", "varchar"),
+        ];
+        let a = analyze(&set_a).await?;
+        let b = analyze(&set_b).await?;
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &analyze(&[]).await?));
+        // the order of the registration and the descriptions don't matter
+        let mut reordered = [set_a[1].clone(), set_a[0].clone()];
+        reordered[0].description = Some("to text".to_string());
+        assert!(Arc::ptr_eq(&a, &analyze(&reordered).await?));
+
+        let ctx = create_wren_ctx(None);
+        let key = |functions: &[RemoteFunction]| {
+            PlanCacheKey::new(&a, &ctx, &properties, Mode::Unparse, "select 1")
+                .map(|key| key.with_functions(functions))
+        };
+        assert_ne!(key(&set_a)?, key(&set_b)?);
+        assert_eq!(key(&set_a)?, key(&reordered)?);
+        ANALYZED_MDL_CACHE.retain(|k, _| k.manifest_hash != manifest.content_hash());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analyze_cached_with_functions_plans_udf(
+    ) -> datafusion::error::Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("test_analyze_cached_with_functions_plans_udf")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let manifest_hash = manifest.content_hash();
+        let add_one = RemoteFunction {
+            function_type: FunctionType::Scalar,
+            name: "add_one".to_string(),
+            return_type: "int".to_string(),
+            param_names: None,
+            param_types: Some(vec![Some("int".to_string())]),
+            description: None,
+        };
+        let analyzed_mdl = analyze_cached_with_functions(
+            manifest,
+            Arc::new(std::collections::HashMap::new()),
+            Mode::Unparse,
+            &[add_one],
+        )
+        .await?;
+        // the function of the cached analysis is registered without passing it again
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            &[],
+            Arc::new(std::collections::HashMap::new()),
+            "select add_one(c_custkey) as one from customer",
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT add_one(customer.c_custkey) AS one FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source) AS customer) AS customer");
+        ANALYZED_MDL_CACHE.retain(|k, _| k.manifest_hash != manifest_hash);
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::RwLock;
+
+/// The source of the current time. All the time reads of the engine should go through a
+/// [Clock], so the time-dependent behavior can be made deterministic by a [FixedClock].
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The default [Clock] that reads the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A [Clock] that always returns the given time until it's moved manually.
+#[derive(Debug)]
+pub struct FixedClock {
+    now: RwLock<SystemTime>,
+}
+
+impl FixedClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: RwLock::new(now),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.write() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.write() += duration;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self.now.read()
+    }
+}
@@ -1,24 +1,27 @@
 use std::any::Any;
 use std::collections::HashMap;
-use std::ops::Deref;
+use std::hash::{Hash, Hasher};
+use std::ops::{ControlFlow, Deref};
 use std::sync::Arc;
 
 use crate::logical_plan::analyze::access_control::validate_clac_rule;
 use crate::logical_plan::analyze::expand_view::ExpandWrenViewRule;
 use crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule;
 use crate::logical_plan::analyze::model_generation::ModelGenerationRule;
+use crate::logical_plan::analyze::plan_node_limit::PlanNodeLimitRule;
 use crate::logical_plan::optimize::simplify_timestamp::TimestampSimplify;
 use crate::logical_plan::optimize::type_coercion::TypeCoercion as WrenTypeCoercion;
 use crate::logical_plan::utils::create_schema;
-use crate::mdl::manifest::Model;
+use crate::mdl::manifest::{Column, Manifest, Metric, Model};
 use crate::mdl::type_planner::WrenTypePlanner;
-use crate::mdl::{AnalyzedWrenMDL, SessionStateRef};
+use crate::mdl::utils::quoted;
+use crate::mdl::{AnalyzedWrenMDL, SessionStateRef, WrenMDL};
 use async_trait::async_trait;
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::catalog::memory::MemoryCatalogProvider;
 use datafusion::catalog::CatalogProvider;
 use datafusion::catalog::{MemorySchemaProvider, Session};
-use datafusion::common::Result;
+use datafusion::common::{plan_err, Result};
 use datafusion::datasource::{TableProvider, TableType, ViewTable};
 use datafusion::execution::session_state::SessionStateBuilder;
 use datafusion::logical_expr::Expr;
@@ -36,11 +39,40 @@ use datafusion::optimizer::{AnalyzerRule, OptimizerRule};
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion::prelude::SessionContext;
 use datafusion::scalar::ScalarValue;
+use datafusion::sql::sqlparser::ast::{self, visit_expressions};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::TableReference;
 use parking_lot::RwLock;
 
 pub type SessionPropertiesRef = Arc<HashMap<String, Option<String>>>;
 
+/// Merge the default session properties of the manifest under the properties of the call.
+/// The keys are compared case-insensitively and a property provided by the call wins, even
+/// if its value is `None`.
+pub fn with_default_properties(
+    manifest: &Manifest,
+    properties: SessionPropertiesRef,
+) -> SessionPropertiesRef {
+    let defaults = manifest.default_properties();
+    let missing: Vec<_> = defaults
+        .iter()
+        .filter(|(key, _)| {
+            !properties
+                .keys()
+                .any(|provided| provided.eq_ignore_ascii_case(key))
+        })
+        .collect();
+    if missing.is_empty() {
+        return properties;
+    }
+    let mut merged = properties.as_ref().clone();
+    for (key, value) in missing {
+        merged.insert(key.to_lowercase(), Some(value.clone()));
+    }
+    Arc::new(merged)
+}
+
 /// Apply Wren Rules to the context for sql generation.
 pub async fn apply_wren_on_ctx(
     ctx: &SessionContext,
@@ -48,6 +80,7 @@ pub async fn apply_wren_on_ctx(
     properties: SessionPropertiesRef,
     mode: Mode,
 ) -> Result<SessionContext> {
+    let properties = with_default_properties(&analyzed_mdl.wren_mdl.manifest, properties);
     let session_timezone = properties
         .get("x-wren-timezone")
         .map(|v| v.as_ref().map(|s| s.as_str()).unwrap_or("UTC").to_string());
@@ -98,11 +131,16 @@ pub async fn apply_wren_on_ctx(
             .collect::<HashMap<_, _>>(),
     );
 
-    let new_state = new_state.with_analyzer_rules(mode.get_analyze_rules(
+    let mut analyze_rules = mode.get_analyze_rules(
         Arc::clone(&analyzed_mdl),
         Arc::clone(&reset_default_catalog_schema),
         Arc::clone(&properties),
-    ));
+    );
+    // the limit is configured by the engine, so the session properties can't lift it
+    if let Some(max_plan_nodes) = analyzed_mdl.max_plan_nodes() {
+        analyze_rules.push(Arc::new(PlanNodeLimitRule::new(max_plan_nodes)));
+    }
+    let new_state = new_state.with_analyzer_rules(analyze_rules);
     let new_state = if let Some(optimize_rules) = mode.get_optimize_rules() {
         new_state.with_optimizer_rules(optimize_rules)
     } else {
@@ -116,19 +154,38 @@ pub async fn apply_wren_on_ctx(
 }
 
 /// Execution mode for Wren engine.
-#[derive(Debug)]
+///
+/// The discriminants are explicit and stable because they're hashed into the cache key
+/// of the analyzed MDL. The compiler rejects a new variant reusing an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum Mode {
     /// Local runtime mode, used for executing queries by DataFusion directly.
-    LocalRuntime,
+    LocalRuntime = 0,
     /// Unparse mode, used for generating SQL statements.
     /// This mode is used to generate SQL statements that can be executed in other SQL engines.
-    Unparse,
+    Unparse = 1,
     /// Permission analyze mode, used for analyzing if the error is caused by permission denied.
     /// It's only be used when an error is raised during Unparse mode.
-    PermissionAnalyze,
+    PermissionAnalyze = 2,
+}
+
+impl Hash for Mode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.discriminant().hash(state)
+    }
 }
 
 impl Mode {
+    /// All the modes. Keep it in sync with the variants.
+    pub const ALL: [Mode; 3] =
+        [Mode::LocalRuntime, Mode::Unparse, Mode::PermissionAnalyze];
+
+    /// The stable discriminant of the mode.
+    pub fn discriminant(&self) -> u8 {
+        *self as u8
+    }
+
     pub fn get_analyze_rules(
         &self,
         analyzed_mdl: Arc<AnalyzedWrenMDL>,
@@ -316,7 +373,24 @@ pub async fn register_table_with_mdl(
             Arc::new(table),
         )?;
     }
-    for view in wren_mdl.manifest.views.iter() {
+    // register the metrics before the views, so that a view can reference a metric
+    for metric in wren_mdl.manifest.metrics.iter() {
+        let statement = metric_statement(&wren_mdl, metric)?;
+        let plan = ctx.state().create_logical_plan(&statement).await?;
+        let view_table = ViewTable::new(plan, Some(statement));
+        ctx.register_table(
+            TableReference::full(wren_mdl.catalog(), wren_mdl.schema(), metric.name()),
+            Arc::new(view_table),
+        )?;
+    }
+    // a parameterized view isn't registered, the references to it are expanded with
+    // the arguments before planning the query
+    for view in wren_mdl
+        .manifest
+        .views
+        .iter()
+        .filter(|view| view.parameters.is_empty())
+    {
         let plan = ctx.state().create_logical_plan(&view.statement).await?;
         let view_table = ViewTable::new(plan, Some(view.statement.clone()));
         ctx.register_table(
@@ -327,6 +401,127 @@ pub async fn register_table_with_mdl(
     Ok(())
 }
 
+/// Generate the aggregation statement of the metric. The dimensions are grouped and
+/// the measures are computed by their expressions.
+///
+/// e.g. a metric based on `orders` with the dimension `o_custkey` and the measure
+/// `total: sum(o_totalprice)` will be
+/// `SELECT "o_custkey", sum(o_totalprice) AS "total" FROM "wren"."test"."orders" GROUP BY 1`
+///
+/// A measure with a filter aggregates only the rows matching it, e.g.
+/// `sum(o_totalprice) FILTER (WHERE o_orderstatus = 'paid')`. The filter may only reference
+/// the columns of the base model of the metric.
+pub(crate) fn metric_statement(wren_mdl: &WrenMDL, metric: &Metric) -> Result<String> {
+    let select_item = |column: &Column| match column.expression() {
+        Some(expression) => format!("{expression} AS {}", quoted(&column.name)),
+        None => quoted(&column.name),
+    };
+    if let Some(column) = metric.dimension.iter().find(|c| c.filter.is_some()) {
+        return plan_err!(
+            "The dimension {} of metric {} can't have a filter",
+            column.name,
+            metric.name()
+        );
+    }
+    let dimensions = metric.dimension.iter().map(|column| select_item(column));
+    let measures = metric
+        .measure
+        .iter()
+        .map(|column| match measure_expression(column) {
+            Some(expression) => {
+                if let Some(filter) = &column.filter {
+                    validate_measure_filter(wren_mdl, metric, column, filter)?;
+                }
+                Ok(format!("{expression} AS {}", quoted(&column.name)))
+            }
+            None => plan_err!(
+                "The measure {} of metric {} should have an expression",
+                column.name,
+                metric.name()
+            ),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut statement = format!(
+        "SELECT {} FROM {}.{}.{}",
+        dimensions.chain(measures).collect::<Vec<_>>().join(", "),
+        quoted(wren_mdl.catalog()),
+        quoted(wren_mdl.schema()),
+        quoted(&metric.base_object),
+    );
+    if !metric.dimension.is_empty() {
+        let group_by = (1..=metric.dimension.len())
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        statement.push_str(&format!(" GROUP BY {group_by}"));
+    }
+    Ok(statement)
+}
+
+/// The expression of the measure with its filter, e.g. `sum(o_totalprice) FILTER (WHERE
+/// o_orderstatus = 'paid')`. The dialects without `FILTER` get the filter moved into the
+/// aggregation when the query is unparsed.
+pub(crate) fn measure_expression(measure: &Column) -> Option<String> {
+    let expression = measure.expression()?;
+    Some(match &measure.filter {
+        Some(filter) => format!("{expression} FILTER (WHERE {filter})"),
+        None => expression.to_string(),
+    })
+}
+
+/// Reject the filter of the measure referencing a column that isn't in the base model.
+fn validate_measure_filter(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    measure: &Column,
+    filter: &str,
+) -> Result<()> {
+    let Some(model) = wren_mdl.get_model(&metric.base_object) else {
+        return plan_err!(
+            "The measure {} of metric {} has a filter, but the base object {} isn't a model",
+            measure.name,
+            metric.name(),
+            metric.base_object
+        );
+    };
+    let predicate = Parser::new(&GenericDialect {})
+        .try_with_sql(filter)?
+        .parse_expr()?;
+    let mut unknown = None;
+    let _ = visit_expressions(&predicate, |expr| {
+        let ident = match expr {
+            ast::Expr::Identifier(ident) => Some(ident),
+            ast::Expr::CompoundIdentifier(idents) => idents.last(),
+            _ => None,
+        };
+        let Some(ident) = ident else {
+            return ControlFlow::Continue(());
+        };
+        let found = model
+            .columns()
+            .iter()
+            .any(|column| match ident.quote_style {
+                Some(_) => column.name == ident.value,
+                None => column.name.eq_ignore_ascii_case(&ident.value),
+            });
+        if found {
+            ControlFlow::Continue(())
+        } else {
+            unknown = Some(ident.value.clone());
+            ControlFlow::Break(())
+        }
+    });
+    match unknown {
+        Some(column) => plan_err!(
+            "The filter of the measure {} of metric {} references {column}, which isn't a column of model {}",
+            measure.name,
+            metric.name(),
+            model.name()
+        ),
+        None => Ok(()),
+    }
+}
+
 #[derive(Debug)]
 pub struct WrenDataSource {
     schema: SchemaRef,
@@ -343,7 +538,10 @@ impl WrenDataSource {
             .get_physical_columns(true)
             .iter()
             .map(|column| {
+                // Without the lineage, the calculated field is registered and the error is
+                // raised only if it's planned, so the other columns are still queryable.
                 if mode.is_permission_analyze()
+                    || (column.is_calculated && analyzed_mdl.lineage().is_err())
                     || validate_clac_rule(
                         model.name(),
                         column,
@@ -395,3 +593,34 @@ impl TableProvider for WrenDataSource {
         unreachable!("WrenDataSource should be replaced before physical planning")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    use crate::mdl::context::Mode;
+
+    #[test]
+    fn test_mode_hash_is_distinct() {
+        // fails to compile if a variant is added, as a reminder to update `Mode::ALL`
+        let variant_count = |mode: Mode| match mode {
+            Mode::LocalRuntime | Mode::Unparse | Mode::PermissionAnalyze => 3,
+        };
+        assert_eq!(Mode::ALL.len(), variant_count(Mode::Unparse));
+
+        let hash = |mode: &Mode| {
+            let mut hasher = DefaultHasher::new();
+            mode.hash(&mut hasher);
+            hasher.finish()
+        };
+        let hashes: HashSet<_> = Mode::ALL.iter().map(hash).collect();
+        assert_eq!(hashes.len(), Mode::ALL.len());
+        let discriminants: HashSet<_> =
+            Mode::ALL.iter().map(|mode| mode.discriminant()).collect();
+        assert_eq!(discriminants.len(), Mode::ALL.len());
+        // the hash is stable for the same mode
+        assert_eq!(hash(&Mode::Unparse), hash(&Mode::Unparse));
+    }
+}
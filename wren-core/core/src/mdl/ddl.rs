@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::SessionContext;
+use datafusion::sql::sqlparser::ast::{visit_relations, Ident, ObjectNamePart, Value};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::unparser::dialect::Dialect;
+
+use crate::mdl::context::{apply_wren_on_ctx, Mode};
+use crate::mdl::dialect::WrenDialect;
+use crate::mdl::format::NameQualification;
+use crate::mdl::manifest::{DataSource, View};
+use crate::mdl::table_sample::TableSamples;
+use crate::mdl::{create_logical_plan, unparse_plan, AnalyzedWrenMDL, WrenMDL};
+
+impl AnalyzedWrenMDL {
+    /// Generate the `CREATE OR REPLACE VIEW` statements of the views for the target dialect.
+    ///
+    /// The statements are ordered by the dependencies, so a view is created after the views
+    /// it references. The body of a view is its statement with the models expanded, like
+    /// the SQL produced by the transformation. The parameterized views are skipped because
+    /// they can't be created without the arguments.
+    ///
+    /// If the dialect has `COMMENT ON`, the descriptions follow the statement of the view
+    /// as comments. A column of the view selecting a column of a model as is, e.g. `c_name`
+    /// of `SELECT c_name FROM customer`, is commented by the description of the column of
+    /// the model. The other dialects get no comments.
+    pub async fn generate_view_ddl(
+        self: &Arc<Self>,
+        ctx: &SessionContext,
+        target_dialect: &DataSource,
+    ) -> Result<Vec<String>> {
+        let ctx = apply_wren_on_ctx(
+            ctx,
+            Arc::clone(self),
+            Arc::new(HashMap::new()),
+            Mode::Unparse,
+        )
+        .await?;
+        let wren_mdl = self.wren_mdl();
+        let dialect = WrenDialect::new(target_dialect);
+        let mut statements = vec![];
+        for view in sort_views_by_dependency(&wren_mdl)? {
+            if !view.parameters.is_empty() {
+                continue;
+            }
+            let plan = create_logical_plan(&ctx, &wren_mdl, &view.statement).await?;
+            let analyzed = ctx.state().optimize(&plan)?;
+            let body = unparse_plan(
+                &wren_mdl,
+                &analyzed,
+                target_dialect,
+                NameQualification::Full,
+                &TableSamples::default(),
+            )?;
+            let name = ident(&dialect, view.name());
+            statements.push(format!("CREATE OR REPLACE VIEW {name} AS {body}"));
+
+            let Some(object_type) = dialect.comment_on_view_type() else {
+                continue;
+            };
+            if let Some(description) = &view.description {
+                statements.push(format!(
+                    "COMMENT ON {object_type} {name} IS {}",
+                    Value::SingleQuotedString(description.clone())
+                ));
+            }
+            for (column, description) in column_descriptions(&wren_mdl, &plan) {
+                statements.push(format!(
+                    "COMMENT ON COLUMN {name}.{} IS {}",
+                    ident(&dialect, &column),
+                    Value::SingleQuotedString(description)
+                ));
+            }
+        }
+        Ok(statements)
+    }
+}
+
+fn ident(dialect: &WrenDialect, name: &str) -> Ident {
+    match dialect.identifier_quote_style(name) {
+        Some(quote) => Ident::with_quote(quote, name),
+        None => Ident::new(name),
+    }
+}
+
+/// The descriptions of the output columns of the plan which are the described columns of
+/// the models as is.
+fn column_descriptions(wren_mdl: &WrenMDL, plan: &LogicalPlan) -> Vec<(String, String)> {
+    plan.schema()
+        .iter()
+        .filter_map(|(qualifier, field)| {
+            let model = wren_mdl.get_model(qualifier?.table())?;
+            let description = model.column(field.name())?.description.clone()?;
+            Some((field.name().clone(), description))
+        })
+        .collect()
+}
+
+/// Sort the views so that a view comes after the views it references. The views without
+/// a dependency between them keep the order of the manifest.
+fn sort_views_by_dependency(wren_mdl: &WrenMDL) -> Result<Vec<Arc<View>>> {
+    let views = wren_mdl.views();
+    let names: HashSet<&str> = views.iter().map(|view| view.name()).collect();
+    let mut dependencies: HashMap<&str, HashSet<String>> = HashMap::new();
+    for view in views {
+        let statement = Parser::parse_sql(&GenericDialect {}, &view.statement)?;
+        let mut referenced = HashSet::new();
+        let _ = visit_relations(&statement, |relation| {
+            if let Some(ObjectNamePart::Identifier(ident)) = relation.0.last() {
+                if ident.value != view.name && names.contains(ident.value.as_str()) {
+                    referenced.insert(ident.value.clone());
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        dependencies.insert(view.name(), referenced);
+    }
+
+    let mut sorted: Vec<Arc<View>> = Vec::with_capacity(views.len());
+    let mut created: HashSet<String> = HashSet::new();
+    while sorted.len() < views.len() {
+        let ready: Vec<_> = views
+            .iter()
+            .filter(|view| !created.contains(&view.name))
+            .filter(|view| dependencies[view.name()].is_subset(&created))
+            .cloned()
+            .collect();
+        if ready.is_empty() {
+            let mut cyclic: Vec<_> = views
+                .iter()
+                .map(|view| view.name())
+                .filter(|name| !created.contains(*name))
+                .collect();
+            cyclic.sort();
+            return plan_err!(
+                "The views {} reference each other cyclically",
+                cyclic.join(", ")
+            );
+        }
+        for view in ready {
+            created.insert(view.name.clone());
+            sorted.push(view);
+        }
+    }
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use datafusion::error::Result;
+    use insta::assert_snapshot;
+
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, ViewBuilder,
+    };
+    use crate::mdl::context::Mode;
+    use crate::mdl::manifest::DataSource;
+    use crate::mdl::{create_wren_ctx, AnalyzedWrenMDL};
+
+    #[tokio::test]
+    async fn test_generate_view_ddl() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            // declared before the view it references
+            .view(
+                ViewBuilder::new("top_customer")
+                    .statement("select c_name from wren.test.customer_view limit 10")
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement("select c_custkey, c_name from wren.test.customer")
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_names")
+                    .statement("select c_name from wren.test.top_customer")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let ctx = create_wren_ctx(None);
+        let ddl = analyzed_mdl
+            .generate_view_ddl(&ctx, &DataSource::Postgres)
+            .await?;
+        // the models and the referenced views are expanded and the mdl prefix is removed
+        assert_snapshot!(ddl.join("\n"), @r"
+        CREATE OR REPLACE VIEW customer_view AS SELECT customer.c_custkey, customer.c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer
+        CREATE OR REPLACE VIEW top_customer AS SELECT customer_view.c_name FROM (SELECT customer.c_name FROM (SELECT customer.c_name FROM (SELECT __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer) AS customer_view LIMIT 10
+        CREATE OR REPLACE VIEW customer_names AS SELECT top_customer.c_name FROM (SELECT customer_view.c_name FROM (SELECT customer.c_name FROM (SELECT customer.c_name FROM (SELECT __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer) AS customer_view LIMIT 10) AS top_customer
+        ");
+
+        // a lowercase identifier is quoted in Oracle
+        let ddl = analyzed_mdl
+            .generate_view_ddl(&ctx, &DataSource::Oracle)
+            .await?;
+        let (head, _) = ddl[0].split_once(" AS ").unwrap();
+        assert_snapshot!(head, @r#"CREATE OR REPLACE VIEW "customer_view""#);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_view_ddl_comments() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(
+                        ColumnBuilder::new("c_custkey", "int")
+                            .description("The customer's key")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("c_name", "varchar")
+                            .description("The name of the customer")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement(
+                        "select c_custkey, upper(c_name) as c_name from wren.test.customer",
+                    )
+                    .description("The customers")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let ctx = create_wren_ctx(None);
+        let ddl = analyzed_mdl
+            .generate_view_ddl(&ctx, &DataSource::Postgres)
+            .await?;
+        // the computed c_name doesn't inherit the description of the model column
+        assert_snapshot!(ddl[1..].join("\n"), @r"
+        COMMENT ON VIEW customer_view IS 'The customers'
+        COMMENT ON COLUMN customer_view.c_custkey IS 'The customer''s key'
+        ");
+
+        let ddl = analyzed_mdl
+            .generate_view_ddl(&ctx, &DataSource::Oracle)
+            .await?;
+        assert_snapshot!(ddl[1], @r#"COMMENT ON TABLE "customer_view" IS 'The customers'"#);
+
+        // MySQL has no COMMENT ON
+        let ddl = analyzed_mdl
+            .generate_view_ddl(&ctx, &DataSource::MySQL)
+            .await?;
+        assert_snapshot!(ddl.join("\n"), @"CREATE OR REPLACE VIEW customer_view AS SELECT customer.c_custkey, upper(customer.c_name) AS c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cyclic_views() {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .view(ViewBuilder::new("a").statement("select * from b").build())
+            .view(ViewBuilder::new("b").statement("select * from a").build())
+            .build();
+        let wren_mdl = crate::mdl::WrenMDL::new(manifest);
+        match super::sort_views_by_dependency(&wren_mdl) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The views a, b reference each other cyclically"
+            ),
+            Ok(_) => panic!("should be error"),
+        }
+    }
+}
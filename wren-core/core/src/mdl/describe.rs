@@ -0,0 +1,411 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::common::tree_node::TreeNodeRecursion;
+use datafusion::common::{plan_datafusion_err, Result};
+use datafusion::datasource::source_as_provider;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::SessionContext;
+
+use crate::logical_plan::analyze::access_control::{
+    resolve_rule_condition, validate_clac_rule, validate_rule,
+};
+use crate::mdl::clock::Clock;
+use crate::mdl::context::{
+    apply_wren_on_ctx, with_default_properties, Mode, SessionPropertiesRef,
+};
+use crate::mdl::{create_logical_plan, AnalyzedWrenMDL, ExecuteOptions, WrenMDL};
+
+/// The session property to configure the max age of the data of a model, in seconds. The
+/// models of the query refreshed longer ago are reported by [describe_query].
+pub const MAX_STALENESS_PROPERTY: &str = "x-wren-max-staleness";
+
+/// What the transformation of a query would apply, computed without running the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryDescription {
+    /// The models referenced by the query, including the models behind the views
+    pub models: Vec<String>,
+    /// The row level access control rules filtering the rows of the models
+    pub row_level_rules: Vec<AppliedRowLevelRule>,
+    /// The column level access control rules evaluated for the columns of the models
+    pub column_level_rules: Vec<AppliedColumnLevelRule>,
+    /// The models refreshed longer ago than [MAX_STALENESS_PROPERTY]
+    pub stale_models: Vec<StaleModel>,
+    /// The row limit the execution injects on top of the `LIMIT` of the SQL, see
+    /// [ExecuteOptions::row_limit]
+    pub row_limit: Option<usize>,
+}
+
+/// A model of the query whose data is older than the max staleness.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleModel {
+    pub model: String,
+    /// The time since the model was last refreshed
+    pub age: Duration,
+}
+
+/// A row level access control rule applied to a model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedRowLevelRule {
+    pub model: String,
+    pub name: String,
+    /// The condition of the rule with the session properties replaced by their values
+    pub predicate: String,
+}
+
+/// A column level access control rule evaluated for a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedColumnLevelRule {
+    pub model: String,
+    pub column: String,
+    pub name: String,
+    /// If false, the column can't be accessed with the session properties
+    pub allowed: bool,
+}
+
+/// Describe the models and the access control rules the transformation of the SQL would
+/// apply with the session properties. The rules are reported instead of being enforced,
+/// so a query touching a denied column is still described.
+///
+/// A rule is listed only if it applies, i.e. its required properties are provided or have
+/// default values. The models are sorted by name and the rules follow the order of the
+/// models in the manifest.
+///
+/// If [MAX_STALENESS_PROPERTY] is set, the models refreshed longer ago than the time of the
+/// [Clock] of the MDL are reported as stale. The models without a known refresh time are
+/// never stale.
+pub async fn describe_query(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    sql: &str,
+) -> Result<QueryDescription> {
+    describe_query_with_options(
+        ctx,
+        analyzed_mdl,
+        properties,
+        sql,
+        &ExecuteOptions::default(),
+    )
+    .await
+}
+
+/// Describe the query like [describe_query] for the execution by
+/// [crate::mdl::execute_stream_with_options] with the [ExecuteOptions], so the row limit
+/// of the options, e.g. the preview limit, is reported as well.
+pub async fn describe_query_with_options(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    sql: &str,
+    options: &ExecuteOptions,
+) -> Result<QueryDescription> {
+    let properties =
+        with_default_properties(&analyzed_mdl.wren_mdl().manifest, properties);
+    // Plan in the permission analyze mode, so the denied columns are still registered.
+    let ctx = apply_wren_on_ctx(
+        ctx,
+        Arc::clone(&analyzed_mdl),
+        Arc::clone(&properties),
+        Mode::PermissionAnalyze,
+    )
+    .await?;
+    let wren_mdl = analyzed_mdl.wren_mdl();
+    let plan = create_logical_plan(&ctx, &wren_mdl, sql).await?;
+    let mut models = BTreeSet::new();
+    collect_models(&wren_mdl, &plan, &mut models)?;
+
+    let mut description = QueryDescription {
+        models: models.iter().cloned().collect(),
+        row_limit: options.row_limit(),
+        ..Default::default()
+    };
+    if let Some(max_staleness) = max_staleness(&properties)? {
+        description.stale_models = wren_mdl
+            .manifest
+            .freshness_report(analyzed_mdl.clock().now())
+            .into_iter()
+            .filter(|(model, _)| models.contains(model))
+            .filter_map(|(model, age)| {
+                age.filter(|age| *age > max_staleness)
+                    .map(|age| StaleModel { model, age })
+            })
+            .collect();
+    }
+    for model in wren_mdl
+        .models()
+        .iter()
+        .filter(|model| models.contains(model.name()))
+    {
+        for rule in model.row_level_access_controls() {
+            if validate_rule(&rule.name, &rule.required_properties, &properties)? {
+                description.row_level_rules.push(AppliedRowLevelRule {
+                    model: model.name().to_string(),
+                    name: rule.name.clone(),
+                    predicate: resolve_rule_condition(&properties, rule)?.to_string(),
+                });
+            }
+        }
+        for column in model.columns() {
+            if let (allowed, Some(name)) =
+                validate_clac_rule(model.name(), column, &properties, None)?
+            {
+                description.column_level_rules.push(AppliedColumnLevelRule {
+                    model: model.name().to_string(),
+                    column: column.name().to_string(),
+                    name,
+                    allowed,
+                });
+            }
+        }
+    }
+    Ok(description)
+}
+
+fn max_staleness(properties: &SessionPropertiesRef) -> Result<Option<Duration>> {
+    let Some(Some(value)) = properties.get(MAX_STALENESS_PROPERTY) else {
+        return Ok(None);
+    };
+    value
+        .parse::<u64>()
+        .map(|secs| Some(Duration::from_secs(secs)))
+        .map_err(|_| {
+            plan_datafusion_err!(
+                "Invalid value for {MAX_STALENESS_PROPERTY}: {value}. Expected the seconds"
+            )
+        })
+}
+
+/// Collect the models scanned by the plan. The plans of the views are walked as well.
+fn collect_models(
+    wren_mdl: &WrenMDL,
+    plan: &LogicalPlan,
+    models: &mut BTreeSet<String>,
+) -> Result<()> {
+    plan.apply_with_subqueries(|plan| {
+        let LogicalPlan::TableScan(scan) = plan else {
+            return Ok(TreeNodeRecursion::Continue);
+        };
+        let name = &scan.table_name;
+        let in_mdl = name
+            .catalog()
+            .map_or(true, |catalog| catalog == wren_mdl.catalog())
+            && name
+                .schema()
+                .map_or(true, |schema| schema == wren_mdl.schema());
+        if in_mdl && wren_mdl.get_model(name.table()).is_some() {
+            models.insert(name.table().to_string());
+        } else if let Some(view_plan) = source_as_provider(&scan.source)?
+            .get_logical_plan()
+            .map(|plan| plan.into_owned())
+        {
+            collect_models(wren_mdl, &view_plan, models)?;
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use datafusion::error::Result;
+
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, ViewBuilder,
+    };
+    use crate::mdl::clock::FixedClock;
+    use crate::mdl::context::Mode;
+    use crate::mdl::describe::{
+        describe_query, describe_query_with_options, AppliedColumnLevelRule,
+        AppliedRowLevelRule, StaleModel, MAX_STALENESS_PROPERTY,
+    };
+    use crate::mdl::manifest::{ColumnLevelOperator, SessionProperty};
+    use crate::mdl::{create_wren_ctx, AnalyzeOptions, AnalyzedWrenMDL, ExecuteOptions};
+
+    #[tokio::test]
+    async fn test_describe_query() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new("c_name", "varchar")
+                            .column_level_access_control(
+                                "hide_name",
+                                vec![SessionProperty::new_required("session_level")],
+                                ColumnLevelOperator::GreaterThan,
+                                "3",
+                            )
+                            .build(),
+                    )
+                    .add_row_level_access_control(
+                        "own_customer",
+                        vec![SessionProperty::new_required("session_user")],
+                        "c_custkey = @session_user",
+                    )
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .add_row_level_access_control(
+                        "recent_orders",
+                        vec![SessionProperty::new_optional("session_order", None)],
+                        "o_orderkey > @session_order",
+                    )
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement("select c_custkey, c_name from wren.test.customer")
+                    .build(),
+            )
+            .build();
+        let properties = Arc::new(HashMap::from([
+            ("session_user".to_string(), Some("1".to_string())),
+            ("session_level".to_string(), Some("2".to_string())),
+        ]));
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::clone(&properties),
+            Mode::Unparse,
+        )?);
+        let description = describe_query(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            Arc::clone(&properties),
+            "select c_name from wren.test.customer_view",
+        )
+        .await?;
+        assert_eq!(description.models, vec!["customer"]);
+        assert_eq!(
+            description.row_level_rules,
+            vec![AppliedRowLevelRule {
+                model: "customer".to_string(),
+                name: "own_customer".to_string(),
+                predicate: "c_custkey = 1".to_string(),
+            }]
+        );
+        assert_eq!(
+            description.column_level_rules,
+            vec![AppliedColumnLevelRule {
+                model: "customer".to_string(),
+                column: "c_name".to_string(),
+                name: "hide_name".to_string(),
+                allowed: false,
+            }]
+        );
+        assert_eq!(description.row_limit, None);
+
+        // the rule of orders doesn't apply without its optional session property
+        let description = describe_query(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            Arc::clone(&properties),
+            "select * from wren.test.orders",
+        )
+        .await?;
+        assert_eq!(description.models, vec!["orders"]);
+        assert!(description.row_level_rules.is_empty());
+
+        // the smaller one of the max rows and the preview limit is injected
+        let description = describe_query_with_options(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            properties,
+            "select * from wren.test.orders limit 500",
+            &ExecuteOptions::new()
+                .with_max_rows(1000)
+                .with_preview_limit(100),
+        )
+        .await?;
+        assert_eq!(description.models, vec!["orders"]);
+        assert_eq!(description.row_limit, Some(100));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_describe_stale_models() -> Result<()> {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .last_refreshed(now - Duration::from_secs(7200))
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .last_refreshed(now)
+                    .build(),
+            )
+            .build();
+        let clock = Arc::new(FixedClock::new(now));
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new().with_clock(Arc::clone(&clock) as _),
+        )?);
+        let sql = "select * from wren.test.customer c join wren.test.orders o \
+            on c.c_custkey = o.o_custkey";
+        let describe = |properties: HashMap<String, Option<String>>| {
+            describe_query(
+                &create_wren_ctx(None),
+                Arc::clone(&analyzed_mdl),
+                Arc::new(properties),
+                sql,
+            )
+        };
+
+        let description = describe(HashMap::new()).await?;
+        assert!(description.stale_models.is_empty());
+
+        let description = describe(HashMap::from([(
+            MAX_STALENESS_PROPERTY.to_string(),
+            Some("3600".to_string()),
+        )]))
+        .await?;
+        assert_eq!(
+            description.stale_models,
+            vec![StaleModel {
+                model: "customer".to_string(),
+                age: Duration::from_secs(7200),
+            }]
+        );
+
+        // the age is measured up to the time of the clock of the MDL
+        clock.advance(Duration::from_secs(3600));
+        let description = describe(HashMap::from([(
+            MAX_STALENESS_PROPERTY.to_string(),
+            Some("3000".to_string()),
+        )]))
+        .await?;
+        assert_eq!(
+            description.stale_models,
+            vec![
+                StaleModel {
+                    model: "customer".to_string(),
+                    age: Duration::from_secs(10800),
+                },
+                StaleModel {
+                    model: "orders".to_string(),
+                    age: Duration::from_secs(3600),
+                },
+            ]
+        );
+        Ok(())
+    }
+}
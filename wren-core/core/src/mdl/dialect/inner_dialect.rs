@@ -17,11 +17,12 @@
  * under the License.
  */
 
+use crate::mdl::dialect::reserved_words::BIGQUERY_RESERVED_WORDS;
 use crate::mdl::dialect::utils::scalar_function_to_sql_internal;
 use crate::mdl::manifest::DataSource;
 use datafusion::common::{plan_err, Result};
 use datafusion::logical_expr::sqlparser::keywords::ALL_KEYWORDS;
-use datafusion::logical_expr::{Expr, LogicalPlan};
+use datafusion::logical_expr::{lit, Expr, LogicalPlan};
 
 use datafusion::scalar::ScalarValue;
 use datafusion::sql::sqlparser::ast::{
@@ -30,6 +31,7 @@ use datafusion::sql::sqlparser::ast::{
 use datafusion::sql::unparser::ast::{
     RelationBuilder, TableFactorBuilder, TableFunctionRelationBuilder,
 };
+use datafusion::sql::unparser::dialect::IntervalStyle;
 use datafusion::sql::unparser::Unparser;
 use regex::Regex;
 
@@ -88,6 +90,112 @@ pub trait InnerDialect: Send + Sync {
     ) -> bool {
         false
     }
+
+    /// A wrapper for [datafusion::sql::unparser::dialect::Dialect::interval_style].
+    /// The default `INTERVAL 30 DAY` is accepted by MySQL, BigQuery, DuckDB, ClickHouse and Databricks.
+    fn interval_style(&self) -> IntervalStyle {
+        IntervalStyle::MySQL
+    }
+
+    /// Whether `ORDER BY` accepts `NULLS FIRST` and `NULLS LAST`. If not, the dialect is
+    /// assumed to sort the nulls as the smallest values, like MySQL and SQL Server do.
+    fn supports_nulls_first_in_sort(&self) -> bool {
+        true
+    }
+
+    /// The literal of a boolean value. The default is `true` or `false`.
+    fn boolean_literal(&self, value: bool) -> ast::Expr {
+        ast::Expr::value(ast::Value::Boolean(value))
+    }
+
+    /// The predicate of a constant boolean value, used where a condition is expected, e.g.
+    /// `WHERE true`. The default is the [InnerDialect::boolean_literal].
+    fn boolean_predicate(&self, value: bool) -> ast::Expr {
+        self.boolean_literal(value)
+    }
+
+    /// The uppercase words quoted when they're used as an identifier. The default is all
+    /// the keywords known by sqlparser, which quotes some words the dialect doesn't reserve.
+    fn reserved_words(&self) -> &'static [&'static str] {
+        ALL_KEYWORDS
+    }
+
+    /// The placeholder of the parameter at the 1-based index. The default is the
+    /// positional `?`, accepted by MySQL, BigQuery, Trino and the ODBC drivers.
+    fn placeholder(&self, _index: usize) -> String {
+        "?".to_string()
+    }
+
+    /// Whether the dialect accepts `TABLESAMPLE` on a table, e.g. `TABLESAMPLE SYSTEM (10)`.
+    /// If not, a query with the clause is rejected instead of scanning the whole table.
+    fn supports_table_sample(&self) -> bool {
+        false
+    }
+
+    /// The object type of a view in `COMMENT ON`, e.g. `VIEW` in `COMMENT ON VIEW v IS
+    /// 'description'`. The columns are commented by `COMMENT ON COLUMN v.c IS '...'`. The
+    /// default is `None`, i.e. the dialect has no `COMMENT ON` and the comments are omitted.
+    fn comment_on_view_type(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether the dialect accepts `FILTER (WHERE ...)` on an aggregate function, e.g. the
+    /// filtered measures of a metric. If not, the filter is moved into the aggregated value,
+    /// e.g. `sum(CASE WHEN p THEN x END)` for `sum(x) FILTER (WHERE p)`.
+    fn supports_aggregate_filter(&self) -> bool {
+        true
+    }
+
+    /// The concatenation of the strings by the `||` operator of DataFusion, which returns
+    /// null if either side is null. The default is `||`. The `concat` function skips the
+    /// nulls instead, so it's rewritten by [InnerDialect::scalar_function_to_sql_overrides]
+    /// if the function of the dialect doesn't.
+    fn string_concat(&self, left: ast::Expr, right: ast::Expr) -> ast::Expr {
+        ast::Expr::BinaryOp {
+            left: Box::new(left),
+            op: ast::BinaryOperator::StringConcat,
+            right: Box::new(right),
+        }
+    }
+}
+
+/// The `CONCAT(left, right)` of the dialects where `||` isn't the concatenation.
+fn concat_function(left: ast::Expr, right: ast::Expr) -> ast::Expr {
+    ast::Expr::Function(Function {
+        name: ObjectName(vec![ObjectNamePart::Identifier(Ident::new("CONCAT"))]),
+        uses_odbc_syntax: false,
+        parameters: ast::FunctionArguments::None,
+        args: ast::FunctionArguments::List(ast::FunctionArgumentList {
+            args: [left, right]
+                .into_iter()
+                .map(|e| ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(e)))
+                .collect(),
+            duplicate_treatment: None,
+            clauses: vec![],
+        }),
+        filter: None,
+        null_treatment: None,
+        over: None,
+        within_group: vec![],
+    })
+}
+
+/// The boolean literal of the dialects without a boolean type, `1` or `0`.
+fn numeric_boolean_literal(value: bool) -> ast::Expr {
+    ast::Expr::value(ast::Value::Number(
+        if value { "1" } else { "0" }.to_string(),
+        false,
+    ))
+}
+
+/// The predicate of the dialects without a boolean type, `1 = 1` or `1 = 0`. A number isn't
+/// accepted as a condition.
+fn numeric_boolean_predicate(value: bool) -> ast::Expr {
+    ast::Expr::BinaryOp {
+        left: Box::new(numeric_boolean_literal(true)),
+        op: ast::BinaryOperator::Eq,
+        right: Box::new(numeric_boolean_literal(value)),
+    }
 }
 
 /// [get_inner_dialect] returns the suitable InnerDialect for the given data source.
@@ -98,6 +206,8 @@ pub fn get_inner_dialect(data_source: &DataSource) -> Box<dyn InnerDialect> {
         DataSource::Oracle => Box::new(OracleDialect {}),
         DataSource::MSSQL => Box::new(MsSqlDialect {}),
         DataSource::Snowflake => Box::new(SnowflakeDialect {}),
+        DataSource::Postgres | DataSource::Redshift => Box::new(PostgresDialect {}),
+        DataSource::Trino | DataSource::Athena => Box::new(TrinoDialect {}),
         _ => Box::new(GenericDialect {}),
     }
 }
@@ -108,10 +218,56 @@ pub struct GenericDialect {}
 
 impl InnerDialect for GenericDialect {}
 
+/// [PostgresDialect] is a dialect that overrides the SQL generation for Postgres and Redshift.
+pub struct PostgresDialect {}
+
+impl InnerDialect for PostgresDialect {
+    fn supports_table_sample(&self) -> bool {
+        true
+    }
+
+    fn comment_on_view_type(&self) -> Option<&'static str> {
+        Some("VIEW")
+    }
+
+    /// Postgres doesn't accept `INTERVAL 30 DAY`. The interval should be a quoted string,
+    /// e.g. `INTERVAL '30 DAYS'`.
+    fn interval_style(&self) -> IntervalStyle {
+        IntervalStyle::PostgresVerbose
+    }
+
+    fn placeholder(&self, index: usize) -> String {
+        format!("${index}")
+    }
+}
+
+/// [TrinoDialect] is a dialect that overrides the SQL generation for Trino and Athena.
+pub struct TrinoDialect {}
+
+impl InnerDialect for TrinoDialect {
+    fn supports_table_sample(&self) -> bool {
+        true
+    }
+
+    /// Trino only accepts the SQL standard interval, e.g. `INTERVAL '30' DAY`.
+    fn interval_style(&self) -> IntervalStyle {
+        IntervalStyle::SQLStandard
+    }
+}
+
 /// [MySQLDialect] is a dialect that overrides the SQL generation for MySQL dialect.
 pub struct MySQLDialect {}
 
 impl InnerDialect for MySQLDialect {
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    /// MySQL sorts the nulls first in the ascending order and has no `NULLS FIRST`.
+    fn supports_nulls_first_in_sort(&self) -> bool {
+        false
+    }
+
     fn scalar_function_to_sql_overrides(
         &self,
         unparser: &Unparser,
@@ -120,18 +276,49 @@ impl InnerDialect for MySQLDialect {
     ) -> Result<Option<ast::Expr>> {
         match function_name {
             "btrim" => scalar_function_to_sql_internal(unparser, None, "trim", args),
+            // `CONCAT` of MySQL returns null if any argument is null, `CONCAT_WS` skips them
+            "concat" => {
+                let args: Vec<Expr> = std::iter::once(lit(""))
+                    .chain(args.iter().cloned())
+                    .collect();
+                scalar_function_to_sql_internal(unparser, None, "concat_ws", &args)
+            }
             _ => Ok(None),
         }
     }
+
+    /// `||` is the logical OR in MySQL unless `PIPES_AS_CONCAT` is set. `CONCAT` returns
+    /// null if any argument is null, like `||` of DataFusion.
+    fn string_concat(&self, left: ast::Expr, right: ast::Expr) -> ast::Expr {
+        concat_function(left, right)
+    }
 }
 
 pub struct BigQueryDialect {}
 
 impl InnerDialect for BigQueryDialect {
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    fn supports_table_sample(&self) -> bool {
+        true
+    }
+
     fn unnest_as_table_factor(&self) -> bool {
         true
     }
 
+    fn reserved_words(&self) -> &'static [&'static str] {
+        BIGQUERY_RESERVED_WORDS
+    }
+
+    /// `CONCAT` of BigQuery returns null if any argument is null, like `||` of DataFusion.
+    /// Unlike DataFusion, the `concat` function of BigQuery also returns null then.
+    fn string_concat(&self, left: ast::Expr, right: ast::Expr) -> ast::Expr {
+        concat_function(left, right)
+    }
+
     fn col_alias_overrides(&self, alias: &str) -> Result<Option<String>> {
         // Check if alias contains any special characters not supported by BigQuery col names
         // https://cloud.google.com/bigquery/docs/schemas#flexible-column-names
@@ -315,6 +502,33 @@ impl BigQueryDialect {
 pub struct OracleDialect {}
 
 impl InnerDialect for OracleDialect {
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    fn placeholder(&self, index: usize) -> String {
+        format!(":{index}")
+    }
+
+    /// Oracle comments on a view as a table.
+    fn comment_on_view_type(&self) -> Option<&'static str> {
+        Some("TABLE")
+    }
+
+    /// Oracle only accepts the SQL standard interval, e.g. `INTERVAL '30' DAY`.
+    fn interval_style(&self) -> IntervalStyle {
+        IntervalStyle::SQLStandard
+    }
+
+    /// Oracle has no boolean type in SQL before 23ai.
+    fn boolean_literal(&self, value: bool) -> ast::Expr {
+        numeric_boolean_literal(value)
+    }
+
+    fn boolean_predicate(&self, value: bool) -> ast::Expr {
+        numeric_boolean_predicate(value)
+    }
+
     fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
         // Oracle defaults to upper case for identifiers
         let identifier_regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
@@ -337,6 +551,38 @@ fn non_uppercase(sql: &str) -> bool {
 pub struct MsSqlDialect {}
 
 impl InnerDialect for MsSqlDialect {
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    fn supports_table_sample(&self) -> bool {
+        true
+    }
+
+    /// SQL Server sorts the nulls first in the ascending order and has no `NULLS FIRST`.
+    fn supports_nulls_first_in_sort(&self) -> bool {
+        false
+    }
+
+    /// SQL Server has no boolean literal, the `bit` values are 1 and 0.
+    fn boolean_literal(&self, value: bool) -> ast::Expr {
+        numeric_boolean_literal(value)
+    }
+
+    fn boolean_predicate(&self, value: bool) -> ast::Expr {
+        numeric_boolean_predicate(value)
+    }
+
+    /// SQL Server concatenates the strings by `+`, which returns null if either side is
+    /// null unless `CONCAT_NULL_YIELDS_NULL` is off. `||` is only accepted since 2025.
+    fn string_concat(&self, left: ast::Expr, right: ast::Expr) -> ast::Expr {
+        ast::Expr::Nested(Box::new(ast::Expr::BinaryOp {
+            left: Box::new(left),
+            op: ast::BinaryOperator::Plus,
+            right: Box::new(right),
+        }))
+    }
+
     fn to_unicode_string_literal(&self, s: &str) -> Option<ast::Expr> {
         if !s.is_ascii() {
             Some(ast::Expr::value(ast::Value::NationalStringLiteral(
@@ -353,6 +599,23 @@ pub static UNNAMED_SNOWFLAKE_FLATTEN_SUBQUERY_PREFIX: &str = "__unnamed_flatten_
 pub struct SnowflakeDialect {}
 
 impl InnerDialect for SnowflakeDialect {
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    fn supports_table_sample(&self) -> bool {
+        true
+    }
+
+    fn comment_on_view_type(&self) -> Option<&'static str> {
+        Some("VIEW")
+    }
+
+    /// Snowflake requires the value and the unit in a quoted string, e.g. `INTERVAL '30 DAYS'`.
+    fn interval_style(&self) -> IntervalStyle {
+        IntervalStyle::PostgresVerbose
+    }
+
     fn unnest_as_table_factor(&self) -> bool {
         true
     }
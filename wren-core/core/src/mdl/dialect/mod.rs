@@ -18,6 +18,9 @@
  */
 
 mod inner_dialect;
+mod normalize;
+mod parameterize;
+mod reserved_words;
 mod utils;
 mod wren_dialect;
 
@@ -0,0 +1,270 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::ast::helpers::attached_token::AttachedToken;
+use datafusion::sql::sqlparser::ast::{
+    BinaryOperator, CaseWhen, Expr, Function, FunctionArg, FunctionArgExpr,
+    FunctionArgumentClause, FunctionArguments, JoinConstraint, JoinOperator, OrderByExpr,
+    OrderByKind, OrderByOptions, Query, Select, SetExpr, Statement, UnaryOperator, Value,
+    ValueWithSpan, VisitMut, VisitorMut, WindowType,
+};
+
+use crate::mdl::dialect::inner_dialect::InnerDialect;
+
+/// Rewrite the unparsed statement to the forms of the dialect that DataFusion doesn't
+/// produce itself, i.e. the boolean literals and predicates, the string concatenation, the null ordering
+/// of `ORDER BY` and the `FILTER` of the aggregations.
+pub(crate) fn normalize_statement(dialect: &dyn InnerDialect, statement: &mut Statement) {
+    let _ = statement.visit(&mut Normalizer { dialect });
+}
+
+struct Normalizer<'a> {
+    dialect: &'a dyn InnerDialect,
+}
+
+impl Normalizer<'_> {
+    /// DataFusion always unparses the explicit `NULLS FIRST` or `NULLS LAST`. If the dialect
+    /// doesn't accept the clause, the nulls are sorted as the smallest values. The clause is
+    /// dropped if the order is the same, or an order key putting the nulls aside is added.
+    fn normalize_order_by(&self, exprs: &mut Vec<OrderByExpr>) {
+        if self.dialect.supports_nulls_first_in_sort() {
+            return;
+        }
+        let mut normalized = Vec::with_capacity(exprs.len());
+        for mut expr in exprs.drain(..) {
+            let asc = expr.options.asc.unwrap_or(true);
+            if let Some(nulls_first) = expr.options.nulls_first.take() {
+                if nulls_first != asc {
+                    normalized.push(OrderByExpr {
+                        expr: nulls_aside(&expr.expr, nulls_first),
+                        options: OrderByOptions {
+                            asc: Some(true),
+                            nulls_first: None,
+                        },
+                        with_fill: None,
+                    });
+                }
+            }
+            normalized.push(expr);
+        }
+        *exprs = normalized;
+    }
+
+    /// Replace the boolean literal in a predicate position by the predicate of the dialect,
+    /// e.g. `WHERE true` to `WHERE 1 = 1`. The literals left are in value positions and
+    /// replaced by [InnerDialect::boolean_literal] in [Normalizer::post_visit_expr].
+    fn normalize_predicate(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Value(ValueWithSpan {
+                value: Value::Boolean(value),
+                ..
+            }) => *expr = self.dialect.boolean_predicate(*value),
+            Expr::Nested(nested) => self.normalize_predicate(nested),
+            _ => {}
+        }
+    }
+
+    /// Normalize the conditions of the select, i.e. `WHERE`, `HAVING`, `QUALIFY` and the
+    /// `ON` of the joins, and of the selects of the set operations.
+    fn normalize_conditions(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                let Select {
+                    from,
+                    selection,
+                    having,
+                    qualify,
+                    ..
+                } = select.as_mut();
+                for condition in [selection, having, qualify].into_iter().flatten() {
+                    self.normalize_predicate(condition);
+                }
+                for join in from.iter_mut().flat_map(|table| table.joins.iter_mut()) {
+                    if let Some(JoinConstraint::On(condition)) =
+                        join_constraint(&mut join.join_operator)
+                    {
+                        self.normalize_predicate(condition);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.normalize_conditions(left);
+                self.normalize_conditions(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The constraint of the joins DataFusion unparses.
+fn join_constraint(operator: &mut JoinOperator) -> Option<&mut JoinConstraint> {
+    match operator {
+        JoinOperator::Inner(constraint)
+        | JoinOperator::LeftOuter(constraint)
+        | JoinOperator::RightOuter(constraint)
+        | JoinOperator::FullOuter(constraint)
+        | JoinOperator::LeftSemi(constraint)
+        | JoinOperator::RightSemi(constraint)
+        | JoinOperator::LeftAnti(constraint)
+        | JoinOperator::RightAnti(constraint) => Some(constraint),
+        _ => None,
+    }
+}
+
+/// `CASE WHEN expr IS NULL THEN 0 ELSE 1 END` sorting the nulls first in the ascending order,
+/// or the reverse to sort them last.
+fn nulls_aside(expr: &Expr, nulls_first: bool) -> Expr {
+    let (null_key, other_key) = if nulls_first { ("0", "1") } else { ("1", "0") };
+    Expr::Case {
+        case_token: AttachedToken::empty(),
+        end_token: AttachedToken::empty(),
+        operand: None,
+        conditions: vec![CaseWhen {
+            condition: Expr::IsNull(Box::new(expr.clone())),
+            result: Expr::value(Value::Number(null_key.to_string(), false)),
+        }],
+        else_result: Some(Box::new(Expr::value(Value::Number(
+            other_key.to_string(),
+            false,
+        )))),
+    }
+}
+
+/// Move the `FILTER (WHERE ...)` of the aggregation into its aggregated value, e.g.
+/// `sum(x) FILTER (WHERE p)` to `sum(CASE WHEN p THEN x END)`. The rows not matching the
+/// predicate are aggregated as nulls, which the aggregate functions skip, and `count(*)`
+/// counts `CASE WHEN p THEN 1 END`. Only the first argument is the aggregated value, so
+/// the other arguments are kept.
+fn filter_into_case(function: &mut Function) {
+    let FunctionArguments::List(list) = &mut function.args else {
+        return;
+    };
+    let Some(FunctionArg::Unnamed(arg)) = list.args.first_mut() else {
+        return;
+    };
+    let value = match arg {
+        FunctionArgExpr::Expr(value) => value.clone(),
+        FunctionArgExpr::Wildcard => Expr::value(Value::Number("1".to_string(), false)),
+        _ => return,
+    };
+    let Some(filter) = function.filter.take() else {
+        return;
+    };
+    *arg = FunctionArgExpr::Expr(Expr::Case {
+        case_token: AttachedToken::empty(),
+        end_token: AttachedToken::empty(),
+        operand: None,
+        conditions: vec![CaseWhen {
+            condition: *filter,
+            result: value,
+        }],
+        else_result: None,
+    });
+}
+
+impl VisitorMut for Normalizer<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(order_by) = query.order_by.as_mut() {
+            if let OrderByKind::Expressions(exprs) = &mut order_by.kind {
+                self.normalize_order_by(exprs);
+            }
+        }
+        self.normalize_conditions(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    /// The operands of the logical operators and the conditions of a searched `CASE` are
+    /// predicates. The conditions of a `CASE` with an operand are values compared with it.
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::And | BinaryOperator::Or,
+                right,
+            } => {
+                self.normalize_predicate(left);
+                self.normalize_predicate(right);
+            }
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr,
+            } => self.normalize_predicate(expr),
+            Expr::Case {
+                operand: None,
+                conditions,
+                ..
+            } => {
+                for when in conditions.iter_mut() {
+                    self.normalize_predicate(&mut when.condition);
+                }
+            }
+            Expr::Function(Function {
+                filter: Some(filter),
+                ..
+            }) => self.normalize_predicate(filter),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Value(ValueWithSpan {
+                value: Value::Boolean(value),
+                ..
+            }) => {
+                *expr = self.dialect.boolean_literal(*value);
+            }
+            Expr::BinaryOp {
+                op: BinaryOperator::StringConcat,
+                ..
+            } => {
+                let Expr::BinaryOp { left, right, .. } =
+                    std::mem::replace(expr, Expr::value(Value::Null))
+                else {
+                    unreachable!("the expression is a binary operation");
+                };
+                *expr = self.dialect.string_concat(*left, *right);
+            }
+            Expr::Function(function) => {
+                if let Some(WindowType::WindowSpec(spec)) = function.over.as_mut() {
+                    self.normalize_order_by(&mut spec.order_by);
+                }
+                self.normalize_order_by(&mut function.within_group);
+                if let FunctionArguments::List(list) = &mut function.args {
+                    for clause in list.clauses.iter_mut() {
+                        if let FunctionArgumentClause::OrderBy(exprs) = clause {
+                            self.normalize_order_by(exprs);
+                        }
+                    }
+                }
+                if function.filter.is_some() && !self.dialect.supports_aggregate_filter()
+                {
+                    filter_into_case(function);
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::ops::ControlFlow;
+
+use datafusion::arrow::datatypes::DECIMAL128_MAX_PRECISION;
+use datafusion::scalar::ScalarValue;
+use datafusion::sql::sqlparser::ast::{
+    Expr, Fetch, LimitClause, Query, SetExpr, Statement, Top, Value, ValueWithSpan,
+    VisitMut, VisitorMut,
+};
+
+use crate::mdl::dialect::inner_dialect::InnerDialect;
+
+/// Replace the string, number and boolean literals of the unparsed statement by the
+/// placeholders of the dialect. The values are returned in the order of the placeholders.
+///
+/// The literals of an `INTERVAL` are kept because most dialects don't accept a parameter
+/// there, and so are the row counts of `LIMIT`, `OFFSET`, `FETCH` and `TOP`. `NULL` is kept
+/// as well, since it has no type to bind.
+pub(crate) fn parameterize_statement(
+    dialect: &dyn InnerDialect,
+    statement: &mut Statement,
+) -> Vec<ScalarValue> {
+    let mut parameterizer = Parameterizer {
+        dialect,
+        values: vec![],
+        interval_depth: 0,
+        row_counts: vec![],
+    };
+    let _ = statement.visit(&mut parameterizer);
+    parameterizer.values
+}
+
+struct Parameterizer<'a> {
+    dialect: &'a dyn InnerDialect,
+    values: Vec<ScalarValue>,
+    /// The number of the `INTERVAL`s being visited
+    interval_depth: usize,
+    /// The row count clauses of the queries being visited. They're taken out of the query
+    /// while it's visited, so their literals aren't parameterized.
+    row_counts: Vec<RowCounts>,
+}
+
+struct RowCounts {
+    limit_clause: Option<LimitClause>,
+    fetch: Option<Fetch>,
+    /// The `TOP` of the selects of the query body, in the order of [take_tops]
+    tops: Vec<Option<Top>>,
+}
+
+/// Take the `TOP` of the selects of the query body, including the ones of a set operation.
+fn take_tops(body: &mut SetExpr, tops: &mut Vec<Option<Top>>) {
+    match body {
+        SetExpr::Select(select) => tops.push(select.top.take()),
+        SetExpr::SetOperation { left, right, .. } => {
+            take_tops(left, tops);
+            take_tops(right, tops);
+        }
+        _ => {}
+    }
+}
+
+/// Put back the `TOP` taken by [take_tops] in the same order.
+fn restore_tops(body: &mut SetExpr, tops: &mut impl Iterator<Item = Option<Top>>) {
+    match body {
+        SetExpr::Select(select) => select.top = tops.next().flatten(),
+        SetExpr::SetOperation { left, right, .. } => {
+            restore_tops(left, tops);
+            restore_tops(right, tops);
+        }
+        _ => {}
+    }
+}
+
+/// The value to bind for the literal, or None if the literal is kept
+fn to_scalar_value(value: &Value) -> Option<ScalarValue> {
+    match value {
+        Value::Number(number, _) => Some(match number.parse::<i64>() {
+            Ok(number) => ScalarValue::Int64(Some(number)),
+            Err(_) => to_decimal(number),
+        }),
+        Value::SingleQuotedString(s) => Some(ScalarValue::Utf8(Some(s.clone()))),
+        Value::Boolean(b) => Some(ScalarValue::Boolean(Some(*b))),
+        _ => None,
+    }
+}
+
+/// The decimal of a number literal with its scale, e.g. `12.50` is `Decimal128(4, 2)`, so
+/// the bound value is exact like the literal. A number with an exponent or more digits
+/// than a Decimal128 holds is bound as its text.
+fn to_decimal(number: &str) -> ScalarValue {
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let precision = integer.trim_start_matches('0').len() + fraction.len();
+    let digits = format!("{integer}{fraction}");
+    match digits.parse::<i128>() {
+        Ok(value)
+            if digits.bytes().all(|b| b.is_ascii_digit())
+                && precision <= DECIMAL128_MAX_PRECISION as usize =>
+        {
+            ScalarValue::Decimal128(
+                Some(value),
+                precision.max(1) as u8,
+                fraction.len() as i8,
+            )
+        }
+        _ => ScalarValue::Utf8(Some(number.to_string())),
+    }
+}
+
+impl VisitorMut for Parameterizer<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        let mut tops = vec![];
+        take_tops(&mut query.body, &mut tops);
+        self.row_counts.push(RowCounts {
+            limit_clause: query.limit_clause.take(),
+            fetch: query.fetch.take(),
+            tops,
+        });
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(row_counts) = self.row_counts.pop() {
+            query.limit_clause = row_counts.limit_clause;
+            query.fetch = row_counts.fetch;
+            restore_tops(&mut query.body, &mut row_counts.tops.into_iter());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        if matches!(expr, Expr::Interval(_)) {
+            self.interval_depth += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Interval(_) => self.interval_depth -= 1,
+            Expr::Value(ValueWithSpan { value, .. }) if self.interval_depth == 0 => {
+                if let Some(scalar) = to_scalar_value(value) {
+                    self.values.push(scalar);
+                    *value =
+                        Value::Placeholder(self.dialect.placeholder(self.values.len()));
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
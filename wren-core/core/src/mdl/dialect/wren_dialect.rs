@@ -17,10 +17,12 @@
  * under the License.
  */
 use crate::mdl::dialect::inner_dialect::{get_inner_dialect, InnerDialect};
+use crate::mdl::dialect::normalize::normalize_statement;
+use crate::mdl::dialect::parameterize::parameterize_statement;
 use crate::mdl::manifest::DataSource;
 use datafusion::common::Result;
-use datafusion::logical_expr::sqlparser::keywords::ALL_KEYWORDS;
 use datafusion::logical_expr::Expr;
+use datafusion::scalar::ScalarValue;
 use datafusion::sql::sqlparser::ast::{self, WindowFrameBound};
 use datafusion::sql::unparser::dialect::{Dialect, IntervalStyle};
 use datafusion::sql::unparser::Unparser;
@@ -40,7 +42,10 @@ impl Dialect for WrenDialect {
         }
 
         let identifier_regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
-        if ALL_KEYWORDS.contains(&identifier.to_uppercase().as_str())
+        if self
+            .inner_dialect
+            .reserved_words()
+            .contains(&identifier.to_uppercase().as_str())
             || !identifier_regex.is_match(identifier)
             || non_lowercase(identifier)
         {
@@ -51,7 +56,7 @@ impl Dialect for WrenDialect {
     }
 
     fn interval_style(&self) -> IntervalStyle {
-        IntervalStyle::MySQL
+        self.inner_dialect.interval_style()
     }
 
     fn scalar_function_to_sql_overrides(
@@ -133,6 +138,33 @@ impl WrenDialect {
             inner_dialect: get_inner_dialect(data_source),
         }
     }
+
+    /// Render the boolean literals and the null ordering of `ORDER BY` in the form of the
+    /// dialect. The unparser keeps the DataFusion forms, which some dialects don't accept or
+    /// read differently, e.g. MySQL sorts the nulls first by default and has no `NULLS LAST`.
+    pub fn normalize_statement(&self, statement: &mut ast::Statement) {
+        normalize_statement(self.inner_dialect.as_ref(), statement)
+    }
+
+    /// Whether the data source accepts `TABLESAMPLE` on a table.
+    pub fn supports_table_sample(&self) -> bool {
+        self.inner_dialect.supports_table_sample()
+    }
+
+    /// The object type of a view in `COMMENT ON`, or `None` if the data source has no
+    /// `COMMENT ON`.
+    pub fn comment_on_view_type(&self) -> Option<&'static str> {
+        self.inner_dialect.comment_on_view_type()
+    }
+
+    /// Replace the literals of the statement by the placeholders of the dialect, e.g. `$1`
+    /// or `?`, and return the values to bind in the order of the placeholders.
+    pub fn parameterize_statement(
+        &self,
+        statement: &mut ast::Statement,
+    ) -> Vec<ScalarValue> {
+        parameterize_statement(self.inner_dialect.as_ref(), statement)
+    }
 }
 
 fn non_lowercase(sql: &str) -> bool {
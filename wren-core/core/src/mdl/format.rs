@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use datafusion::common::plan_datafusion_err;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::sql::sqlparser::ast::{ObjectName, Statement, VisitMut, VisitorMut};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::ParserError;
+use datafusion::sql::sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+/// The keywords starting a new line in the pretty output.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "GROUP",
+    "HAVING",
+    "QUALIFY",
+    "WINDOW",
+    "ORDER",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "JOIN",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+];
+
+/// The keywords which don't start a new line if they follow a clause keyword, e.g. the
+/// `JOIN` of `LEFT OUTER JOIN` or the `SELECT` of `UNION ALL SELECT`.
+const JOIN_MODIFIERS: &[&str] = &["INNER", "LEFT", "RIGHT", "FULL", "CROSS", "OUTER"];
+
+/// The keywords uppercased in the pretty output.
+const UPPERCASE_KEYWORDS: &[&str] = &[
+    "ALL", "AND", "AS", "ASC", "BETWEEN", "BY", "CASE", "DESC", "DISTINCT", "ELSE",
+    "END", "EXISTS", "IN", "IS", "LIKE", "NOT", "NULL", "ON", "OR", "OUTER", "THEN",
+    "USING", "WHEN", "WITH",
+];
+
+/// The options for unparsing and formatting the SQL produced by the transformation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Put each clause on a new line and uppercase the keywords. The output is compact if false.
+    pub pretty: bool,
+    /// The number of spaces to indent a clause per nested level of parentheses.
+    pub indent: usize,
+    /// Qualify every column of the plan by its table, e.g. `t.a` instead of `a`.
+    pub qualify_columns: bool,
+    /// How many parts of the table references are emitted.
+    pub name_qualification: NameQualification,
+}
+
+/// How the table references of the output are qualified, e.g. `catalog.schema.table`,
+/// `schema.table` or `table`. A reference with fewer parts is kept as it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameQualification {
+    /// Keep all the parts of the references
+    #[default]
+    Full,
+    /// Strip the catalog, e.g. `schema.table`
+    SchemaQualified,
+    /// Strip the catalog and the schema, e.g. `table`
+    Bare,
+}
+
+impl NameQualification {
+    /// The max number of the parts of a table reference, or None if it's unlimited
+    fn max_parts(&self) -> Option<usize> {
+        match self {
+            NameQualification::Full => None,
+            NameQualification::SchemaQualified => Some(2),
+            NameQualification::Bare => Some(1),
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            indent: 2,
+            qualify_columns: false,
+            name_qualification: NameQualification::default(),
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn pretty() -> Self {
+        Self {
+            pretty: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_qualify_columns(mut self, qualify_columns: bool) -> Self {
+        self.qualify_columns = qualify_columns;
+        self
+    }
+
+    pub fn with_name_qualification(
+        mut self,
+        name_qualification: NameQualification,
+    ) -> Self {
+        self.name_qualification = name_qualification;
+        self
+    }
+}
+
+/// Strip the leading parts of the table references of the statement by the qualification.
+///
+/// It fails if two different tables would be referenced by the same name, e.g.
+/// `s1.customer` and `s2.customer` with [NameQualification::Bare].
+pub(crate) fn qualify_table_names(
+    statement: &mut Statement,
+    name_qualification: NameQualification,
+) -> Result<()> {
+    let Some(max_parts) = name_qualification.max_parts() else {
+        return Ok(());
+    };
+    let mut qualifier = TableNameQualifier {
+        max_parts,
+        stripped: HashMap::new(),
+    };
+    match statement.visit(&mut qualifier) {
+        ControlFlow::Break(e) => Err(e),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+struct TableNameQualifier {
+    max_parts: usize,
+    /// The original references of the stripped names
+    stripped: HashMap<ObjectName, ObjectName>,
+}
+
+impl VisitorMut for TableNameQualifier {
+    type Break = DataFusionError;
+
+    fn pre_visit_relation(
+        &mut self,
+        relation: &mut ObjectName,
+    ) -> ControlFlow<Self::Break> {
+        let parts = relation.0.len();
+        if parts <= self.max_parts {
+            return ControlFlow::Continue(());
+        }
+        let name = ObjectName(relation.0[parts - self.max_parts..].to_vec());
+        match self.stripped.get(&name) {
+            Some(original) if original != relation => {
+                return ControlFlow::Break(plan_datafusion_err!(
+                    "The tables {original} and {relation} are ambiguous as {name}"
+                ));
+            }
+            Some(_) => {}
+            None => {
+                self.stripped.insert(name.clone(), relation.clone());
+            }
+        }
+        *relation = name;
+        ControlFlow::Continue(())
+    }
+}
+
+/// Format the SQL by the options. The SQL is returned as it is if `pretty` isn't enabled.
+///
+/// The output only depends on the tokens of the SQL, so formatting the same SQL, or an
+/// already formatted SQL, always gives the same result.
+///
+/// Only the clauses of the queries start a new line. The keywords in the parentheses of
+/// the other expressions, e.g. `OVER (PARTITION BY a ORDER BY b)` or `EXTRACT(YEAR FROM d)`,
+/// are kept inline, and so is the `FROM` of `IS DISTINCT FROM`.
+pub fn format_sql(sql: &str, options: &FormatOptions) -> Result<String> {
+    if !options.pretty {
+        return Ok(sql.to_string());
+    }
+    let tokens = Tokenizer::new(&GenericDialect {}, sql)
+        .tokenize()
+        .map_err(ParserError::from)?;
+
+    let mut output = String::with_capacity(sql.len());
+    // whether each open parenthesis starts a subquery
+    let mut parens: Vec<bool> = vec![];
+    let mut pending_space = false;
+    let mut previous_keyword: Option<String> = None;
+    let mut iter = tokens.iter();
+    while let Some(token) = iter.next() {
+        match token {
+            Token::Whitespace(
+                Whitespace::Space | Whitespace::Newline | Whitespace::Tab,
+            ) => {
+                pending_space = true;
+                continue;
+            }
+            Token::Whitespace(_) => {
+                output.push_str(&token.to_string());
+                continue;
+            }
+            Token::Word(word) if word.quote_style.is_none() => {
+                let upper = word.value.to_uppercase();
+                let is_function = matches!(
+                    iter.clone().find(|t| !matches!(t, Token::Whitespace(_))),
+                    Some(Token::LParen)
+                );
+                let follows_clause =
+                    previous_keyword.as_deref().is_some_and(|previous| {
+                        JOIN_MODIFIERS.contains(&previous)
+                            || (matches!(
+                                previous,
+                                "UNION" | "INTERSECT" | "EXCEPT" | "ALL"
+                            ) && upper != "UNION")
+                    });
+                let is_clause = CLAUSE_KEYWORDS.contains(&upper.as_str()) && !is_function;
+                let in_query = parens.last().copied().unwrap_or(true);
+                let is_distinct_from =
+                    upper == "FROM" && previous_keyword.as_deref() == Some("DISTINCT");
+                if is_clause && in_query && !is_distinct_from {
+                    if !follows_clause && !output.is_empty() {
+                        let depth = parens.iter().filter(|is_query| **is_query).count();
+                        new_line(&mut output, depth, options.indent);
+                    } else if pending_space {
+                        output.push(' ');
+                    }
+                    output.push_str(&upper);
+                } else if is_clause
+                    || (UPPERCASE_KEYWORDS.contains(&upper.as_str()) && !is_function)
+                {
+                    push_space(&mut output, pending_space);
+                    output.push_str(&upper);
+                } else {
+                    push_space(&mut output, pending_space);
+                    output.push_str(&word.value);
+                }
+                previous_keyword = Some(upper);
+            }
+            _ => {
+                push_space(&mut output, pending_space);
+                match token {
+                    Token::LParen => parens.push(starts_query(iter.clone())),
+                    Token::RParen => {
+                        parens.pop();
+                    }
+                    _ => {}
+                }
+                output.push_str(&token.to_string());
+                previous_keyword = None;
+            }
+        }
+        pending_space = false;
+    }
+    Ok(output)
+}
+
+/// Check if the tokens after a parenthesis start a query, e.g. `(SELECT` or `(WITH`
+fn starts_query<'a>(mut tokens: impl Iterator<Item = &'a Token>) -> bool {
+    match tokens.find(|t| !matches!(t, Token::Whitespace(_))) {
+        Some(Token::Word(word)) if word.quote_style.is_none() => {
+            matches!(word.value.to_uppercase().as_str(), "SELECT" | "WITH")
+        }
+        _ => false,
+    }
+}
+
+fn new_line(output: &mut String, depth: usize, indent: usize) {
+    output.push('\n');
+    output.push_str(&" ".repeat(depth * indent));
+}
+
+fn push_space(output: &mut String, pending_space: bool) {
+    if pending_space && !output.is_empty() && !output.ends_with('\n') {
+        output.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mdl::format::{format_sql, FormatOptions};
+    use datafusion::error::Result;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_format_sql() -> Result<()> {
+        let sql =
+            "select a, count(*) as cnt from (select a from t where b = 'x  y') as s \
+            left outer join u on s.a = u.a where a > 1 group by a order by a limit 10";
+        assert_eq!(format_sql(sql, &FormatOptions::default())?, sql);
+
+        let pretty = format_sql(sql, &FormatOptions::pretty())?;
+        assert_eq!(
+            pretty,
+            "SELECT a, count(*) AS cnt\n\
+            FROM (\n  SELECT a\n  FROM t\n  WHERE b = 'x  y') AS s\n\
+            LEFT OUTER JOIN u ON s.a = u.a\n\
+            WHERE a > 1\n\
+            GROUP BY a\n\
+            ORDER BY a\n\
+            LIMIT 10"
+        );
+        // formatting the pretty output again gives the same result
+        assert_eq!(format_sql(&pretty, &FormatOptions::pretty())?, pretty);
+
+        let indented = format_sql(sql, &FormatOptions::pretty().with_indent(4))?;
+        assert!(indented.contains("\n    FROM t"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_keywords_in_expressions() -> Result<()> {
+        let format = |sql| format_sql(sql, &FormatOptions::pretty());
+        assert_snapshot!(
+            format("select a, row_number() over (partition by a order by b) as rn from t")?,
+            @r"
+        SELECT a, row_number() over (partition BY a ORDER BY b) AS rn
+        FROM t
+        "
+        );
+        assert_snapshot!(
+            format(
+                "select extract(year from d), substring(s from 2) from t \
+                where a is distinct from b"
+            )?,
+            @r"
+        SELECT extract(year FROM d), substring(s FROM 2)
+        FROM t
+        WHERE a IS DISTINCT FROM b
+        "
+        );
+        // a subquery in an expression is still broken into the clauses
+        assert_snapshot!(
+            format("select coalesce((select max(a) from t), 0) from u")?,
+            @r"
+        SELECT coalesce((
+          SELECT max(a)
+          FROM t), 0)
+        FROM u
+        "
+        );
+        Ok(())
+    }
+}
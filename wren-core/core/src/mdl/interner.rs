@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+/// The default max number of the distinct strings kept by an [Interner].
+pub const DEFAULT_INTERNER_CAPACITY: usize = 4096;
+
+/// A deduping pool for the repeated identifiers, e.g. the model, column and view names.
+/// Interning the same name returns the same [Arc], so it's allocated only once.
+///
+/// The pool is bounded. When it's full, the new string is still returned as an [Arc] but
+/// isn't kept in the pool. Create an interner per manifest so it doesn't grow across manifests.
+#[derive(Debug)]
+pub struct Interner {
+    capacity: usize,
+    pool: RwLock<HashSet<Arc<str>>>,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERNER_CAPACITY)
+    }
+}
+
+impl Interner {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            pool: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.pool.read().get(s) {
+            return Arc::clone(interned);
+        }
+        let mut pool = self.pool.write();
+        // check again because another thread may have interned it
+        if let Some(interned) = pool.get(s) {
+            return Arc::clone(interned);
+        }
+        let interned: Arc<str> = Arc::from(s);
+        if pool.len() < self.capacity {
+            pool.insert(Arc::clone(&interned));
+        }
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.pool.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.pool.write().clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::interner::Interner;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_intern() {
+        let interner = Interner::new(2);
+        let a = interner.intern("customer");
+        let b = interner.intern(&"customer".to_string());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+
+        interner.intern("orders");
+        // the pool is full, the new string isn't kept
+        let c = interner.intern("lineitem");
+        let d = interner.intern("lineitem");
+        assert_eq!(c, d);
+        assert!(!Arc::ptr_eq(&c, &d));
+        assert_eq!(interner.len(), 2);
+    }
+}
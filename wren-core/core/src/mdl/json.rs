@@ -0,0 +1,176 @@
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
+use datafusion::arrow::datatypes::{
+    DataType, Fields, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::error::Result;
+use serde_json::{Number, Value};
+
+/// Convert the record batches to a JSON array of the row objects, e.g. `[{"a":1,"b":"x"}]`.
+///
+/// The keys of a row follow the order of the schema, so the JSON is written as text:
+/// [serde_json::Value] sorts the keys of its objects. The numbers, strings, booleans, nulls,
+/// structs and lists are mapped to the JSON types. The other types, e.g. the temporal and
+/// decimal types, are formatted as strings like the pretty printed batches.
+pub fn batches_to_json(batches: &[RecordBatch]) -> Result<String> {
+    let mut json = String::from("[");
+    for (index, (batch, row)) in batches
+        .iter()
+        .flat_map(|batch| (0..batch.num_rows()).map(move |row| (batch, row)))
+        .enumerate()
+    {
+        if index > 0 {
+            json.push(',');
+        }
+        write_object(&mut json, batch.schema().fields(), batch.columns(), row)?;
+    }
+    json.push(']');
+    Ok(json)
+}
+
+/// Write the object of the row with the keys in the order of the fields
+fn write_object(
+    json: &mut String,
+    fields: &Fields,
+    columns: &[ArrayRef],
+    row: usize,
+) -> Result<()> {
+    json.push('{');
+    for (index, (field, column)) in fields.iter().zip(columns).enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        json.push_str(&Value::from(field.name().as_str()).to_string());
+        json.push(':');
+        write_value(json, column.as_ref(), row)?;
+    }
+    json.push('}');
+    Ok(())
+}
+
+fn write_value(json: &mut String, array: &dyn Array, row: usize) -> Result<()> {
+    if array.is_null(row) {
+        json.push_str("null");
+        return Ok(());
+    }
+    match array.data_type() {
+        DataType::Struct(fields) => {
+            write_object(json, fields, array.as_struct().columns(), row)
+        }
+        DataType::List(_) => write_list(json, array.as_list::<i32>().value(row).as_ref()),
+        DataType::LargeList(_) => {
+            write_list(json, array.as_list::<i64>().value(row).as_ref())
+        }
+        _ => {
+            json.push_str(&scalar_value(array, row)?.to_string());
+            Ok(())
+        }
+    }
+}
+
+fn write_list(json: &mut String, values: &dyn Array) -> Result<()> {
+    json.push('[');
+    for index in 0..values.len() {
+        if index > 0 {
+            json.push(',');
+        }
+        write_value(json, values, index)?;
+    }
+    json.push(']');
+    Ok(())
+}
+
+fn scalar_value(array: &dyn Array, row: usize) -> Result<Value> {
+    let value = match array.data_type() {
+        DataType::Boolean => Value::from(array.as_boolean().value(row)),
+        DataType::Int8 => Value::from(array.as_primitive::<Int8Type>().value(row)),
+        DataType::Int16 => Value::from(array.as_primitive::<Int16Type>().value(row)),
+        DataType::Int32 => Value::from(array.as_primitive::<Int32Type>().value(row)),
+        DataType::Int64 => Value::from(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt8 => Value::from(array.as_primitive::<UInt8Type>().value(row)),
+        DataType::UInt16 => Value::from(array.as_primitive::<UInt16Type>().value(row)),
+        DataType::UInt32 => Value::from(array.as_primitive::<UInt32Type>().value(row)),
+        DataType::UInt64 => Value::from(array.as_primitive::<UInt64Type>().value(row)),
+        DataType::Float32 => {
+            float_value(array.as_primitive::<Float32Type>().value(row).into())
+        }
+        DataType::Float64 => float_value(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => Value::from(array.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => Value::from(array.as_string::<i64>().value(row)),
+        DataType::Utf8View => Value::from(array.as_string_view().value(row)),
+        _ => {
+            let formatter = ArrayFormatter::try_new(array, &FormatOptions::default())?;
+            Value::from(formatter.value(row).to_string())
+        }
+    };
+    Ok(value)
+}
+
+/// JSON has no NaN or infinity, so they become null.
+fn float_value(value: f64) -> Value {
+    Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use datafusion::error::Result;
+    use insta::assert_snapshot;
+    use serde_json::json;
+
+    use crate::mdl::json::batches_to_json;
+    use crate::mdl::{create_wren_ctx, execute_stream_with_limit, AnalyzedWrenMDL};
+    use datafusion::physical_plan::common::collect;
+
+    #[tokio::test]
+    async fn test_batches_to_json() -> Result<()> {
+        let stream = execute_stream_with_limit(
+            &create_wren_ctx(None),
+            Arc::new(AnalyzedWrenMDL::default()),
+            Arc::new(HashMap::new()),
+            "select value as id, \
+                case when value = 2 then null else 'name' || value end as name, \
+                value % 2 = 0 as even, \
+                value / 2.0 as half, \
+                named_struct('x', value, 'y', 'a') as point, \
+                make_array(value, value + 1) as ids, \
+                date '2024-01-01' as created \
+            from generate_series(1, 2) order by 1",
+            1,
+        )
+        .await?;
+        let batches = collect(stream).await?;
+        let actual = batches_to_json(&batches)?;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&actual).unwrap(),
+            json!([
+                {
+                    "id": 1,
+                    "name": "name1",
+                    "even": false,
+                    "half": 0.5,
+                    "point": {"x": 1, "y": "a"},
+                    "ids": [1, 2],
+                    "created": "2024-01-01"
+                },
+                {
+                    "id": 2,
+                    "name": null,
+                    "even": true,
+                    "half": 1.0,
+                    "point": {"x": 2, "y": "a"},
+                    "ids": [2, 3],
+                    "created": "2024-01-01"
+                }
+            ])
+        );
+        // the keys follow the order of the schema
+        assert_snapshot!(actual, @r#"[{"id":1,"name":"name1","even":false,"half":0.5,"point":{"x":1,"y":"a"},"ids":[1,2],"created":"2024-01-01"},{"id":2,"name":null,"even":true,"half":1.0,"point":{"x":2,"y":"a"},"ids":[2,3],"created":"2024-01-01"}]"#);
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::ast::{
+    FunctionArg, FunctionArgExpr, ObjectNamePart, Statement, TableFactor, VisitMut,
+    VisitorMut,
+};
+
+/// Rewrite `LATERAL unnest(...)` to `unnest(...)`.
+///
+/// `unnest` in `FROM` can reference the columns of the preceding tables, e.g.
+/// `FROM orders o CROSS JOIN unnest(o.o_items)`, so it's lateral already. With the `LATERAL`
+/// keyword, the parser reads it as a table function named `unnest`, which isn't registered.
+pub(crate) fn rewrite_lateral_unnest(statement: &mut Statement) {
+    let _ = statement.visit(&mut LateralUnnestRewriter);
+}
+
+struct LateralUnnestRewriter;
+
+impl VisitorMut for LateralUnnestRewriter {
+    type Break = ();
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        let TableFactor::Function {
+            lateral: true,
+            name,
+            args,
+            alias,
+        } = table_factor
+        else {
+            return ControlFlow::Continue(());
+        };
+        let is_unnest = matches!(
+            name.0.as_slice(),
+            [ObjectNamePart::Identifier(ident)] if ident.value.eq_ignore_ascii_case("unnest")
+        );
+        let array_exprs: Option<Vec<_>> = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr.clone()),
+                _ => None,
+            })
+            .collect();
+        if let (true, Some(array_exprs)) = (is_unnest, array_exprs) {
+            *table_factor = TableFactor::UNNEST {
+                alias: alias.take(),
+                array_exprs,
+                with_offset: false,
+                with_offset_alias: None,
+                with_ordinality: false,
+            };
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use datafusion::error::{DataFusionError, Result};
+use tokio::sync::Semaphore;
+
+/// Limit the number of the analyses running at the same time.
+///
+/// The analysis is CPU-heavy, so the excess requests wait for a permit instead of
+/// competing for the cores. If `max_queued` is set, a request arriving when that many
+/// requests are already waiting fails with [DataFusionError::ResourcesExhausted].
+///
+/// It's configured per analyzed MDL by [crate::mdl::AnalyzeOptions::with_max_concurrent_analyses].
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    max_concurrent: usize,
+    max_queued: Option<usize>,
+    semaphore: Semaphore,
+    queued: AtomicUsize,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(max_concurrent: usize, max_queued: Option<usize>) -> Self {
+        Self {
+            max_concurrent,
+            max_queued,
+            semaphore: Semaphore::new(max_concurrent),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// The number of the requests waiting for a permit.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Acquire)
+    }
+
+    /// Run the future once a permit is acquired.
+    pub async fn run<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        let permit = match self.semaphore.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let queued = self.queued.fetch_add(1, Ordering::AcqRel);
+                // released when the permit is acquired or the waiting request is dropped
+                let _queued = QueuedGuard(&self.queued);
+                if self
+                    .max_queued
+                    .is_some_and(|max_queued| queued >= max_queued)
+                {
+                    return Err(DataFusionError::ResourcesExhausted(format!(
+                        "Too many concurrent analyses. {} are running and {} are queued",
+                        self.max_concurrent, queued
+                    )));
+                }
+                self.semaphore
+                    .acquire()
+                    .await
+                    .map_err(|e| DataFusionError::External(Box::new(e)))?
+            }
+        };
+        let result = future.await;
+        drop(permit);
+        result
+    }
+}
+
+/// Decrease the number of the queued requests when it's dropped.
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use datafusion::error::{DataFusionError, Result};
+    use tokio::task::JoinSet;
+
+    use crate::mdl::limiter::ConcurrencyLimiter;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_max_concurrent() -> Result<()> {
+        let limiter = Arc::new(ConcurrencyLimiter::new(2, None));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let mut tasks = JoinSet::new();
+        for _ in 0..32 {
+            let limiter = Arc::clone(&limiter);
+            let running = Arc::clone(&running);
+            let max_running = Arc::clone(&max_running);
+            tasks.spawn(async move {
+                limiter
+                    .run(async {
+                        let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(current, Ordering::SeqCst);
+                        for _ in 0..100 {
+                            tokio::task::yield_now().await;
+                        }
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                    .await
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap()?;
+        }
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        assert_eq!(limiter.queued(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_queued() -> Result<()> {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1, Some(0)));
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let running = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move {
+                limiter
+                    .run(async move {
+                        started_tx.send(()).unwrap();
+                        release_rx.await.unwrap();
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        started_rx.await.unwrap();
+
+        match limiter.run(async { Ok(()) }).await {
+            Err(DataFusionError::ResourcesExhausted(_)) => {}
+            other => panic!("expected resources exhausted, but got {other:?}"),
+        }
+
+        release_tx.send(()).unwrap();
+        running.await.unwrap()?;
+        // the permit is released after the running analysis completes
+        limiter.run(async { Ok(()) }).await
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued() -> Result<()> {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1, Some(1)));
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let running = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move {
+                limiter
+                    .run(async move {
+                        started_tx.send(()).unwrap();
+                        release_rx.await.unwrap();
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        started_rx.await.unwrap();
+
+        // the queued request is dropped while it waits for a permit
+        let queued = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move { limiter.run(async { Ok(()) }).await })
+        };
+        while limiter.queued() == 0 {
+            tokio::task::yield_now().await;
+        }
+        queued.abort();
+        assert!(queued.await.unwrap_err().is_cancelled());
+        assert_eq!(limiter.queued(), 0);
+
+        release_tx.send(()).unwrap();
+        running.await.unwrap()?;
+        limiter.run(async { Ok(()) }).await
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use datafusion::common::Result;
+use datafusion::error::DataFusionError;
+use datafusion::execution::context::SessionState;
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions, visit_expressions_mut, Expr, FunctionArg, FunctionArgExpr,
+    FunctionArguments, GroupByExpr, Ident, Query, SetExpr, Statement, TableAlias,
+    TableFactor, Visit, VisitMut, VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+
+use crate::mdl::context::{measure_expression, metric_statement};
+use crate::mdl::manifest::{Column, Metric};
+use crate::mdl::utils::mdl_object_name;
+use crate::mdl::WrenMDL;
+
+/// Push the `HAVING` of a query on a metric down into the aggregation of the metric.
+///
+/// A row of a metric is already an aggregated group, so `SELECT o_custkey, total FROM revenue
+/// HAVING total > 100` filters the groups by the measure. The query is rewritten to the
+/// aggregation of the metric with the measures substituted by their expressions, e.g.
+/// `SELECT o_custkey, total FROM (SELECT o_custkey, sum(o_totalprice) AS total FROM orders
+/// GROUP BY 1 HAVING sum(o_totalprice) > 100) AS revenue`.
+///
+/// A query grouping by every dimension of the metric has a single row of the metric in each
+/// group, so `sum`, `min`, `max` and `avg` of a measure are the measure itself, e.g.
+/// `SELECT o_custkey, sum(total) FROM revenue GROUP BY o_custkey HAVING sum(total) > 100`
+/// gets the same `HAVING` pushed down.
+///
+/// Only the query selecting from a single metric is rewritten. The other `HAVING`s, e.g.
+/// `count(*) > 1` or grouping by some of the dimensions, aggregate the rows of the metric
+/// again, so they're kept.
+pub(crate) fn push_down_metric_having(
+    state: &SessionState,
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+) -> Result<()> {
+    let mut rewriter = MetricHavingRewriter { state, wren_mdl };
+    match statement.visit(&mut rewriter) {
+        ControlFlow::Break(e) => Err(e),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+/// The aggregate functions returning the value of a single row.
+const SINGLE_ROW_AGGREGATES: &[&str] = &["sum", "min", "max", "avg"];
+
+struct MetricHavingRewriter<'a> {
+    state: &'a SessionState,
+    wren_mdl: &'a WrenMDL,
+}
+
+impl MetricHavingRewriter<'_> {
+    fn contains_aggregate<V: Visit>(&self, node: &V) -> bool {
+        visit_expressions(node, |expr| match expr {
+            Expr::Function(function)
+                if self
+                    .state
+                    .aggregate_functions()
+                    .contains_key(&function.name.to_string().to_lowercase()) =>
+            {
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        })
+        .is_break()
+    }
+
+    fn rewrite(&self, query: &mut Query) -> Result<()> {
+        let SetExpr::Select(select) = query.body.as_mut() else {
+            return Ok(());
+        };
+        if select.having.is_none()
+            || select.from.len() != 1
+            || !select.from[0].joins.is_empty()
+        {
+            return Ok(());
+        }
+        let TableFactor::Table {
+            name,
+            alias,
+            args: None,
+            ..
+        } = &mut select.from[0].relation
+        else {
+            return Ok(());
+        };
+        let Some(metric) = mdl_object_name(self.wren_mdl, name)
+            .and_then(|name| self.wren_mdl.get_metric(name))
+        else {
+            return Ok(());
+        };
+        let qualifier = alias
+            .as_ref()
+            .map(|alias| alias.name.value.clone())
+            .unwrap_or_else(|| metric.name.clone());
+        let GroupByExpr::Expressions(group_by, _) = &select.group_by else {
+            return Ok(());
+        };
+        let mut having = select.having.clone().unwrap();
+        if group_by.is_empty() {
+            if self.contains_aggregate(&select.projection)
+                || self.contains_aggregate(&having)
+            {
+                return Ok(());
+            }
+        } else {
+            if !groups_every_dimension(&metric, &qualifier, group_by) {
+                return Ok(());
+            }
+            unwrap_single_row_aggregates(&metric, &qualifier, &mut having);
+            if self.contains_aggregate(&having) {
+                return Ok(());
+            }
+        }
+        substitute_columns(&metric, &qualifier, &mut having)?;
+
+        let mut metric_query = Parser::new(&GenericDialect {})
+            .try_with_sql(&metric_statement(self.wren_mdl, &metric)?)?
+            .parse_query()?;
+        if let SetExpr::Select(metric_select) = metric_query.body.as_mut() {
+            metric_select.having = Some(having);
+        }
+        let alias = alias.take().unwrap_or_else(|| TableAlias {
+            name: Ident::with_quote('"', &metric.name),
+            columns: vec![],
+        });
+        select.from[0].relation = TableFactor::Derived {
+            lateral: false,
+            subquery: metric_query,
+            alias: Some(alias),
+        };
+        select.having = None;
+        Ok(())
+    }
+}
+
+impl VisitorMut for MetricHavingRewriter<'_> {
+    type Break = DataFusionError;
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.rewrite(query) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => ControlFlow::Break(e),
+        }
+    }
+}
+
+/// The dimension or measure of the metric referenced by the expression, e.g. `total` or
+/// `revenue.total`.
+fn metric_column<'m>(
+    metric: &'m Metric,
+    qualifier: &str,
+    expr: &Expr,
+) -> Option<&'m Column> {
+    let ident = match expr {
+        Expr::Identifier(ident) => ident,
+        Expr::CompoundIdentifier(idents)
+            if idents.len() == 2 && idents[0].value == qualifier =>
+        {
+            &idents[1]
+        }
+        _ => return None,
+    };
+    metric
+        .dimension
+        .iter()
+        .chain(metric.measure.iter())
+        .find(|column| match ident.quote_style {
+            Some(_) => column.name == ident.value,
+            None => column.name.eq_ignore_ascii_case(&ident.value),
+        })
+        .map(|column| column.as_ref())
+}
+
+/// Whether the `GROUP BY` references every dimension of the metric and nothing else.
+fn groups_every_dimension(metric: &Metric, qualifier: &str, group_by: &[Expr]) -> bool {
+    let mut dimensions = HashSet::new();
+    for expr in group_by {
+        match metric_column(metric, qualifier, expr) {
+            Some(column) if metric.dimension.iter().any(|d| d.name == column.name) => {
+                dimensions.insert(column.name.as_str());
+            }
+            _ => return false,
+        }
+    }
+    dimensions.len() == metric.dimension.len()
+}
+
+/// Replace the aggregations of a single row, e.g. `sum(total)`, by their arguments. The other
+/// aggregations are kept.
+fn unwrap_single_row_aggregates(metric: &Metric, qualifier: &str, expr: &mut Expr) {
+    let _ = visit_expressions_mut(expr, |expr| {
+        let Expr::Function(function) = expr else {
+            return ControlFlow::<()>::Continue(());
+        };
+        if !SINGLE_ROW_AGGREGATES
+            .contains(&function.name.to_string().to_lowercase().as_str())
+            || function.filter.is_some()
+            || function.over.is_some()
+        {
+            return ControlFlow::Continue(());
+        }
+        let FunctionArguments::List(list) = &function.args else {
+            return ControlFlow::Continue(());
+        };
+        let [FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))] = list.args.as_slice()
+        else {
+            return ControlFlow::Continue(());
+        };
+        if list.clauses.is_empty() && metric_column(metric, qualifier, arg).is_some() {
+            *expr = arg.clone();
+        }
+        ControlFlow::Continue(())
+    });
+}
+
+/// Replace the references to the dimensions and measures of the metric by their expressions,
+/// so the predicate can be evaluated by the aggregation of the metric.
+fn substitute_columns(metric: &Metric, qualifier: &str, expr: &mut Expr) -> Result<()> {
+    let mut error = None;
+    let _ = visit_expressions_mut(expr, |expr| {
+        if let Some(column) = metric_column(metric, qualifier, expr) {
+            match column_expr(column) {
+                Ok(column_expr) => *expr = column_expr,
+                Err(e) => {
+                    error = Some(e);
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn column_expr(column: &Column) -> Result<Expr> {
+    match measure_expression(column) {
+        Some(expression) => {
+            let expr = Parser::new(&GenericDialect {})
+                .try_with_sql(&expression)?
+                .parse_expr()?;
+            Ok(Expr::Nested(Box::new(expr)))
+        }
+        None => Ok(Expr::Identifier(Ident::with_quote('"', &column.name))),
+    }
+}
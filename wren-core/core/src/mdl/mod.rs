@@ -1,57 +1,118 @@
-use crate::logical_plan::analyze::access_control::validate_clac_rule;
+use crate::logical_plan::analyze::access_control::{
+    validate_clac_rule, validate_declared_session_keys,
+    validate_rlac_rule_with_declared_keys,
+};
 use crate::logical_plan::error::WrenError;
-use crate::logical_plan::utils::{from_qualified_name_str, try_map_data_type};
-use crate::mdl::builder::ManifestBuilder;
-use crate::mdl::context::{apply_wren_on_ctx, Mode, WrenDataSource};
+use crate::logical_plan::utils::{qualify_columns, try_map_data_type};
+use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+use crate::mdl::cache::{parse_cache, plan_cache, ParseCache, PlanCache, PlanCacheKey};
+use crate::mdl::clock::{Clock, SystemClock};
+use crate::mdl::context::{
+    apply_wren_on_ctx, with_default_properties, Mode, WrenDataSource,
+};
+use crate::mdl::format::{
+    format_sql, qualify_table_names, FormatOptions, NameQualification,
+};
 use crate::mdl::function::{
     ByPassAggregateUDF, ByPassScalarUDF, ByPassWindowFunction, FunctionType,
     RemoteFunction,
 };
+use crate::mdl::interner::Interner;
+use crate::mdl::lateral::rewrite_lateral_unnest;
+use crate::mdl::limiter::ConcurrencyLimiter;
 use crate::mdl::manifest::{Column, Manifest, Metric, Model, View};
-use crate::mdl::utils::to_field;
+use crate::mdl::metric_having::push_down_metric_having;
+use crate::mdl::qualify::rewrite_qualify;
+use crate::mdl::sql_length::SqlLengthLimit;
+use crate::mdl::table_sample::TableSamples;
+use crate::mdl::utils::{create_default_value_expr, mdl_object_name, to_field};
+use crate::mdl::view_parameter::expand_parameterized_views;
 use crate::DataFusionError;
 use context::SessionPropertiesRef;
-use datafusion::arrow::datatypes::Field;
-use datafusion::common::internal_datafusion_err;
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::arrow::temporal_conversions::timestamp_ns_to_datetime;
+use datafusion::common::{
+    internal_datafusion_err, internal_err, plan_datafusion_err, plan_err,
+};
 use datafusion::datasource::TableProvider;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::execution::{SessionStateBuilder, SessionStateDefaults};
-use datafusion::logical_expr::{AggregateUDF, ScalarUDF, WindowUDF};
-use datafusion::prelude::{SessionConfig, SessionContext};
-use datafusion::sql::parser::DFParser;
-use datafusion::sql::sqlparser::ast::{Expr, ExprWithAlias, Ident};
+use datafusion::logical_expr::{
+    AggregateUDF, LogicalPlan, LogicalPlanBuilder, ScalarUDF, WindowUDF,
+};
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::prelude::{DataFrame, SessionConfig, SessionContext};
+use datafusion::scalar::ScalarValue;
+use datafusion::sql::parser::{DFParser, Statement as DFStatement};
+use datafusion::sql::sqlparser::ast::{
+    self, visit_relations, Expr, ExprWithAlias, Ident, Visit, Visitor,
+};
 use datafusion::sql::sqlparser::dialect::dialect_from_str;
 use datafusion::sql::unparser::Unparser;
 use datafusion::sql::TableReference;
 pub use dataset::Dataset;
 use dialect::WrenDialect;
-use log::{debug, info};
+use log::{debug, info, warn};
 use manifest::Relationship;
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::hash::Hash;
+use std::ops::ControlFlow;
+use std::time::UNIX_EPOCH;
 use std::{collections::HashMap, sync::Arc};
 use wren_core_base::mdl::DataSource;
 
 pub mod builder {
     pub use wren_core_base::mdl::builder::*;
 }
+pub mod cache;
+pub mod clock;
 pub mod context;
 pub(crate) mod dataset;
+mod ddl;
+pub mod describe;
 mod dialect;
+pub mod format;
 pub mod function;
+pub mod interner;
+pub mod json;
+mod lateral;
+pub mod limiter;
 pub mod lineage;
+mod metric_having;
+mod qualify;
+mod register;
+pub mod session;
+pub mod sql_length;
+mod table_sample;
 pub mod manifest {
     pub use wren_core_base::mdl::manifest::*;
 }
 pub mod type_planner;
 pub mod utils;
+mod view_parameter;
 
 pub type SessionStateRef = Arc<RwLock<SessionState>>;
 
 pub struct AnalyzedWrenMDL {
     pub wren_mdl: Arc<WrenMDL>,
-    pub lineage: Arc<lineage::Lineage>,
+    /// The lineage of the columns, or the reason it can't be computed
+    lineage: std::result::Result<Arc<lineage::Lineage>, String>,
+    /// The functions registered for every transformation of the MDL
+    remote_functions: Vec<RemoteFunction>,
+    /// The source of the current time of the queries
+    clock: Arc<dyn Clock>,
+    /// The max length of the SQL to transform
+    sql_length_limit: Option<SqlLengthLimit>,
+    /// The max number of the nodes of the expanded plan
+    max_plan_nodes: Option<usize>,
+    /// The limiter of the concurrent transformations
+    concurrency_limiter: Option<Arc<ConcurrencyLimiter>>,
+    /// The plan cache used instead of the global one
+    plan_cache: Option<Arc<PlanCache>>,
+    /// The parse cache used instead of the global one
+    parse_cache: Option<Arc<ParseCache>>,
 }
 
 impl Hash for AnalyzedWrenMDL {
@@ -64,25 +125,255 @@ impl Default for AnalyzedWrenMDL {
     fn default() -> Self {
         let manifest = ManifestBuilder::default().build();
         let wren_mdl = WrenMDL::new(manifest);
-        let lineage = lineage::Lineage::new(&wren_mdl).unwrap();
+        let lineage = Ok(Arc::new(lineage::Lineage::new(&wren_mdl).unwrap()));
         AnalyzedWrenMDL {
             wren_mdl: Arc::new(wren_mdl),
-            lineage: Arc::new(lineage),
+            lineage,
+            remote_functions: vec![],
+            clock: Arc::new(SystemClock),
+            sql_length_limit: None,
+            max_plan_nodes: None,
+            concurrency_limiter: None,
+            plan_cache: None,
+            parse_cache: None,
+        }
+    }
+}
+
+/// The options of [AnalyzedWrenMDL::analyze_with_options].
+#[derive(Debug, Clone)]
+pub struct AnalyzeOptions {
+    properties: SessionPropertiesRef,
+    mode: Mode,
+    register_tables: HashMap<String, Arc<dyn TableProvider>>,
+    remote_functions: Vec<RemoteFunction>,
+    clock: Arc<dyn Clock>,
+    data_source: Option<DataSource>,
+    sql_length_limit: Option<SqlLengthLimit>,
+    max_plan_nodes: Option<usize>,
+    concurrency_limiter: Option<Arc<ConcurrencyLimiter>>,
+    plan_cache: Option<Arc<PlanCache>>,
+    parse_cache: Option<Arc<ParseCache>>,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self {
+            properties: Arc::new(HashMap::new()),
+            mode: Mode::Unparse,
+            register_tables: HashMap::new(),
+            remote_functions: vec![],
+            clock: Arc::new(SystemClock),
+            data_source: None,
+            sql_length_limit: None,
+            max_plan_nodes: None,
+            concurrency_limiter: None,
+            plan_cache: None,
+            parse_cache: None,
         }
     }
 }
 
+impl AnalyzeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The session properties used to check the column level access control rules.
+    pub fn with_properties(mut self, properties: SessionPropertiesRef) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Register the table instead of the one inferred from the model columns.
+    pub fn with_register_table(
+        mut self,
+        name: impl Into<String>,
+        table: Arc<dyn TableProvider>,
+    ) -> Self {
+        self.register_tables.insert(name.into(), table);
+        self
+    }
+
+    /// The functions registered for every transformation of the analyzed MDL, in addition
+    /// to the ones passed to the transformation.
+    pub fn with_remote_functions(
+        mut self,
+        remote_functions: Vec<RemoteFunction>,
+    ) -> Self {
+        self.remote_functions = remote_functions;
+        self
+    }
+
+    /// The clock providing the time of `now()`, `current_date` and the other time
+    /// functions of the executed queries, and the time the freshness of the models is
+    /// measured against. Defaults to the [SystemClock].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The data source whose dialect the SQL is transformed to, instead of the data source
+    /// of the manifest.
+    pub fn with_data_source(mut self, data_source: DataSource) -> Self {
+        self.data_source = Some(data_source);
+        self
+    }
+
+    /// Reject the SQL longer than the limit before it's parsed. No limit by default.
+    pub fn with_sql_length_limit(mut self, sql_length_limit: SqlLengthLimit) -> Self {
+        self.sql_length_limit = Some(sql_length_limit);
+        self
+    }
+
+    /// Abort the analysis with [DataFusionError::ResourcesExhausted] if the plan expanded
+    /// by the MDL has more nodes than the limit. No limit by default.
+    pub fn with_max_plan_nodes(mut self, max_plan_nodes: usize) -> Self {
+        self.max_plan_nodes = Some(max_plan_nodes);
+        self
+    }
+
+    /// Limit the number of the concurrent transformations of the analyzed MDL. The excess
+    /// transformations wait for a permit. If `max_queued` is set, the transformation
+    /// arriving when that many are waiting fails with [DataFusionError::ResourcesExhausted].
+    ///
+    /// The limiter is created here, so the MDLs analyzed with clones of the options share
+    /// it. No limit by default.
+    pub fn with_max_concurrent_analyses(
+        self,
+        max_concurrent_analyses: usize,
+        max_queued: Option<usize>,
+    ) -> Self {
+        self.with_concurrency_limiter(Arc::new(ConcurrencyLimiter::new(
+            max_concurrent_analyses,
+            max_queued,
+        )))
+    }
+
+    /// Share the limiter with the other MDLs, e.g. to limit the transformations of all the
+    /// manifests of the process.
+    pub fn with_concurrency_limiter(
+        mut self,
+        concurrency_limiter: Arc<ConcurrencyLimiter>,
+    ) -> Self {
+        self.concurrency_limiter = Some(concurrency_limiter);
+        self
+    }
+
+    /// Cache the plans of the analyzed MDL in the given cache instead of the global one
+    /// enabled by [cache::set_plan_cache_capacity].
+    pub fn with_plan_cache(mut self, plan_cache: Arc<PlanCache>) -> Self {
+        self.plan_cache = Some(plan_cache);
+        self
+    }
+
+    /// Cache the parsed statements in the given cache instead of the global one enabled
+    /// by [cache::set_parse_cache_capacity].
+    pub fn with_parse_cache(mut self, parse_cache: Arc<ParseCache>) -> Self {
+        self.parse_cache = Some(parse_cache);
+        self
+    }
+
+    pub fn properties(&self) -> &SessionPropertiesRef {
+        &self.properties
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn remote_functions(&self) -> &[RemoteFunction] {
+        &self.remote_functions
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn data_source(&self) -> Option<DataSource> {
+        self.data_source
+    }
+
+    pub fn sql_length_limit(&self) -> Option<SqlLengthLimit> {
+        self.sql_length_limit
+    }
+
+    pub fn max_plan_nodes(&self) -> Option<usize> {
+        self.max_plan_nodes
+    }
+
+    pub fn concurrency_limiter(&self) -> Option<&Arc<ConcurrencyLimiter>> {
+        self.concurrency_limiter.as_ref()
+    }
+
+    pub fn plan_cache(&self) -> Option<&Arc<PlanCache>> {
+        self.plan_cache.as_ref()
+    }
+
+    pub fn parse_cache(&self) -> Option<&Arc<ParseCache>> {
+        self.parse_cache.as_ref()
+    }
+}
+
+/// Compute the lineage of the MDL. A failure is logged and kept for the queries needing it.
+fn analyze_lineage(
+    wren_mdl: &WrenMDL,
+) -> std::result::Result<Arc<lineage::Lineage>, String> {
+    lineage::Lineage::new(wren_mdl).map(Arc::new).map_err(|e| {
+        warn!("Failed to compute the lineage of the MDL: {e}");
+        e.to_string()
+    })
+}
+
 impl AnalyzedWrenMDL {
     pub fn analyze(
         manifest: Manifest,
         properties: SessionPropertiesRef,
         mode: Mode,
     ) -> Result<Self> {
-        let wren_mdl = Arc::new(WrenMDL::infer_and_register_remote_table(
-            manifest, properties, mode,
-        )?);
-        let lineage = Arc::new(lineage::Lineage::new(&wren_mdl)?);
-        Ok(AnalyzedWrenMDL { wren_mdl, lineage })
+        Self::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new()
+                .with_properties(properties)
+                .with_mode(mode),
+        )
+    }
+
+    pub fn analyze_with_options(
+        mut manifest: Manifest,
+        options: &AnalyzeOptions,
+    ) -> Result<Self> {
+        if let Some(data_source) = options.data_source {
+            manifest.data_source = Some(data_source);
+        }
+        let mut wren_mdl = WrenMDL::infer_and_register_remote_table(
+            manifest,
+            Arc::clone(&options.properties),
+            options.mode,
+        )?;
+        for (name, table) in &options.register_tables {
+            wren_mdl.register_table(name.clone(), Arc::clone(table));
+        }
+        wren_mdl.validate_default_values()?;
+        wren_mdl.validate_session_keys()?;
+        let wren_mdl = Arc::new(wren_mdl);
+        let lineage = analyze_lineage(&wren_mdl);
+        Ok(AnalyzedWrenMDL {
+            wren_mdl,
+            lineage,
+            remote_functions: options.remote_functions.clone(),
+            clock: Arc::clone(&options.clock),
+            sql_length_limit: options.sql_length_limit,
+            max_plan_nodes: options.max_plan_nodes,
+            concurrency_limiter: options.concurrency_limiter.clone(),
+            plan_cache: options.plan_cache.clone(),
+            parse_cache: options.parse_cache.clone(),
+        })
     }
 
     pub fn analyze_with_tables(
@@ -93,10 +384,19 @@ impl AnalyzedWrenMDL {
         for (name, table) in register_tables {
             wren_mdl.register_table(name, table);
         }
-        let lineage = lineage::Lineage::new(&wren_mdl)?;
+        wren_mdl.validate_default_values()?;
+        wren_mdl.validate_session_keys()?;
+        let lineage = analyze_lineage(&wren_mdl);
         Ok(AnalyzedWrenMDL {
             wren_mdl: Arc::new(wren_mdl),
-            lineage: Arc::new(lineage),
+            lineage,
+            remote_functions: vec![],
+            clock: Arc::new(SystemClock),
+            sql_length_limit: None,
+            max_plan_nodes: None,
+            concurrency_limiter: None,
+            plan_cache: None,
+            parse_cache: None,
         })
     }
 
@@ -104,11 +404,124 @@ impl AnalyzedWrenMDL {
         Arc::clone(&self.wren_mdl)
     }
 
-    pub fn lineage(&self) -> &lineage::Lineage {
-        &self.lineage
+    /// The functions registered by [AnalyzeOptions::with_remote_functions]
+    pub fn remote_functions(&self) -> &[RemoteFunction] {
+        &self.remote_functions
+    }
+
+    /// The clock set by [AnalyzeOptions::with_clock]
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// The limit set by [AnalyzeOptions::with_sql_length_limit]
+    pub fn sql_length_limit(&self) -> Option<SqlLengthLimit> {
+        self.sql_length_limit
+    }
+
+    /// The limit set by [AnalyzeOptions::with_max_plan_nodes]
+    pub fn max_plan_nodes(&self) -> Option<usize> {
+        self.max_plan_nodes
+    }
+
+    /// The limiter set by [AnalyzeOptions::with_max_concurrent_analyses]
+    pub fn concurrency_limiter(&self) -> Option<&Arc<ConcurrencyLimiter>> {
+        self.concurrency_limiter.as_ref()
+    }
+
+    /// The cache set by [AnalyzeOptions::with_plan_cache], or the global plan cache if
+    /// it's enabled
+    pub fn plan_cache(&self) -> Option<Arc<PlanCache>> {
+        self.plan_cache.clone().or_else(plan_cache)
+    }
+
+    /// The cache set by [AnalyzeOptions::with_parse_cache], or the global parse cache if
+    /// it's enabled
+    pub fn parse_cache(&self) -> Option<Arc<ParseCache>> {
+        self.parse_cache.clone().or_else(parse_cache)
+    }
+
+    /// The lineage of the columns, required to plan the calculated fields. If it can't be
+    /// computed, the error is returned here instead of failing the analysis, so the queries
+    /// not using the calculated fields are still transformed.
+    pub fn lineage(&self) -> Result<&lineage::Lineage> {
+        match &self.lineage {
+            Ok(lineage) => Ok(lineage),
+            Err(e) => plan_err!("The lineage of the MDL is unavailable: {e}"),
+        }
+    }
+
+    /// List the queryable models and views with their columns.
+    /// The columns are read from the tables registered in the catalog, so the columns
+    /// denied by the CLAC rules for the given properties are excluded.
+    pub async fn list_tables(
+        self: &Arc<Self>,
+        properties: SessionPropertiesRef,
+    ) -> Result<Vec<TableInfo>> {
+        let ctx = apply_wren_on_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(self),
+            properties,
+            Mode::Unparse,
+        )
+        .await?;
+        let schema = ctx
+            .catalog(self.wren_mdl.catalog())
+            .and_then(|catalog| catalog.schema(self.wren_mdl.schema()))
+            .ok_or_else(|| {
+                internal_datafusion_err!(
+                    "Schema {}.{} is not registered",
+                    self.wren_mdl.catalog(),
+                    self.wren_mdl.schema()
+                )
+            })?;
+
+        let datasets = self
+            .wren_mdl
+            .models()
+            .iter()
+            .map(|model| (model.name(), TableKind::Model))
+            .chain(
+                self.wren_mdl
+                    .views()
+                    .iter()
+                    .map(|view| (view.name(), TableKind::View)),
+            );
+        let mut tables = vec![];
+        for (name, kind) in datasets {
+            let Some(table) = schema.table(name).await? else {
+                return internal_err!("Table {} is not registered", name);
+            };
+            let columns = table
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| (field.name().to_string(), field.data_type().clone()))
+                .collect();
+            tables.push(TableInfo {
+                name: name.to_string(),
+                kind,
+                columns,
+            });
+        }
+        Ok(tables)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Model,
+    View,
+}
+
+/// The queryable table of the semantic layer and its columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+    pub name: String,
+    pub kind: TableKind,
+    pub columns: Vec<(String, DataType)>,
+}
+
 pub type RegisterTables = HashMap<String, Arc<dyn TableProvider>>;
 // This is the main struct that holds the manifest and provides methods to access the models
 pub struct WrenMDL {
@@ -116,26 +529,36 @@ pub struct WrenMDL {
     pub qualified_references: HashMap<datafusion::common::Column, ColumnReference>,
     pub register_tables: RegisterTables,
     pub catalog_schema_prefix: String,
+    /// The [Manifest::content_hash] computed once, because the caches key every lookup by it
+    manifest_hash: u64,
 }
 
 impl Hash for WrenMDL {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.manifest.hash(state);
+        self.manifest_hash.hash(state);
     }
 }
 
 impl WrenMDL {
     pub fn new(manifest: Manifest) -> Self {
+        // The keys of the columns of a dataset share the catalog, schema and dataset names,
+        // so they're interned instead of allocated per column.
+        let interner = Interner::default();
+        let qualified_column = |dataset: &str, column: &str| {
+            datafusion::common::Column::new(
+                Some(TableReference::full(
+                    interner.intern(&manifest.catalog),
+                    interner.intern(&manifest.schema),
+                    interner.intern(dataset),
+                )),
+                column,
+            )
+        };
         let mut qualifed_references = HashMap::new();
         manifest.models.iter().for_each(|model| {
             model.get_visible_columns().for_each(|column| {
                 qualifed_references.insert(
-                    from_qualified_name_str(
-                        &manifest.catalog,
-                        &manifest.schema,
-                        model.name(),
-                        column.name(),
-                    ),
+                    qualified_column(model.name(), column.name()),
                     ColumnReference::new(
                         Dataset::Model(Arc::clone(model)),
                         Arc::clone(&column),
@@ -146,12 +569,7 @@ impl WrenMDL {
         manifest.metrics.iter().for_each(|metric| {
             metric.dimension.iter().for_each(|dimension| {
                 qualifed_references.insert(
-                    from_qualified_name_str(
-                        &manifest.catalog,
-                        &manifest.schema,
-                        metric.name(),
-                        dimension.name(),
-                    ),
+                    qualified_column(metric.name(), dimension.name()),
                     ColumnReference::new(
                         Dataset::Metric(Arc::clone(metric)),
                         Arc::clone(dimension),
@@ -160,12 +578,7 @@ impl WrenMDL {
             });
             metric.measure.iter().for_each(|measure| {
                 qualifed_references.insert(
-                    from_qualified_name_str(
-                        &manifest.catalog,
-                        &manifest.schema,
-                        metric.name(),
-                        measure.name(),
-                    ),
+                    qualified_column(metric.name(), measure.name()),
                     ColumnReference::new(
                         Dataset::Metric(Arc::clone(metric)),
                         Arc::clone(measure),
@@ -176,6 +589,7 @@ impl WrenMDL {
 
         WrenMDL {
             catalog_schema_prefix: format!("{}.{}.", &manifest.catalog, &manifest.schema),
+            manifest_hash: manifest.content_hash(),
             manifest,
             qualified_references: qualifed_references,
             register_tables: HashMap::new(),
@@ -193,6 +607,7 @@ impl WrenMDL {
         properties: SessionPropertiesRef,
         mode: Mode,
     ) -> Result<Self> {
+        let properties = with_default_properties(&manifest, properties);
         let mut mdl = WrenMDL::new(manifest);
         let sources: Vec<_> = mdl
             .models()
@@ -303,6 +718,11 @@ impl WrenMDL {
         &self.manifest.catalog
     }
 
+    /// The [Manifest::content_hash] of the manifest
+    pub fn manifest_hash(&self) -> u64 {
+        self.manifest_hash
+    }
+
     pub fn schema(&self) -> &str {
         &self.manifest.schema
     }
@@ -323,6 +743,11 @@ impl WrenMDL {
         &self.manifest.metrics
     }
 
+    /// Return true if the manifest defines no models, views or metrics.
+    pub fn is_empty(&self) -> bool {
+        self.models().is_empty() && self.views().is_empty() && self.metrics().is_empty()
+    }
+
     pub fn data_source(&self) -> Option<DataSource> {
         self.manifest.data_source
     }
@@ -343,6 +768,14 @@ impl WrenMDL {
             .cloned()
     }
 
+    pub fn get_metric(&self, name: &str) -> Option<Arc<Metric>> {
+        self.manifest
+            .metrics
+            .iter()
+            .find(|metric| metric.name == name)
+            .cloned()
+    }
+
     pub fn get_relationship(&self, name: &str) -> Option<Arc<Relationship>> {
         self.manifest
             .relationships
@@ -361,6 +794,43 @@ impl WrenMDL {
     pub fn catalog_schema_prefix(&self) -> &str {
         &self.catalog_schema_prefix
     }
+
+    /// Check if the default values of the columns are literals matching the column types.
+    fn validate_default_values(&self) -> Result<()> {
+        self.models()
+            .iter()
+            .flat_map(|model| model.columns.iter())
+            .try_for_each(|column| create_default_value_expr(column).map(|_| ()))
+    }
+
+    /// Check if the access control rules only use the session keys declared in the manifest.
+    /// Nothing is checked if the manifest doesn't declare any key.
+    fn validate_session_keys(&self) -> Result<()> {
+        let Some(declared_session_keys) = self.manifest.declared_session_keys() else {
+            return Ok(());
+        };
+        for model in self.models() {
+            for rule in model.row_level_access_controls() {
+                validate_rlac_rule_with_declared_keys(
+                    rule,
+                    model,
+                    Some(declared_session_keys),
+                )?;
+            }
+            for clac in model
+                .columns
+                .iter()
+                .filter_map(|column| column.column_level_access_control())
+            {
+                validate_declared_session_keys(
+                    &clac.name,
+                    &clac.required_properties,
+                    declared_session_keys,
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Create a SessionContext with the default functions registered
@@ -406,113 +876,765 @@ pub async fn transform_sql_with_ctx(
     properties: SessionPropertiesRef,
     sql: &str,
 ) -> Result<String> {
-    info!("wren-core received SQL: {sql}");
-    remote_functions.iter().try_for_each(|remote_function| {
-        debug!("Registering remote function: {remote_function:?}");
-        register_remote_function(ctx, remote_function)?;
-        Ok::<_, DataFusionError>(())
-    })?;
-    let ctx = apply_wren_on_ctx(
+    transform_sql_with_format(
         ctx,
-        Arc::clone(&analyzed_mdl),
-        Arc::clone(&properties),
-        Mode::Unparse,
+        analyzed_mdl,
+        remote_functions,
+        properties,
+        sql,
+        &FormatOptions::default(),
     )
-    .await?;
-    let plan = match ctx.state().create_logical_plan(sql).await {
-        Ok(plan) => plan,
-        Err(e) => {
-            eprintln!("Failed to create logical plan: {e}");
-            match permission_analyze(
-                analyzed_mdl.wren_mdl().manifest.clone(),
-                sql,
-                remote_functions,
-                properties,
-            )
-            .await
-            {
-                Ok(_) => {
-                    return Err(e);
-                }
-                Err(e) => {
-                    return Err(e);
-                }
-            }
+    .await
+}
+
+/// Transform the parsed statement based on the MDL with the SessionContext. It's the same as
+/// [transform_sql_with_ctx] without parsing the SQL again, for the callers that already
+/// parse it, e.g. to route the query.
+///
+/// The statement is rendered back to SQL for the logs, the SQL length limit and the key of
+/// the plan cache.
+pub async fn transform_statement_with_ctx(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    statement: ast::Statement,
+) -> Result<String> {
+    let limiter = analyzed_mdl.concurrency_limiter().cloned();
+    let transform = async {
+        let properties =
+            with_default_properties(&analyzed_mdl.wren_mdl().manifest, properties);
+        let sql = statement.to_string();
+        info!("wren-core received SQL: {sql}");
+        if let Some(limit) = analyzed_mdl.sql_length_limit() {
+            limit.check(&sql)?;
         }
+        transform_statement_internal(
+            ctx,
+            analyzed_mdl,
+            remote_functions,
+            properties,
+            &sql,
+            DFStatement::Statement(Box::new(statement)),
+            &FormatOptions::default(),
+        )
+        .await
     };
-    debug!("wren-core original plan:\n {plan}");
-    let analyzed = ctx.state().optimize(&plan)?;
-    debug!("wren-core final planned:\n {analyzed}");
-
-    let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
-    let wren_dialect = WrenDialect::new(&data_source);
-    let unparser = Unparser::new(&wren_dialect).with_pretty(true);
-    // show the planned sql
-    match unparser.plan_to_sql(&analyzed) {
-        Ok(sql) => {
-            // TODO: workaround to remove unnecessary catalog and schema of mdl
-            let replaced = sql
-                .to_string()
-                .replace(analyzed_mdl.wren_mdl().catalog_schema_prefix(), "");
-            info!("wren-core planned SQL: {replaced}");
-            Ok(replaced)
-        }
-        Err(e) => Err(e),
+    match limiter {
+        Some(limiter) => limiter.run(transform).await,
+        None => transform.await,
     }
 }
 
-/// Try to check if the fail reason is a permission denied error.
+/// Transform the SQL based on the MDL with the SessionContext and format the output
+/// by the [FormatOptions].
 ///
-/// In a normal exeuction flow, if a column is not allowed to be used in the model plan,
-/// it will return an column not found error because the column won't be registered in the [WrenDataSource].
-/// Through this function, we can check if the error is a permission denied error, then provide a more user-friendly error message.
-async fn permission_analyze(
-    manifest: Manifest,
-    sql: &str,
+/// If [AnalyzeOptions::with_max_concurrent_analyses] is configured, the transformation waits
+/// for a permit of the limiter before it starts.
+pub async fn transform_sql_with_format(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
     remote_functions: &[RemoteFunction],
     properties: SessionPropertiesRef,
-) -> Result<()> {
-    let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
-        manifest,
-        Arc::clone(&properties),
-        Mode::PermissionAnalyze,
-    )?);
-    let ctx = create_wren_ctx(None);
-    remote_functions.iter().try_for_each(|remote_function| {
-        debug!("Registering remote function: {remote_function:?}");
-        register_remote_function(&ctx, remote_function)?;
-        Ok::<_, DataFusionError>(())
-    })?;
-    let ctx = apply_wren_on_ctx(&ctx, analyzed_mdl, properties, Mode::PermissionAnalyze)
-        .await?;
+    sql: &str,
+    format: &FormatOptions,
+) -> Result<String> {
+    let limiter = analyzed_mdl.concurrency_limiter().cloned();
+    let transform = transform_sql_internal(
+        ctx,
+        analyzed_mdl,
+        remote_functions,
+        properties,
+        sql,
+        format,
+    );
+    match limiter {
+        Some(limiter) => limiter.run(transform).await,
+        None => transform.await,
+    }
+}
 
-    let plan = match ctx.state().create_logical_plan(sql).await {
-        Ok(plan) => plan,
-        Err(e) => {
-            debug!("Failed to create logical plan: {e}");
-            return Ok(());
-        }
+/// Check the analysis pipeline works by transforming `SELECT 1` on a tiny in-memory
+/// manifest. It's a cheap liveness probe without depending on a real manifest.
+pub async fn selftest() -> Result<()> {
+    let manifest = ManifestBuilder::new()
+        .catalog("selftest")
+        .schema("selftest")
+        .model(
+            ModelBuilder::new("selftest")
+                .table_reference("selftest")
+                .column(ColumnBuilder::new("id", "int").build())
+                .build(),
+        )
+        .build();
+    let selftest = async {
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::new()),
+            Mode::Unparse,
+        )?);
+        transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            &[],
+            Arc::new(HashMap::new()),
+            "SELECT 1 FROM selftest",
+        )
+        .await
     };
-    debug!("wren-core start to anlayze:\n {plan}");
-    match ctx.state().optimize(&plan) {
-        Ok(_) => {
-            info!("SQL is allowed to be planned");
-        }
-        // If the error is a permission denied error, we throw it instead. Otherwise, we throw the original error.
-        Err(e) => {
-            if let DataFusionError::Context(_, ee) = &e {
-                if let DataFusionError::External(we) = ee.as_ref() {
-                    if we.downcast_ref::<WrenError>().is_some() {
-                        return Err(e);
-                    }
-                }
-            }
-        }
-    }
-    Ok(())
+    selftest
+        .await
+        .map(|_| ())
+        .map_err(|e| e.context("The self-test of the analysis failed"))
 }
 
-fn register_remote_function(
+/// Plan the SQL. The parsed statement is rewritten before planning if it references the
+/// parameterized views, because DataFusion doesn't accept the named arguments of a table
+/// function, if it filters a metric by `HAVING`, if it has a `LATERAL unnest(...)` or a
+/// `QUALIFY`.
+pub(crate) async fn create_logical_plan(
+    ctx: &SessionContext,
+    wren_mdl: &WrenMDL,
+    sql: &str,
+) -> Result<LogicalPlan> {
+    let state = ctx.state();
+    let dialect = state.config_options().sql_parser.dialect.clone();
+    let statement = state.sql_to_statement(sql, &dialect)?;
+    create_logical_plan_from_statement(ctx, wren_mdl, statement).await
+}
+
+/// Plan the parsed statement. It's rewritten before planning like [create_logical_plan].
+pub(crate) async fn create_logical_plan_from_statement(
+    ctx: &SessionContext,
+    wren_mdl: &WrenMDL,
+    mut statement: DFStatement,
+) -> Result<LogicalPlan> {
+    let state = ctx.state();
+    if let DFStatement::Statement(statement) = &mut statement {
+        rewrite_lateral_unnest(statement);
+        rewrite_qualify(statement);
+        if wren_mdl
+            .views()
+            .iter()
+            .any(|view| !view.parameters.is_empty())
+        {
+            expand_parameterized_views(wren_mdl, statement)?;
+        }
+        if !wren_mdl.metrics().is_empty() {
+            push_down_metric_having(&state, wren_mdl, statement)?;
+        }
+    }
+    // the statement is consumed by the planning, keep it to report the unknown table
+    let original = wren_mdl.is_empty().then(|| statement.clone());
+    match (state.statement_to_plan(statement).await, original) {
+        (Err(e), Some(DFStatement::Statement(statement))) => {
+            Err(unknown_table_in_statement(ctx, wren_mdl, &statement).unwrap_or(e))
+        }
+        (result, _) => result,
+    }
+}
+
+/// Find the table of the SQL that would be a model or view but can't be resolved, because
+/// the manifest is empty. The planning error of DataFusion doesn't tell that the manifest
+/// defines nothing, so it's reported instead.
+fn unknown_table_in_statement(
+    ctx: &SessionContext,
+    wren_mdl: &WrenMDL,
+    statement: &ast::Statement,
+) -> Option<DataFusionError> {
+    let mut cte_names = CteNames::default();
+    let _ = statement.visit(&mut cte_names);
+    let mut unknown = None;
+    let _ = visit_relations(statement, |relation| {
+        let is_unknown = mdl_object_name(wren_mdl, relation).is_some_and(|name| {
+            !cte_names.0.contains(name)
+                && !ctx
+                    .table_exist(relation.to_string().as_str())
+                    .unwrap_or(false)
+        });
+        if is_unknown {
+            unknown = Some(relation.to_string());
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    });
+    unknown.map(|name| {
+        plan_datafusion_err!(
+            "Unknown table {name}: the manifest defines no models or views"
+        )
+    })
+}
+
+/// Collect the names of the common table expressions, which aren't tables of the context.
+#[derive(Default)]
+struct CteNames(HashSet<String>);
+
+impl Visitor for CteNames {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &ast::Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.0.insert(cte.alias.name.value.clone());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+async fn transform_sql_internal(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+    format: &FormatOptions,
+) -> Result<String> {
+    let (properties, statement) = parse_sql(ctx, &analyzed_mdl, properties, sql)?;
+    transform_statement_internal(
+        ctx,
+        analyzed_mdl,
+        remote_functions,
+        properties,
+        sql,
+        statement,
+        format,
+    )
+    .await
+}
+
+/// Merge the default properties of the manifest, check the SQL length limit and parse the
+/// SQL. If the [AnalyzedWrenMDL::parse_cache] is enabled, the statement parsed before is
+/// reused.
+fn parse_sql(
+    ctx: &SessionContext,
+    analyzed_mdl: &AnalyzedWrenMDL,
+    properties: SessionPropertiesRef,
+    sql: &str,
+) -> Result<(SessionPropertiesRef, DFStatement)> {
+    let properties =
+        with_default_properties(&analyzed_mdl.wren_mdl().manifest, properties);
+    info!("wren-core received SQL: {sql}");
+    if let Some(limit) = analyzed_mdl.sql_length_limit() {
+        limit.check(sql)?;
+    }
+    let dialect = ctx.state().config_options().sql_parser.dialect.clone();
+    let Some(parse_cache) = analyzed_mdl.parse_cache() else {
+        let statement = ctx.state().sql_to_statement(sql, &dialect)?;
+        return Ok((properties, statement));
+    };
+    let key = (dialect, sql.to_string());
+    if let Some(statement) = parse_cache.get(&key) {
+        return Ok((properties, statement.as_ref().clone()));
+    }
+    let statement = ctx.state().sql_to_statement(sql, &key.0)?;
+    parse_cache.insert_if_absent(key, Arc::new(statement.clone()));
+    Ok((properties, statement))
+}
+
+/// Transform the parsed statement of the SQL. The SQL is the key of the plan cache.
+async fn transform_statement_internal(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+    mut statement: DFStatement,
+    format: &FormatOptions,
+) -> Result<String> {
+    let wren_mdl = analyzed_mdl.wren_mdl();
+    let data_source = wren_mdl.data_source().unwrap_or_default();
+    let samples = TableSamples::take(
+        &wren_mdl,
+        &WrenDialect::new(&data_source),
+        &data_source,
+        &mut statement,
+    )?;
+    let analyzed = plan_statement(
+        ctx,
+        Arc::clone(&analyzed_mdl),
+        remote_functions,
+        properties,
+        sql,
+        statement,
+        format,
+    )
+    .await?;
+    let replaced = unparse_plan(
+        &wren_mdl,
+        &analyzed,
+        &data_source,
+        format.name_qualification,
+        &samples,
+    )?;
+    info!("wren-core planned SQL: {replaced}");
+    format_sql(&replaced, format)
+}
+
+/// Transform the SQL based on the MDL with the SessionContext, with the literals replaced
+/// by the placeholders of the dialect of the data source, e.g. `$1` for Postgres or `?` for
+/// MySQL. The values to bind are returned in the order of the placeholders. The literals
+/// injected by the access control rules and the session properties are parameterized too.
+///
+/// If [AnalyzeOptions::with_max_concurrent_analyses] is configured, the transformation waits
+/// for a permit of the limiter before it starts.
+pub async fn transform_sql_parameterized(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+) -> Result<(String, Vec<ScalarValue>)> {
+    let limiter = analyzed_mdl.concurrency_limiter().cloned();
+    let transform = async {
+        let format = FormatOptions::default();
+        let (properties, mut statement) = parse_sql(ctx, &analyzed_mdl, properties, sql)?;
+        let wren_mdl = analyzed_mdl.wren_mdl();
+        let data_source = wren_mdl.data_source().unwrap_or_default();
+        let wren_dialect = WrenDialect::new(&data_source);
+        let samples =
+            TableSamples::take(&wren_mdl, &wren_dialect, &data_source, &mut statement)?;
+        let analyzed = plan_statement(
+            ctx,
+            Arc::clone(&analyzed_mdl),
+            remote_functions,
+            properties,
+            sql,
+            statement,
+            &format,
+        )
+        .await?;
+        let mut statement = unparse_statement(
+            &wren_dialect,
+            &analyzed,
+            format.name_qualification,
+            &samples,
+        )?;
+        let values = wren_dialect.parameterize_statement(&mut statement);
+        let parameterized = strip_mdl_prefix(&wren_mdl, &statement);
+        info!("wren-core planned SQL: {parameterized}");
+        Ok((parameterized, values))
+    };
+    match limiter {
+        Some(limiter) => limiter.run(transform).await,
+        None => transform.await,
+    }
+}
+
+/// Plan the parsed statement of the SQL with the plan cache. The SQL is the key of the cache.
+/// The functions of the analyzed MDL are registered along with the given ones.
+async fn plan_statement(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+    statement: DFStatement,
+    format: &FormatOptions,
+) -> Result<LogicalPlan> {
+    let remote_functions: Vec<_> = analyzed_mdl
+        .remote_functions()
+        .iter()
+        .chain(remote_functions)
+        .cloned()
+        .collect();
+    // register before building the key, because the functions of the session are a part of it
+    remote_functions.iter().try_for_each(|remote_function| {
+        debug!("Registering remote function: {remote_function:?}");
+        register_remote_function(ctx, remote_function)?;
+        Ok::<_, DataFusionError>(())
+    })?;
+    let analyzed = match analyzed_mdl.plan_cache() {
+        Some(plan_cache) => {
+            let key =
+                PlanCacheKey::new(&analyzed_mdl, ctx, &properties, Mode::Unparse, sql)?
+                    .with_functions(&remote_functions);
+            match plan_cache.get(&key) {
+                Some(plan) => plan.as_ref().clone(),
+                None => {
+                    let plan = plan_sql(
+                        ctx,
+                        Arc::clone(&analyzed_mdl),
+                        &remote_functions,
+                        properties,
+                        sql,
+                        statement,
+                    )
+                    .await?;
+                    plan_cache.insert_if_absent(key, Arc::new(plan.clone()));
+                    plan
+                }
+            }
+        }
+        None => {
+            plan_sql(
+                ctx,
+                Arc::clone(&analyzed_mdl),
+                &remote_functions,
+                properties,
+                sql,
+                statement,
+            )
+            .await?
+        }
+    };
+    let analyzed = if format.qualify_columns {
+        qualify_columns(analyzed)?
+    } else {
+        analyzed
+    };
+    debug!("wren-core final planned:\n {analyzed}");
+    Ok(analyzed)
+}
+
+/// Plan and optimize the parsed statement of the SQL for unparsing. The remote functions are
+/// already registered by [plan_statement].
+async fn plan_sql(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+    statement: DFStatement,
+) -> Result<LogicalPlan> {
+    let ctx = apply_wren_on_ctx(
+        ctx,
+        Arc::clone(&analyzed_mdl),
+        Arc::clone(&properties),
+        Mode::Unparse,
+    )
+    .await?;
+    let plan = match create_logical_plan_from_statement(
+        &ctx,
+        &analyzed_mdl.wren_mdl(),
+        statement,
+    )
+    .await
+    {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Failed to create logical plan: {e}");
+            match permission_analyze(
+                analyzed_mdl.wren_mdl().manifest.clone(),
+                sql,
+                remote_functions,
+                properties,
+            )
+            .await
+            {
+                Ok(_) => {
+                    return Err(e);
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+    };
+    debug!("wren-core original plan:\n {plan}");
+    preserve_output_names(&plan, ctx.state().optimize(&plan)?)
+}
+
+/// Unparse the analyzed plan to the SQL of the dialect of the data source. The table
+/// references are qualified by the [NameQualification], and the `TABLESAMPLE` clauses
+/// taken before planning are attached to the tables of the models.
+pub(crate) fn unparse_plan(
+    wren_mdl: &WrenMDL,
+    plan: &LogicalPlan,
+    data_source: &DataSource,
+    name_qualification: NameQualification,
+    samples: &TableSamples,
+) -> Result<String> {
+    let wren_dialect = WrenDialect::new(data_source);
+    let statement = unparse_statement(&wren_dialect, plan, name_qualification, samples)?;
+    Ok(strip_mdl_prefix(wren_mdl, &statement))
+}
+
+fn unparse_statement(
+    wren_dialect: &WrenDialect,
+    plan: &LogicalPlan,
+    name_qualification: NameQualification,
+    samples: &TableSamples,
+) -> Result<ast::Statement> {
+    let unparser = Unparser::new(wren_dialect).with_pretty(true);
+    let mut statement = unparser.plan_to_sql(plan)?;
+    wren_dialect.normalize_statement(&mut statement);
+    // the tables are matched by their full names
+    samples.attach(&mut statement)?;
+    qualify_table_names(&mut statement, name_qualification)?;
+    Ok(statement)
+}
+
+fn strip_mdl_prefix(wren_mdl: &WrenMDL, statement: &ast::Statement) -> String {
+    // TODO: workaround to remove unnecessary catalog and schema of mdl
+    statement
+        .to_string()
+        .replace(wren_mdl.catalog_schema_prefix(), "")
+}
+
+/// Restore the output column names of the original plan if the analyzed plan lost them.
+///
+/// The model and view expansion re-projects the columns, so a user-specified alias
+/// (e.g. `SELECT c_custkey AS customer_id`) could be replaced by the underlying column name.
+/// The output columns are matched by position, and the analyzed plan is wrapped by a
+/// projection aliasing them back only if any name differs.
+fn preserve_output_names(
+    original: &LogicalPlan,
+    analyzed: LogicalPlan,
+) -> Result<LogicalPlan> {
+    let original_fields = original.schema().fields();
+    let names_differ = analyzed.schema().fields().len() == original_fields.len()
+        && analyzed
+            .schema()
+            .fields()
+            .iter()
+            .zip(original_fields.iter())
+            .any(|(analyzed, original)| analyzed.name() != original.name());
+    if !names_differ {
+        return Ok(analyzed);
+    }
+    let exprs = analyzed
+        .schema()
+        .iter()
+        .zip(original_fields.iter())
+        .map(|(analyzed, original)| {
+            datafusion::logical_expr::Expr::from(analyzed).alias(original.name())
+        })
+        .collect::<Vec<_>>();
+    LogicalPlanBuilder::from(analyzed).project(exprs)?.build()
+}
+
+/// Execute the SQL based on the MDL by the local runtime and stream the result batches.
+///
+/// The session is configured with `batch_size` and a single target partition, so each
+/// operator produces batches of about `batch_size` rows and only a bounded number of
+/// batches are buffered before the consumer polls the stream. It gives the consumer
+/// backpressure instead of collecting the whole result in memory.
+///
+/// No row limit is applied by this function, see [execute_stream_with_options] for the row
+/// caps. The `LIMIT` of the SQL is still planned as usual, so the stream ends once enough
+/// rows are produced. The final batch of a limited query may be smaller than `batch_size`.
+pub async fn execute_stream_with_limit(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    sql: &str,
+    batch_size: usize,
+) -> Result<SendableRecordBatchStream> {
+    execute_stream_with_options(
+        ctx,
+        analyzed_mdl,
+        properties,
+        sql,
+        &ExecuteOptions::new().with_batch_size(batch_size),
+    )
+    .await
+}
+
+/// The options of [execute_stream_with_options].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecuteOptions {
+    batch_size: usize,
+    max_rows: Option<usize>,
+    preview_limit: Option<usize>,
+}
+
+impl Default for ExecuteOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 8192,
+            max_rows: None,
+            preview_limit: None,
+        }
+    }
+}
+
+impl ExecuteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of rows of each batch of the stream. The default is 8192.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// The protective cap of the rows returned by any query.
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// The rows sampled by a preview request, e.g. 100 rows for the preview of a UI. It's
+    /// independent of [ExecuteOptions::with_max_rows], and the smaller one of the two wins.
+    pub fn with_preview_limit(mut self, preview_limit: usize) -> Self {
+        self.preview_limit = Some(preview_limit);
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn max_rows(&self) -> Option<usize> {
+        self.max_rows
+    }
+
+    pub fn preview_limit(&self) -> Option<usize> {
+        self.preview_limit
+    }
+
+    /// The limit applied to the query, the smaller one of the max rows and the preview limit
+    pub fn row_limit(&self) -> Option<usize> {
+        match (self.max_rows, self.preview_limit) {
+            (Some(max_rows), Some(preview_limit)) => Some(max_rows.min(preview_limit)),
+            (max_rows, preview_limit) => max_rows.or(preview_limit),
+        }
+    }
+}
+
+/// Execute the SQL like [execute_stream_with_limit] with the [ExecuteOptions]. If the
+/// options have a row limit, the result is limited to it on top of the `LIMIT` of the SQL.
+///
+/// The SQL is planned by the same rewrites as [transform_sql_with_ctx], e.g. `QUALIFY` and
+/// the parameterized views. The planning waits for a permit of the concurrency limiter if
+/// it's configured, but the streaming doesn't. `TABLESAMPLE` can't be executed locally.
+pub async fn execute_stream_with_options(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    sql: &str,
+    options: &ExecuteOptions,
+) -> Result<SendableRecordBatchStream> {
+    if options.batch_size == 0 {
+        return plan_err!("The batch size should be greater than 0");
+    }
+    let config = ctx
+        .copied_config()
+        .with_batch_size(options.batch_size)
+        .with_target_partitions(1);
+    let ctx = SessionContext::new_with_state(
+        SessionStateBuilder::new_from_existing(ctx.state())
+            .with_config(config)
+            .build(),
+    );
+    let limiter = analyzed_mdl.concurrency_limiter().cloned();
+    let analyze = async {
+        let (properties, mut statement) =
+            parse_sql(&ctx, &analyzed_mdl, properties, sql)?;
+        let wren_mdl = analyzed_mdl.wren_mdl();
+        let data_source = wren_mdl.data_source().unwrap_or_default();
+        // the samples are attached to the unparsed SQL only, DataFusion would read the
+        // whole table
+        let samples = TableSamples::take(
+            &wren_mdl,
+            &WrenDialect::new(&data_source),
+            &data_source,
+            &mut statement,
+        )?;
+        if !samples.is_empty() {
+            return plan_err!("TABLESAMPLE is unsupported by the local execution");
+        }
+        let clock = Arc::clone(&analyzed_mdl.clock);
+        let ctx = apply_wren_on_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            properties,
+            Mode::LocalRuntime,
+        )
+        .await?;
+        // `SessionContext::sql` starts the query at the system time, so the state is
+        // started by the clock of the MDL instead. `now()` and the other time functions
+        // read it.
+        let mut state = ctx.state();
+        start_execution_with_clock(&mut state, clock.as_ref());
+        let plan = create_logical_plan_from_statement(&ctx, &wren_mdl, statement).await?;
+        Ok::<_, DataFusionError>((ctx, state, plan))
+    };
+    let (ctx, state, plan) = match limiter {
+        Some(limiter) => limiter.run(analyze).await?,
+        None => analyze.await?,
+    };
+    let df = match plan {
+        LogicalPlan::Ddl(_) | LogicalPlan::Statement(_) => {
+            ctx.execute_logical_plan(plan).await?
+        }
+        plan => DataFrame::new(state, plan),
+    };
+    let df = match options.row_limit() {
+        Some(limit) => df.limit(0, Some(limit))?,
+        None => df,
+    };
+    df.execute_stream().await
+}
+
+/// Start the query of the state at the time of the clock. The system time set by
+/// [SessionContext::state] is kept if the time of the clock can't be a timestamp.
+fn start_execution_with_clock(state: &mut SessionState, clock: &dyn Clock) {
+    let start_time = clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|since_epoch| i64::try_from(since_epoch.as_nanos()).ok())
+        .and_then(timestamp_ns_to_datetime);
+    if let Some(start_time) = start_time {
+        state.execution_props_mut().query_execution_start_time = start_time.and_utc();
+    }
+}
+
+/// Try to check if the fail reason is a permission denied error.
+///
+/// In a normal exeuction flow, if a column is not allowed to be used in the model plan,
+/// it will return an column not found error because the column won't be registered in the [WrenDataSource].
+/// Through this function, we can check if the error is a permission denied error, then provide a more user-friendly error message.
+async fn permission_analyze(
+    manifest: Manifest,
+    sql: &str,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+) -> Result<()> {
+    let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+        manifest,
+        Arc::clone(&properties),
+        Mode::PermissionAnalyze,
+    )?);
+    let ctx = create_wren_ctx(None);
+    remote_functions.iter().try_for_each(|remote_function| {
+        debug!("Registering remote function: {remote_function:?}");
+        register_remote_function(&ctx, remote_function)?;
+        Ok::<_, DataFusionError>(())
+    })?;
+    let ctx = apply_wren_on_ctx(&ctx, analyzed_mdl, properties, Mode::PermissionAnalyze)
+        .await?;
+
+    let plan = match ctx.state().create_logical_plan(sql).await {
+        Ok(plan) => plan,
+        Err(e) => {
+            debug!("Failed to create logical plan: {e}");
+            return Ok(());
+        }
+    };
+    debug!("wren-core start to anlayze:\n {plan}");
+    match ctx.state().optimize(&plan) {
+        Ok(_) => {
+            info!("SQL is allowed to be planned");
+        }
+        // If the error is a permission denied error, we throw it instead. Otherwise, we throw the original error.
+        Err(e) => {
+            if let DataFusionError::Context(_, ee) = &e {
+                if let DataFusionError::External(we) = ee.as_ref() {
+                    if we.downcast_ref::<WrenError>().is_some() {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn register_remote_function(
     ctx: &SessionContext,
     remote_function: &RemoteFunction,
 ) -> Result<()> {
@@ -566,20 +1688,38 @@ mod test {
     use std::fs;
     use std::path::PathBuf;
     use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
 
-    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, MetricBuilder, ModelBuilder, ViewBuilder,
+    };
+    use crate::mdl::cache::{ParseCache, PlanCache};
+    use crate::mdl::clock::FixedClock;
     use crate::mdl::context::{apply_wren_on_ctx, Mode, SessionPropertiesRef};
+    use crate::mdl::format::{FormatOptions, NameQualification};
     use crate::mdl::function::RemoteFunction;
+    use crate::mdl::limiter::ConcurrencyLimiter;
     use crate::mdl::manifest::DataSource::MySQL;
     use crate::mdl::manifest::Manifest;
-    use crate::mdl::{self, create_wren_ctx, transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::mdl::sql_length::{SqlLengthLimit, SqlLengthUnit};
+    use crate::mdl::{
+        self, create_wren_ctx, execute_stream_with_limit, execute_stream_with_options,
+        selftest, transform_sql_with_ctx, transform_sql_with_format,
+        transform_statement_with_ctx, AnalyzeOptions, AnalyzedWrenMDL, ExecuteOptions,
+        TableKind, WrenMDL,
+    };
     use datafusion::arrow::array::{
         ArrayRef, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
     };
+    use datafusion::arrow::datatypes::DataType;
     use datafusion::arrow::util::pretty::pretty_format_batches_with_options;
     use datafusion::common::format::DEFAULT_FORMAT_OPTIONS;
     use datafusion::common::not_impl_err;
     use datafusion::common::Result;
+    use datafusion::error::DataFusionError;
+    use datafusion::physical_plan::common::collect;
+    use datafusion::sql::sqlparser::dialect::{Dialect, GenericDialect, MsSqlDialect};
+    use datafusion::sql::sqlparser::parser::Parser;
     use datafusion::sql::unparser::plan_to_sql;
     use insta::assert_snapshot;
     use wren_core_base::mdl::{
@@ -691,6 +1831,135 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_transform_with_pretty_format() -> Result<()> {
+        let test_data: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+                .iter()
+                .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path())?;
+        let mdl = match serde_json::from_str::<Manifest>(&mdl_json) {
+            Ok(mdl) => mdl,
+            Err(e) => return not_impl_err!("Failed to parse mdl json: {e}"),
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            mdl,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let sql = "select c_name from test.test.customer where c_custkey = 1";
+        let ctx = create_wren_ctx(None);
+        let transform = || {
+            transform_sql_with_format(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+                &FormatOptions::pretty(),
+            )
+        };
+        let pretty = transform().await?;
+        assert!(pretty.contains("\nFROM"), "unexpected sql: {pretty}");
+        assert!(pretty.contains("\nWHERE"), "unexpected sql: {pretty}");
+        assert_eq!(transform().await?, pretty);
+
+        let compact = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!compact.contains('\n'));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preserve_user_alias() -> Result<()> {
+        let test_data: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+                .iter()
+                .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path())?;
+        let mdl = match serde_json::from_str::<Manifest>(&mdl_json) {
+            Ok(mdl) => mdl,
+            Err(e) => return not_impl_err!("Failed to parse mdl json: {e}"),
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            mdl,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let sql = "select c_custkey as customer_id, c_name as c_custkey, \
+            c_custkey + 1 as next_key from test.test.customer_view";
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        // the aliases should be kept by the outermost projection
+        let outer = actual
+            .split_once(" FROM ")
+            .map(|(s, _)| s)
+            .unwrap_or(&actual);
+        assert!(outer.contains("AS customer_id"), "unexpected sql: {actual}");
+        assert!(outer.contains("AS c_custkey"), "unexpected sql: {actual}");
+        assert!(outer.contains("AS next_key"), "unexpected sql: {actual}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_group_by_all() -> Result<()> {
+        let test_data: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+                .iter()
+                .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path())?;
+        let mdl = match serde_json::from_str::<Manifest>(&mdl_json) {
+            Ok(mdl) => mdl,
+            Err(e) => return not_impl_err!("Failed to parse mdl json: {e}"),
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            mdl,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let ctx = create_wren_ctx(None);
+        // `GROUP BY ALL` is expanded to the non-aggregated projection while planning,
+        // so the output is the same as enumerating them for every dialect.
+        for (group_by_all, enumerated) in [
+            (
+                "select c_name, count(*) from test.test.customer group by all",
+                "select c_name, count(*) from test.test.customer group by c_name",
+            ),
+            (
+                "select c_name, c_custkey + 1, sum(c_custkey), count(*) \
+                from test.test.customer group by all",
+                "select c_name, c_custkey + 1, sum(c_custkey), count(*) \
+                from test.test.customer group by c_name, c_custkey + 1",
+            ),
+        ] {
+            let transform = |sql| {
+                transform_sql_with_ctx(
+                    &ctx,
+                    Arc::clone(&analyzed_mdl),
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+            };
+            let actual = transform(group_by_all).await?;
+            assert_eq!(actual, transform(enumerated).await?);
+            assert!(!actual.contains("GROUP BY ALL"), "unexpected sql: {actual}");
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_plan_calculation_without_unnamed_subquery() -> Result<()> {
         let test_data: PathBuf =
@@ -768,6 +2037,235 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_transform_sql_parameterized() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(MySQL)
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .add_row_level_access_control(
+                        "own_customer",
+                        vec![SessionProperty::new_optional("session_user", None)],
+                        "c_name = @session_user",
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let (sql, values) = transform_sql_parameterized(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "select c_custkey from customer where c_custkey > 10 and c_name = 'Gura'",
+        )
+        .await?;
+        assert_snapshot!(format!("{sql}\n{values:?}"), @r#"
+        SELECT customer.c_custkey FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer WHERE customer.c_custkey > ? AND customer.c_name = ?
+        [Int64(10), Utf8("Gura")]
+        "#);
+
+        // the literal of the access control rule is parameterized too
+        let (sql, values) = transform_sql_parameterized(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(build_headers(&[(
+                "session_user".to_string(),
+                Some("'Ina'".to_string()),
+            )])),
+            "select c_custkey from customer",
+        )
+        .await?;
+        assert_snapshot!(format!("{sql}\n{values:?}"), @r#"
+        SELECT customer.c_custkey FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer WHERE customer.c_name = ?) AS customer
+        [Utf8("Ina")]
+        "#);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameterize_statement() -> Result<()> {
+        let parameterize =
+            |data_source: &DataSource, dialect: &dyn Dialect, sql: &str| {
+                let mut statement = Parser::parse_sql(dialect, sql)?.remove(0);
+                let values = crate::mdl::dialect::WrenDialect::new(data_source)
+                    .parameterize_statement(&mut statement);
+                Ok::<_, DataFusionError>(format!("{statement}\n{values:?}"))
+            };
+        // the row counts are kept and the decimals are bound with the scale of the literal
+        assert_snapshot!(
+            parameterize(
+                &DataSource::Postgres,
+                &GenericDialect {},
+                "SELECT a FROM t WHERE b > 10.50 AND c = 'x' ORDER BY a LIMIT 10 OFFSET 5",
+            )?,
+            @r#"
+        SELECT a FROM t WHERE b > $1 AND c = $2 ORDER BY a LIMIT 10 OFFSET 5
+        [Decimal128(Some(1050),4,2), Utf8("x")]
+        "#
+        );
+        assert_snapshot!(
+            parameterize(
+                &DataSource::Postgres,
+                &GenericDialect {},
+                "SELECT a FROM t WHERE b = 0.05 OR b = 1e3 OFFSET 5 ROWS FETCH FIRST 10 ROWS ONLY",
+            )?,
+            @r#"
+        SELECT a FROM t WHERE b = $1 OR b = $2 OFFSET 5 ROWS FETCH FIRST 10 ROWS ONLY
+        [Decimal128(Some(5),2,2), Utf8("1e3")]
+        "#
+        );
+        assert_snapshot!(
+            parameterize(
+                &DataSource::MSSQL,
+                &MsSqlDialect {},
+                "SELECT TOP 3 a FROM t WHERE b = 1 UNION ALL SELECT TOP 2 a FROM u WHERE b = 2",
+            )?,
+            @r#"
+        SELECT TOP 3 a FROM t WHERE b = ? UNION ALL SELECT TOP 2 a FROM u WHERE b = ?
+        [Int64(1), Int64(2)]
+        "#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_selftest() -> Result<()> {
+        selftest().await
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_lineage() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new_calculated("c_broken", "varchar")
+                            .expression("missing.c_name")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        assert!(analyzed_mdl.lineage().is_err());
+
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "select c_custkey from wren.test.customer",
+        )
+        .await?;
+        assert!(
+            actual.contains("FROM customer AS __source"),
+            "unexpected sql: {actual}"
+        );
+
+        // the calculated field needs the lineage
+        match transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            &[],
+            Arc::new(HashMap::new()),
+            "select c_broken from wren.test.customer",
+        )
+        .await
+        {
+            Err(e) => assert!(
+                e.to_string()
+                    .contains("The lineage of the MDL is unavailable"),
+                "unexpected error: {e}"
+            ),
+            Ok(sql) => panic!("should be error: {sql}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_name_qualification() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("tpch.s1.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("customer_backup")
+                    .table_reference("tpch.s2.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let transform = |sql: &'static str, name_qualification| {
+            let analyzed_mdl = Arc::clone(&analyzed_mdl);
+            async move {
+                transform_sql_with_format(
+                    &create_wren_ctx(None),
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                    &FormatOptions::default().with_name_qualification(name_qualification),
+                )
+                .await
+            }
+        };
+        let sql = "select c_custkey from customer";
+        for (name_qualification, expected) in [
+            (NameQualification::Full, "FROM tpch.s1.customer AS __source"),
+            (
+                NameQualification::SchemaQualified,
+                "FROM s1.customer AS __source",
+            ),
+            (NameQualification::Bare, "FROM customer AS __source"),
+        ] {
+            let actual = transform(sql, name_qualification).await?;
+            assert!(actual.contains(expected), "unexpected sql: {actual}");
+        }
+
+        let sql = "select c.c_custkey from customer c join customer_backup b \
+            on c.c_custkey = b.c_custkey";
+        let actual = transform(sql, NameQualification::SchemaQualified).await?;
+        assert!(actual.contains("FROM s1.customer AS __source"));
+        assert!(actual.contains("FROM s2.customer AS __source"));
+        match transform(sql, NameQualification::Bare).await {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The tables tpch.s1.customer and tpch.s2.customer are ambiguous as customer"
+            ),
+            Ok(sql) => panic!("should be error: {sql}"),
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_remote_function() -> Result<()> {
         env_logger::init();
@@ -832,6 +2330,65 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_column_source_name() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new("account_balance", "double")
+                            .source_name("c_acctbal")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("double_balance", "double")
+                            .expression("account_balance * 2")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("rich_customer")
+                    .statement("select c_custkey, account_balance from wren.test.customer where account_balance > 1000")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let mut queries = vec![];
+        for sql in [
+            "select account_balance from customer",
+            "select double_balance from customer",
+            "select c_custkey from rich_customer where account_balance < 5000",
+        ] {
+            queries.push(
+                transform_sql_with_ctx(
+                    &ctx,
+                    Arc::clone(&analyzed_mdl),
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await?,
+            );
+        }
+        // the calculated columns and the views reference the column by its name
+        assert_snapshot!(queries.join("\n"), @r"
+        SELECT customer.account_balance FROM (SELECT customer.account_balance FROM (SELECT __source.c_acctbal AS account_balance FROM customer AS __source) AS customer) AS customer
+        SELECT customer.double_balance FROM (SELECT customer.account_balance * 2 AS double_balance FROM (SELECT __source.c_acctbal AS account_balance FROM customer AS __source) AS customer) AS customer
+        SELECT rich_customer.c_custkey FROM (SELECT customer.c_custkey, customer.account_balance FROM (SELECT customer.c_custkey, customer.account_balance FROM (SELECT __source.c_custkey AS c_custkey, __source.c_acctbal AS account_balance FROM customer AS __source) AS customer) AS customer WHERE customer.account_balance > 1000) AS rich_customer WHERE rich_customer.account_balance < 5000
+        ");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_unicode_remote_column_name() -> Result<()> {
         let ctx = create_wren_ctx(None);
@@ -1055,6 +2612,48 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_empty_manifest() -> Result<()> {
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .build(),
+            Arc::new(HashMap::new()),
+            Mode::Unparse,
+        )?);
+        for sql in ["select * from orders", "select * from wren.test.orders"] {
+            match transform_sql_with_ctx(
+                &create_wren_ctx(None),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await
+            {
+                Err(e) => assert!(
+                    e.to_string()
+                        .contains("the manifest defines no models or views"),
+                    "{e}"
+                ),
+                Ok(_) => panic!("should be error"),
+            }
+        }
+
+        // the query without any MDL reference passes through
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "with t as (select 1 as id) select id from t",
+        )
+        .await?;
+        assert!(actual.contains("SELECT"), "{actual}");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_disable_decorrelate_predicate_subquery() -> Result<()> {
         let manifest = ManifestBuilder::new()
@@ -1206,6 +2805,372 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_interval_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let mut intervals = vec![];
+        for data_source in [
+            DataSource::MySQL,
+            DataSource::BigQuery,
+            DataSource::DuckDB,
+            DataSource::Oracle,
+            DataSource::Trino,
+            DataSource::Athena,
+            DataSource::Postgres,
+            DataSource::Redshift,
+            DataSource::Snowflake,
+        ] {
+            let manifest = ManifestBuilder::new().data_source(data_source).build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::new()),
+                Mode::Unparse,
+            )?);
+            let mut actual = vec![];
+            for sql in ["select interval '30 days'", "select interval 30 day"] {
+                actual.push(
+                    transform_sql_with_ctx(
+                        &ctx,
+                        Arc::clone(&analyzed_mdl),
+                        &[],
+                        Arc::new(HashMap::new()),
+                        sql,
+                    )
+                    .await?,
+                );
+            }
+            // both the quoted and the numeric forms are unparsed to the dialect form
+            assert_eq!(actual[0], actual[1], "{data_source}");
+            intervals.push(format!("{data_source}: {}", actual[0]));
+        }
+        assert_snapshot!(intervals.join("\n"), @r"
+        MYSQL: SELECT INTERVAL 30 DAY
+        BIGQUERY: SELECT INTERVAL 30 DAY
+        DUCKDB: SELECT INTERVAL 30 DAY
+        ORACLE: SELECT INTERVAL '30' DAY
+        TRINO: SELECT INTERVAL '30' DAY
+        ATHENA: SELECT INTERVAL '30' DAY
+        POSTGRES: SELECT INTERVAL '30 DAYS'
+        REDSHIFT: SELECT INTERVAL '30 DAYS'
+        SNOWFLAKE: SELECT INTERVAL '30 DAYS'
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reserved_words_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        // `user` is reserved by Postgres but not by BigQuery. Postgres quotes every keyword
+        // known by sqlparser, BigQuery only its reserved words, e.g. `hash`.
+        let mut selections = vec![];
+        for data_source in [DataSource::Postgres, DataSource::BigQuery] {
+            let manifest = ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("user", "varchar").build())
+                        .column(ColumnBuilder::new("hash", "varchar").build())
+                        .column(ColumnBuilder::new("c_name", "varchar").build())
+                        .build(),
+                )
+                .data_source(data_source)
+                .build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::new()),
+                Mode::Unparse,
+            )?);
+            let actual = transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                r#"select "user", hash, c_name from customer"#,
+            )
+            .await?;
+            selections.push(format!("{data_source}: {actual}"));
+        }
+        assert_snapshot!(selections.join("\n"), @r#"
+        POSTGRES: SELECT customer."user", customer."hash", customer.c_name FROM (SELECT customer."user", customer."hash", customer.c_name FROM (SELECT __source."user" AS "user", __source."hash" AS "hash", __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer
+        BIGQUERY: SELECT customer.user, customer."hash", customer.c_name FROM (SELECT customer.user, customer."hash", customer.c_name FROM (SELECT __source.user AS user, __source."hash" AS "hash", __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer
+        "#);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_null_ordering_and_boolean_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let mut queries = vec![];
+        for data_source in [DataSource::Postgres, DataSource::MySQL, DataSource::MSSQL] {
+            let manifest = ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_name", "varchar").build())
+                        .column(ColumnBuilder::new("c_nationkey", "int").build())
+                        .build(),
+                )
+                .data_source(data_source)
+                .build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::new()),
+                Mode::Unparse,
+            )?);
+            for sql in [
+                "select c_name, true as flag from customer order by c_nationkey",
+                "select c_name, true as flag from customer order by c_nationkey desc",
+                "select c_name, true as flag from customer order by c_nationkey nulls first",
+            ] {
+                let actual = transform_sql_with_ctx(
+                    &ctx,
+                    Arc::clone(&analyzed_mdl),
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await?;
+                queries.push(format!("{data_source}: {actual}"));
+            }
+        }
+        // MySQL and SQL Server sort the nulls first in the ascending order
+        assert_snapshot!(queries.join("\n"), @r"
+        POSTGRES: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY customer.c_nationkey ASC NULLS LAST
+        POSTGRES: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY customer.c_nationkey DESC NULLS FIRST
+        POSTGRES: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY customer.c_nationkey ASC NULLS FIRST
+        MYSQL: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY CASE WHEN customer.c_nationkey IS NULL THEN 1 ELSE 0 END ASC, customer.c_nationkey ASC
+        MYSQL: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY CASE WHEN customer.c_nationkey IS NULL THEN 0 ELSE 1 END ASC, customer.c_nationkey DESC
+        MYSQL: SELECT customer.c_name, true AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY customer.c_nationkey ASC
+        MSSQL: SELECT customer.c_name, 1 AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY CASE WHEN customer.c_nationkey IS NULL THEN 1 ELSE 0 END ASC, customer.c_nationkey ASC
+        MSSQL: SELECT customer.c_name, 1 AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY CASE WHEN customer.c_nationkey IS NULL THEN 0 ELSE 1 END ASC, customer.c_nationkey DESC
+        MSSQL: SELECT customer.c_name, 1 AS flag FROM (SELECT customer.c_name, customer.c_nationkey FROM (SELECT __source.c_name AS c_name, __source.c_nationkey AS c_nationkey FROM customer AS __source) AS customer) AS customer ORDER BY customer.c_nationkey ASC
+        ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_boolean_predicate_of_dialects() -> Result<()> {
+        let statements = [
+            "SELECT true AS flag FROM t WHERE true",
+            "SELECT CASE WHEN true THEN false ELSE true END AS v FROM t",
+            "SELECT a FROM t WHERE a = true AND false OR NOT (true)",
+            "SELECT t.a FROM t INNER JOIN u ON true",
+            "SELECT a FROM t GROUP BY a HAVING true",
+        ];
+        let normalize = |data_source: &DataSource| -> Result<String> {
+            let dialect = crate::mdl::dialect::WrenDialect::new(data_source);
+            let mut normalized = vec![];
+            for sql in statements {
+                let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+                dialect.normalize_statement(&mut statement);
+                normalized.push(statement.to_string());
+            }
+            Ok(normalized.join("\n"))
+        };
+        // the literal is a number in a value position and a comparison in a predicate position
+        for data_source in [DataSource::Oracle, DataSource::MSSQL] {
+            assert_snapshot!(normalize(&data_source)?, @r"
+            SELECT 1 AS flag FROM t WHERE 1 = 1
+            SELECT CASE WHEN 1 = 1 THEN 0 ELSE 1 END AS v FROM t
+            SELECT a FROM t WHERE a = 1 AND 1 = 0 OR NOT (1 = 1)
+            SELECT t.a FROM t INNER JOIN u ON 1 = 1
+            SELECT a FROM t GROUP BY a HAVING 1 = 1
+            ");
+        }
+        assert_eq!(normalize(&DataSource::Postgres)?, statements.join("\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_string_concat_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let mut queries = vec![];
+        for data_source in [
+            DataSource::Postgres,
+            DataSource::DuckDB,
+            DataSource::Oracle,
+            DataSource::MySQL,
+            DataSource::BigQuery,
+            DataSource::MSSQL,
+        ] {
+            let manifest = ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_name", "varchar").build())
+                        .column(ColumnBuilder::new("c_last_name", "varchar").build())
+                        .build(),
+                )
+                .data_source(data_source)
+                .build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::new()),
+                Mode::Unparse,
+            )?);
+            for sql in [
+                "select c_name || c_last_name from customer",
+                "select concat(c_name, c_last_name) from customer",
+            ] {
+                let actual = transform_sql_with_ctx(
+                    &ctx,
+                    Arc::clone(&analyzed_mdl),
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await?;
+                queries.push(format!("{data_source}: {actual}"));
+            }
+        }
+        // `||` returns null if either side is null and `concat` skips the nulls
+        assert_snapshot!(queries.join("\n"), @r"
+        POSTGRES: SELECT customer.c_name || customer.c_last_name FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        POSTGRES: SELECT concat(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        DUCKDB: SELECT customer.c_name || customer.c_last_name FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        DUCKDB: SELECT concat(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        ORACLE: SELECT customer.c_name || customer.c_last_name FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        ORACLE: SELECT concat(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        MYSQL: SELECT CONCAT(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        MYSQL: SELECT concat_ws('', customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        BIGQUERY: SELECT CONCAT(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        BIGQUERY: SELECT concat(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        MSSQL: SELECT (customer.c_name + customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        MSSQL: SELECT concat(customer.c_name, customer.c_last_name) FROM (SELECT customer.c_name, customer.c_last_name FROM (SELECT __source.c_name AS c_name, __source.c_last_name AS c_last_name FROM customer AS __source) AS customer) AS customer
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filtered_measure_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = |data_source, filter: &str| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_orderstatus", "varchar").build())
+                        .column(ColumnBuilder::new("o_totalprice", "double").build())
+                        .build(),
+                )
+                .metric(
+                    MetricBuilder::new("revenue")
+                        .base_object("orders")
+                        .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                        .measure(
+                            ColumnBuilder::new("paid_total", "double")
+                                .expression("sum(o_totalprice)")
+                                .filter(filter)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .data_source(data_source)
+                .build()
+        };
+        let transform = |manifest| {
+            let ctx = ctx.clone();
+            async move {
+                let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                    manifest,
+                    Arc::new(HashMap::new()),
+                    Mode::Unparse,
+                )?);
+                transform_sql_with_ctx(
+                    &ctx,
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(HashMap::new()),
+                    "select o_custkey, paid_total from revenue",
+                )
+                .await
+            }
+        };
+
+        let native =
+            transform(manifest(DataSource::Postgres, "o_orderstatus = 'paid'")).await?;
+        assert_snapshot!(native, @"SELECT revenue.o_custkey, revenue.paid_total FROM (SELECT orders.o_custkey, sum(orders.o_totalprice) FILTER (WHERE orders.o_orderstatus = 'paid') AS paid_total FROM (SELECT orders.o_custkey, orders.o_orderstatus, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_orderstatus AS o_orderstatus, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey) AS revenue");
+
+        // the filter is moved into the aggregation if the dialect has no FILTER
+        let rewritten =
+            transform(manifest(DataSource::MySQL, "o_orderstatus = 'paid'")).await?;
+        assert_snapshot!(rewritten, @"SELECT revenue.o_custkey, revenue.paid_total FROM (SELECT orders.o_custkey, sum(CASE WHEN orders.o_orderstatus = 'paid' THEN orders.o_totalprice END) AS paid_total FROM (SELECT orders.o_custkey, orders.o_orderstatus, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_orderstatus AS o_orderstatus, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey) AS revenue");
+
+        match transform(manifest(DataSource::Postgres, "o_status = 'paid'")).await {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The filter of the measure paid_total of metric revenue \
+                references o_status, which isn't a column of model orders"
+            ),
+            Ok(sql) => panic!("should be error: {sql}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_sample_of_dialects() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let transform = |data_source, sql: &'static str| {
+            let ctx = ctx.clone();
+            async move {
+                let manifest = ManifestBuilder::new()
+                    .catalog("wren")
+                    .schema("test")
+                    .model(
+                        ModelBuilder::new("customer")
+                            .table_reference("customer")
+                            .column(ColumnBuilder::new("c_custkey", "int").build())
+                            .build(),
+                    )
+                    .data_source(data_source)
+                    .build();
+                let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                    manifest,
+                    Arc::new(HashMap::new()),
+                    Mode::Unparse,
+                )?);
+                transform_sql_with_ctx(
+                    &ctx,
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await
+            }
+        };
+        let sql = "select c_custkey from customer tablesample system (10)";
+        let actual = transform(DataSource::Postgres, sql).await?;
+        assert_snapshot!(actual, @"SELECT customer.c_custkey FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source TABLESAMPLE SYSTEM (10)) AS customer) AS customer");
+
+        match transform(DataSource::MySQL, sql).await {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: TABLESAMPLE is unsupported for the data source MYSQL"
+            ),
+            Ok(sql) => panic!("should be error: {sql}"),
+        }
+        let sql = "select a.c_custkey from customer a tablesample system (10) \
+            join customer b on a.c_custkey = b.c_custkey";
+        match transform(DataSource::Postgres, sql).await {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The table customer is sampled by TABLESAMPLE in some references only"
+            ),
+            Ok(sql) => panic!("should be error: {sql}"),
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_unnest_as_table_factor() -> Result<()> {
         let ctx = create_wren_ctx(None);
@@ -1247,11 +3212,87 @@ mod test {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_simplify_timestamp() -> Result<()> {
-        let ctx = create_wren_ctx(None);
-        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::default());
-        let sql = "select timestamp '2011-01-01 18:00:00 +08:00'";
+    #[tokio::test]
+    async fn test_simplify_timestamp() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::default());
+        let sql = "select timestamp '2011-01-01 18:00:00 +08:00'";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 10:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00 +08:00\"\")\"");
+
+        let sql = "select timestamp '2011-01-01 18:00:00 Asia/Taipei'";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 10:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00 Asia/Taipei\"\")\"");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_properties() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .add_row_level_access_control(
+                        "own_customer",
+                        vec![SessionProperty::new_required("session_user")],
+                        "c_custkey = @session_user",
+                    )
+                    .build(),
+            )
+            .default_properties(HashMap::from([
+                ("x-wren-timezone".to_string(), "+08:00".to_string()),
+                ("session_user".to_string(), "1".to_string()),
+            ]))
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::new()),
+            Mode::Unparse,
+        )?);
+
+        let sql = "select timestamp with time zone '2011-01-01 18:00:00'";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 10:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00\"\")\"");
+        // the property of the call overrides the default
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::from([(
+                "x-wren-timezone".to_string(),
+                Some("+09:00".to_string()),
+            )])),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 09:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00\"\")\"");
+
+        let sql = "select c_custkey from customer";
         let actual = transform_sql_with_ctx(
             &ctx,
             Arc::clone(&analyzed_mdl),
@@ -1260,18 +3301,107 @@ mod test {
             sql,
         )
         .await?;
-        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 10:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00 +08:00\"\")\"");
-
-        let sql = "select timestamp '2011-01-01 18:00:00 Asia/Taipei'";
+        assert!(actual.contains("WHERE customer.c_custkey = 1"), "{actual}");
         let actual = transform_sql_with_ctx(
             &ctx,
             Arc::clone(&analyzed_mdl),
             &[],
-            Arc::new(HashMap::new()),
+            Arc::new(HashMap::from([(
+                "session_user".to_string(),
+                Some("2".to_string()),
+            )])),
             sql,
         )
         .await?;
-        assert_snapshot!(actual, @"SELECT CAST('2011-01-01 10:00:00' AS TIMESTAMP) AS \"Utf8(\"\"2011-01-01 18:00:00 Asia/Taipei\"\")\"");
+        assert!(actual.contains("WHERE customer.c_custkey = 2"), "{actual}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_interned_qualified_references() {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let wren_mdl = WrenMDL::new(manifest);
+        let tables: Vec<_> = wren_mdl
+            .qualified_references
+            .keys()
+            .filter_map(|column| column.relation.as_ref())
+            .collect();
+        assert_eq!(tables.len(), 2);
+        // the columns of the model share the interned names of their table reference
+        assert!(std::ptr::eq(
+            tables[0].table().as_ptr(),
+            tables[1].table().as_ptr()
+        ));
+        assert!(std::ptr::eq(
+            tables[0].catalog().unwrap().as_ptr(),
+            tables[1].catalog().unwrap().as_ptr()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_session_key() -> Result<()> {
+        let manifest_of = |condition: &str, property: &str| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .declared_session_key("session_region")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", "int").build())
+                        .column(ColumnBuilder::new("c_region", "varchar").build())
+                        .add_row_level_access_control(
+                            "own_region",
+                            vec![SessionProperty::new_required(property)],
+                            condition,
+                        )
+                        .build(),
+                )
+                .build()
+        };
+
+        // the typo'd key never matches any session property, so the manifest is rejected
+        let manifest = manifest_of("c_region = @session_regoin", "session_regoin");
+        match AnalyzedWrenMDL::analyze(manifest, Arc::new(HashMap::new()), Mode::Unparse)
+        {
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: The session property @session_regoin is used for `own_region` rule, but not declared in the manifest"
+            ),
+            _ => panic!("Expected error"),
+        }
+
+        let manifest = manifest_of("c_region = @session_region", "session_region");
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::new()),
+            Mode::Unparse,
+        )?);
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            &[],
+            Arc::new(HashMap::from([(
+                "session_region".to_string(),
+                Some("'asia'".to_string()),
+            )])),
+            "select c_custkey from customer",
+        )
+        .await?;
+        assert!(
+            actual.contains("WHERE customer.c_region = 'asia'"),
+            "{actual}"
+        );
         Ok(())
     }
 
@@ -1443,15 +3573,391 @@ mod test {
             Mode::LocalRuntime,
         )
         .await?;
-        let sql = r#"select arrow_typeof(timestamp_col), arrow_typeof(timestamptz_col) from wren.test.timestamp_table limit 1"#;
-        let result = ctx.sql(sql).await?.collect().await?;
-        assert_snapshot!(batches_to_string(&result), @r#"
-        +---------------------------------------------+-----------------------------------------------+
-        | arrow_typeof(timestamp_table.timestamp_col) | arrow_typeof(timestamp_table.timestamptz_col) |
-        +---------------------------------------------+-----------------------------------------------+
-        | Timestamp(Nanosecond, None)                 | Timestamp(Nanosecond, Some("UTC"))            |
-        +---------------------------------------------+-----------------------------------------------+
-        "#);
+        let sql = r#"select arrow_typeof(timestamp_col), arrow_typeof(timestamptz_col) from wren.test.timestamp_table limit 1"#;
+        let result = ctx.sql(sql).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&result), @r#"
+        +---------------------------------------------+-----------------------------------------------+
+        | arrow_typeof(timestamp_table.timestamp_col) | arrow_typeof(timestamp_table.timestamptz_col) |
+        +---------------------------------------------+-----------------------------------------------+
+        | Timestamp(Nanosecond, None)                 | Timestamp(Nanosecond, Some("UTC"))            |
+        +---------------------------------------------+-----------------------------------------------+
+        "#);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_column_default_value() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new("c_region", "varchar")
+                            .default_value("'unknown'")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let actual = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "select c_custkey, c_region from customer",
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT customer.c_custkey, customer.c_region FROM (SELECT customer.c_custkey, customer.c_region FROM (SELECT __source.c_custkey AS c_custkey, coalesce(__source.c_region, 'unknown') AS c_region FROM customer AS __source) AS customer) AS customer");
+
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(
+                        ColumnBuilder::new("c_custkey", "int")
+                            .default_value("'abc'")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        match AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        ) {
+            Err(e) => {
+                assert_snapshot!(e.to_string(), @"Error during planning: The default value 'abc' of column c_custkey doesn't match the column type int")
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_plan_nodes() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("v1")
+                    .statement("select * from customer")
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("v2")
+                    .statement("select * from v1 union all select * from v1")
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("v3")
+                    .statement("select * from v2 union all select * from v2")
+                    .build(),
+            )
+            .build();
+        let transform = |max_plan_nodes: usize, properties: HashMap<_, _>| {
+            let manifest = manifest.clone();
+            async move {
+                let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+                    manifest,
+                    &AnalyzeOptions::new().with_max_plan_nodes(max_plan_nodes),
+                )?);
+                transform_sql_with_ctx(
+                    &create_wren_ctx(None),
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(properties),
+                    "select * from v3",
+                )
+                .await
+            }
+        };
+
+        let _ = transform(1000, HashMap::new()).await?;
+
+        // the session properties can't lift the limit of the options
+        let properties = HashMap::from([(
+            "x-wren-max-plan-nodes".to_string(),
+            Some("1000".to_string()),
+        )]);
+        match transform(10, properties).await {
+            Err(e) => {
+                let root = e.find_root();
+                assert!(matches!(root, DataFusionError::ResourcesExhausted(_)));
+                assert_snapshot!(root.to_string(), @"Resources exhausted: The expanded plan exceeds the limit of 10 nodes");
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_analyses() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let limiter = Arc::new(ConcurrencyLimiter::new(1, Some(1)));
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new().with_concurrency_limiter(Arc::clone(&limiter)),
+        )?);
+        let transform = || {
+            let analyzed_mdl = Arc::clone(&analyzed_mdl);
+            async move {
+                transform_sql_with_ctx(
+                    &create_wren_ctx(None),
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(HashMap::new()),
+                    "select c_custkey from customer",
+                )
+                .await
+            }
+        };
+
+        // hold the only permit
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let running = {
+            let limiter = Arc::clone(&limiter);
+            tokio::spawn(async move {
+                limiter
+                    .run(async move {
+                        started_tx.send(()).unwrap();
+                        release_rx.await.unwrap();
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        started_rx.await.unwrap();
+
+        // the transformation beyond the limit waits for the permit
+        let waiting = transform();
+        tokio::pin!(waiting);
+        tokio::select! {
+            biased;
+            _ = &mut waiting => panic!("the transformation should wait for the permit"),
+            _ = async {
+                while limiter.queued() == 0 {
+                    tokio::task::yield_now().await;
+                }
+            } => {}
+        }
+        // and the one beyond the queue is rejected
+        match transform().await {
+            Err(DataFusionError::ResourcesExhausted(message)) => assert_snapshot!(
+                message,
+                @"Too many concurrent analyses. 1 are running and 1 are queued"
+            ),
+            other => panic!("expected resources exhausted, but got {other:?}"),
+        }
+
+        release_tx.send(()).unwrap();
+        running.await.unwrap()?;
+        assert_snapshot!(
+            waiting.await?,
+            @"SELECT customer.c_custkey FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source) AS customer) AS customer"
+        );
+        assert_eq!(limiter.queued(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_tables() -> Result<()> {
+        let test_data: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+                .iter()
+                .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path())?;
+        let mdl = match serde_json::from_str::<Manifest>(&mdl_json) {
+            Ok(mdl) => mdl,
+            Err(e) => return not_impl_err!("Failed to parse mdl json: {}", e),
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            mdl,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let tables = analyzed_mdl.list_tables(Arc::new(HashMap::new())).await?;
+        let expected_columns = vec![
+            ("c_custkey".to_string(), DataType::Int32),
+            ("c_name".to_string(), DataType::Utf8),
+            ("custkey_plus".to_string(), DataType::Int32),
+        ];
+        let customer = tables.iter().find(|t| t.name == "customer").unwrap();
+        assert_eq!(customer.kind, TableKind::Model);
+        assert_eq!(customer.columns, expected_columns);
+        let customer_view = tables.iter().find(|t| t.name == "customer_view").unwrap();
+        assert_eq!(customer_view.kind, TableKind::View);
+        assert_eq!(customer_view.columns, expected_columns);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_with_limit() -> Result<()> {
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::default());
+        let stream = execute_stream_with_limit(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            Arc::new(HashMap::new()),
+            "select value from generate_series(1, 10000)",
+            1024,
+        )
+        .await?;
+        let batches = collect(stream).await?;
+        assert_eq!(batches.len(), 10);
+        assert!(batches.iter().all(|batch| batch.num_rows() <= 1024));
+        assert_eq!(
+            batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+            10000
+        );
+
+        let result = execute_stream_with_limit(
+            &create_wren_ctx(None),
+            Arc::new(AnalyzedWrenMDL::default()),
+            Arc::new(HashMap::new()),
+            "select 1",
+            0,
+        )
+        .await;
+        match result {
+            Err(e) => {
+                assert_snapshot!(e.to_string(), @"Error during planning: The batch size should be greater than 0")
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_with_preview_limit() -> Result<()> {
+        let sql = "select value from generate_series(1, 10000)";
+        let count_rows = |options: ExecuteOptions| async move {
+            let stream = execute_stream_with_options(
+                &create_wren_ctx(None),
+                Arc::new(AnalyzedWrenMDL::default()),
+                Arc::new(HashMap::new()),
+                sql,
+                &options,
+            )
+            .await?;
+            let batches = collect(stream).await?;
+            Ok::<_, DataFusionError>(
+                batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+            )
+        };
+        let options = ExecuteOptions::new()
+            .with_batch_size(1024)
+            .with_max_rows(5000);
+        assert_eq!(count_rows(options).await?, 5000);
+        // the preview is capped by the preview limit under the max rows
+        assert_eq!(count_rows(options.with_preview_limit(100)).await?, 100);
+        // and by the max rows if it's smaller
+        assert_eq!(
+            count_rows(
+                ExecuteOptions::new()
+                    .with_max_rows(50)
+                    .with_preview_limit(100)
+            )
+            .await?,
+            50
+        );
+        assert_eq!(count_rows(ExecuteOptions::new()).await?, 10000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_stream_with_qualify() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        ctx.register_batch("customer", customer())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let registers =
+            HashMap::from([("datafusion.public.customer".to_string(), provider)]);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+        let stream = execute_stream_with_options(
+            &ctx,
+            analyzed_mdl,
+            Arc::new(HashMap::new()),
+            "select c_custkey, c_name from wren.test.customer \
+            qualify row_number() over (order by c_custkey desc) <= 2 order by c_custkey",
+            &ExecuteOptions::new(),
+        )
+        .await?;
+        let batches = collect(stream).await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-----------+--------+
+        | c_custkey | c_name |
+        +-----------+--------+
+        | 2         | Azki   |
+        | 3         | Ina    |
+        +-----------+--------+
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_fixed_clock() -> Result<()> {
+        // 2024-01-02T03:04:05Z
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_164_645);
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+            ManifestBuilder::new().build(),
+            &AnalyzeOptions::new().with_clock(Arc::new(FixedClock::new(now))),
+        )?);
+        let stream = execute_stream_with_options(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            Arc::new(HashMap::new()),
+            "select current_date as today, now() as now, \
+            date_trunc('month', now()) - interval '1 day' as last_month_end",
+            &ExecuteOptions::new(),
+        )
+        .await?;
+        let batches = collect(stream).await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +------------+----------------------+----------------------+
+        | today      | now                  | last_month_end       |
+        +------------+----------------------+----------------------+
+        | 2024-01-02 | 2024-01-02T03:04:05Z | 2023-12-31T00:00:00Z |
+        +------------+----------------------+----------------------+
+        ");
         Ok(())
     }
 
@@ -1969,18 +4475,510 @@ mod test {
             Arc::new(HashMap::default()),
             Mode::Unparse,
         )?);
-        let result = transform_sql_with_ctx(
-            &ctx,
-            Arc::clone(&analyzed_mdl),
-            &[],
-            Arc::new(HashMap::new()),
-            sql,
-        )
-        .await?;
-        assert_snapshot!(
-            result,
-            @r#"SELECT customer.c_custkey, customer.c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM "遠端".test."客戶" AS __source) AS customer) AS customer WHERE customer.c_custkey = 1"#
-        );
+        let result = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(
+            result,
+            @r#"SELECT customer.c_custkey, customer.c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM "遠端".test."客戶" AS __source) AS customer) AS customer WHERE customer.c_custkey = 1"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metric_in_view() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("revenue")
+                    .base_object("orders")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("total", "double")
+                            .expression("sum(o_totalprice)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("revenue_view")
+                    .statement("select o_custkey, total from wren.test.revenue")
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("revenue_all")
+                    .statement("select * from wren.test.revenue")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let transform = |sql| {
+            transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+        };
+        // the aggregation from the measure to the end of its GROUP BY
+        fn aggregation(sql: &str) -> &str {
+            let start = sql.find("sum(").expect("the measure should be expanded");
+            let group_by = start + sql[start..].find(" GROUP BY ").unwrap();
+            let end =
+                group_by + sql[group_by..].find(')').unwrap_or(sql.len() - group_by);
+            &sql[start..end]
+        }
+
+        let direct = transform("select o_custkey, total from revenue").await?;
+        assert!(
+            aggregation(&direct).contains("o_totalprice"),
+            "unexpected sql: {direct}"
+        );
+        for sql in [
+            "select o_custkey, total from revenue_view",
+            "select o_custkey, total from revenue_all",
+        ] {
+            let actual = transform(sql).await?;
+            assert_eq!(aggregation(&actual), aggregation(&direct), "{actual}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parameterized_view() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("orders_view")
+                    .statement(
+                        "select o_orderkey from wren.test.orders \
+                        where o_custkey > $threshold and o_orderkey < $max_key",
+                    )
+                    .parameter("threshold", None)
+                    .parameter("max_key", Some("1000"))
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let transform = |sql| {
+            transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+        };
+
+        let first = transform("select * from orders_view(threshold => 100)").await?;
+        assert_snapshot!(first, @"SELECT orders_view.o_orderkey FROM (SELECT orders.o_orderkey FROM (SELECT orders.o_orderkey, orders.o_custkey FROM (SELECT __source.o_orderkey AS o_orderkey, __source.o_custkey AS o_custkey FROM orders AS __source) AS orders) AS orders WHERE orders.o_custkey > 100 AND orders.o_orderkey < 1000) AS orders_view");
+        let second = transform(
+            "select v.o_orderkey from orders_view(threshold => 200, max_key => 10) v",
+        )
+        .await?;
+        assert_snapshot!(second, @"SELECT v.o_orderkey FROM (SELECT orders.o_orderkey FROM (SELECT orders.o_orderkey, orders.o_custkey FROM (SELECT __source.o_orderkey AS o_orderkey, __source.o_custkey AS o_custkey FROM orders AS __source) AS orders) AS orders WHERE orders.o_custkey > 200 AND orders.o_orderkey < 10) AS v");
+
+        let mut errors = vec![];
+        for sql in [
+            "select * from orders_view",
+            "select * from orders_view(max_key => 10)",
+            "select * from orders_view(threshold => 1, unknown => 2)",
+            "select * from orders_view(1)",
+            "select * from orders_view(threshold => o_custkey)",
+            "select * from orders_view(threshold => (select 1))",
+        ] {
+            match transform(sql).await {
+                Err(e) => errors.push(e.to_string()),
+                Ok(_) => panic!("{sql} should be error"),
+            }
+        }
+        assert_snapshot!(errors.join("\n"), @r"
+        Error during planning: The parameter threshold of view orders_view is required
+        Error during planning: The parameter threshold of view orders_view is required
+        Error during planning: The view orders_view has no parameter unknown
+        Error during planning: The arguments of view orders_view should be passed by name, e.g. orders_view(name => value)
+        Error during planning: The argument threshold of view orders_view should be a literal, but got o_custkey
+        Error during planning: The argument threshold of view orders_view should be a literal, but got (SELECT 1)
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_parameterized_view() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        ctx.register_batch("orders", orders())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("orders")
+            .await?
+            .unwrap();
+        let registers =
+            HashMap::from([("datafusion.public.orders".to_string(), provider)]);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("datafusion.public.orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_custkey", "bigint").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("orders_view")
+                    .statement(
+                        "select o_orderkey from wren.test.orders \
+                        where o_custkey > $threshold order by o_orderkey",
+                    )
+                    .parameter("threshold", None)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+        let options = ExecuteOptions::new();
+        let execute = |sql| {
+            execute_stream_with_options(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                Arc::new(HashMap::new()),
+                sql,
+                &options,
+            )
+        };
+
+        let batches = collect(
+            execute("select * from wren.test.orders_view(threshold => 1)").await?,
+        )
+        .await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +------------+
+        | o_orderkey |
+        +------------+
+        | 2          |
+        | 3          |
+        +------------+
+        ");
+        match execute("select * from wren.test.orders_view").await {
+            Err(e) => {
+                assert_snapshot!(e.to_string(), @"Error during planning: The parameter threshold of view orders_view is required")
+            }
+            _ => panic!("should be error"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_referencing_parameterized_view() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("first_view")
+                    .statement(
+                        "select o_orderkey from wren.test.second_view(threshold => $threshold)",
+                    )
+                    .parameter("threshold", None)
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("second_view")
+                    .statement(
+                        "select o_orderkey from wren.test.first_view(threshold => $threshold)",
+                    )
+                    .parameter("threshold", None)
+                    .build(),
+            )
+            .build();
+        let wren_mdl = WrenMDL::new(manifest);
+        let mut statement = Parser::parse_sql(
+            &GenericDialect {},
+            "select * from wren.test.first_view(threshold => 1)",
+        )?
+        .remove(0);
+        let e = crate::mdl::view_parameter::expand_parameterized_views(
+            &wren_mdl,
+            &mut statement,
+        )
+        .unwrap_err();
+        assert_snapshot!(
+            e,
+            @"Error during planning: The view first_view references itself: first_view -> second_view -> first_view"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metric_having() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("revenue")
+                    .base_object("orders")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("total", "double")
+                            .expression("sum(o_totalprice)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let transform = |sql| {
+            transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+        };
+
+        // the measure is substituted by the expanded aggregation
+        let actual =
+            transform("select o_custkey, total from revenue having total > 100").await?;
+        assert_snapshot!(actual, @"SELECT revenue.o_custkey, revenue.total FROM (SELECT orders.o_custkey, sum(orders.o_totalprice) AS total FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey HAVING sum(orders.o_totalprice) > 100) AS revenue");
+        let actual = transform(
+            "select r.o_custkey, r.total from wren.test.revenue r having r.total > 100 and o_custkey > 1",
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT r.o_custkey, r.total FROM (SELECT orders.o_custkey, sum(orders.o_totalprice) AS total FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey HAVING sum(orders.o_totalprice) > 100 AND orders.o_custkey > 1) AS r");
+
+        // every group is a single row of the metric, so the aggregation of the measure is
+        // the measure itself
+        let actual = transform(
+            "select o_custkey, sum(total) from revenue group by o_custkey having sum(total) > 100",
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT revenue.o_custkey, sum(revenue.total) FROM (SELECT orders.o_custkey, sum(orders.o_totalprice) AS total FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey HAVING sum(orders.o_totalprice) > 100) AS revenue GROUP BY revenue.o_custkey");
+
+        // the other aggregations over the metric rows keep their HAVING
+        let actual = transform(
+            "select o_custkey, sum(total) from revenue group by o_custkey having count(*) > 1",
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT revenue.o_custkey, sum(revenue.total) FROM (SELECT orders.o_custkey, sum(orders.o_totalprice) AS total FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders GROUP BY orders.o_custkey) AS revenue GROUP BY revenue.o_custkey HAVING count(*) > 1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_cache() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("plan_cache")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let plan_cache = Arc::new(PlanCache::new(64).with_metadata_tracking());
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new().with_plan_cache(Arc::clone(&plan_cache)),
+        )?);
+        let transform = |sql| {
+            transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+        };
+        let first = transform("select c_name from customer where c_custkey = 1").await?;
+        let second =
+            transform("SELECT c_name\n  FROM customer\n  WHERE c_custkey = 1").await?;
+        assert_eq!(first, second);
+
+        let entries = plan_cache.entry_metadata();
+        // the query is planned once and the equivalent one reuses the plan
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].hit_count, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parse_cache() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let parse_cache = Arc::new(ParseCache::new(64).with_lfu_eviction());
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new().with_parse_cache(Arc::clone(&parse_cache)),
+        )?);
+        let transform = |sql| {
+            transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+        };
+        let hot = "select c_custkey from customer where c_custkey = 970";
+        let other = "select c_custkey from customer where c_custkey = 971";
+        let first = transform(hot).await?;
+        assert_eq!(transform(hot).await?, first);
+        assert_eq!(transform(hot).await?, first);
+        transform(other).await?;
+
+        let hit_count = |sql: &str| {
+            parse_cache
+                .entry_metadata()
+                .into_iter()
+                .find(|entry| entry.key.1 == sql)
+                .map(|entry| entry.hit_count)
+        };
+        // the hot query is parsed once and the distinct one is parsed on its own
+        assert_eq!(hit_count(hot), Some(2));
+        assert_eq!(hit_count(other), Some(0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rlac_in_subquery() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_nationkey", "int").build())
+                    .add_row_level_access_control(
+                        "nation",
+                        vec![SessionProperty::new_required("session_nation")],
+                        "c_nationkey = @session_nation",
+                    )
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        // the protected model is only referenced inside the subqueries
+        let sqls = [
+            "SELECT o_orderkey FROM orders WHERE EXISTS \
+            (SELECT 1 FROM customer WHERE c_custkey = o_custkey)",
+            "SELECT o_orderkey FROM orders WHERE o_custkey IN (SELECT c_custkey FROM customer)",
+            "SELECT o_orderkey, (SELECT max(c_custkey) FROM customer) FROM orders",
+        ];
+        for sql in sqls {
+            let headers =
+                build_headers(&[("session_nation".to_string(), Some("1".to_string()))]);
+            let actual = transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(headers),
+                sql,
+            )
+            .await?;
+            assert!(
+                actual.contains("customer.c_nationkey = 1"),
+                "the rlac filter is missing: {actual}"
+            );
+
+            // the required property is still checked in the subquery
+            match transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await
+            {
+                Err(e) => assert!(
+                    e.to_string().contains(
+                        "session property session_nation is required for `nation` rule"
+                    ),
+                    "unexpected error: {e}"
+                ),
+                Ok(sql) => panic!("Expected error, but got {sql}"),
+            }
+        }
         Ok(())
     }
 
@@ -2709,6 +5707,133 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_analyze_with_options() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new("c_name", "string")
+                            .column_level_access_control(
+                                "cls rule",
+                                vec![SessionProperty::new_required("session_level")],
+                                ColumnLevelOperator::Equals,
+                                "1",
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let column_names = |analyzed_mdl: &AnalyzedWrenMDL| {
+            analyzed_mdl
+                .wren_mdl()
+                .get_table("customer")
+                .map(|table| {
+                    table
+                        .schema()
+                        .fields()
+                        .iter()
+                        .map(|field| field.name().clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        let properties = Arc::new(build_headers(&[(
+            "session_level".to_string(),
+            Some("0".to_string()),
+        )]));
+
+        // the column denied by the properties isn't registered
+        let options = AnalyzeOptions::new().with_properties(Arc::clone(&properties));
+        let analyzed_mdl =
+            AnalyzedWrenMDL::analyze_with_options(manifest.clone(), &options)?;
+        assert_eq!(column_names(&analyzed_mdl), vec!["c_custkey"]);
+
+        // the permission analyze mode ignores the column level access control
+        let options = options.with_mode(Mode::PermissionAnalyze);
+        assert_eq!(options.mode(), Mode::PermissionAnalyze);
+        let analyzed_mdl =
+            AnalyzedWrenMDL::analyze_with_options(manifest.clone(), &options)?;
+        assert_eq!(column_names(&analyzed_mdl), vec!["c_custkey", "c_name"]);
+
+        let analyzed_mdl = AnalyzedWrenMDL::analyze_with_options(
+            manifest,
+            &AnalyzeOptions::new()
+                .with_properties(properties)
+                .with_register_table(
+                    "orders",
+                    analyzed_mdl.wren_mdl().get_table("customer").unwrap(),
+                ),
+        )?;
+        assert!(analyzed_mdl.wren_mdl().get_table("orders").is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analyze_options_of_transformation() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(MySQL)
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let options = AnalyzeOptions::new()
+            .with_data_source(DataSource::Postgres)
+            .with_sql_length_limit(SqlLengthLimit::new(60, SqlLengthUnit::Chars));
+        assert_eq!(options.data_source(), Some(DataSource::Postgres));
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_options(manifest, &options)?);
+        assert_eq!(
+            analyzed_mdl.wren_mdl().data_source(),
+            Some(DataSource::Postgres)
+        );
+
+        // the placeholders follow the data source of the options instead of MySQL
+        let (sql, values) = transform_sql_parameterized(
+            &create_wren_ctx(None),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "select c_custkey from customer where c_custkey > 10",
+        )
+        .await?;
+        assert_snapshot!(format!("{sql}\n{values:?}"), @r"
+        SELECT customer.c_custkey FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source) AS customer) AS customer WHERE customer.c_custkey > $1
+        [Int64(10)]
+        ");
+
+        // the limit of the options can't be lifted by the session properties
+        let result = transform_sql_with_ctx(
+            &create_wren_ctx(None),
+            analyzed_mdl,
+            &[],
+            Arc::new(build_headers(&[(
+                "x-wren-max-sql-length".to_string(),
+                Some("1000".to_string()),
+            )])),
+            "select c_custkey from customer where c_custkey > 10 and c_custkey < 1000",
+        )
+        .await;
+        match result {
+            Err(DataFusionError::ResourcesExhausted(message)) => assert_snapshot!(
+                message,
+                @"The SQL has 72 chars, which exceeds the limit of 60 chars"
+            ),
+            other => panic!("expected resources exhausted, but got {other:?}"),
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_clac_with_required_properties() -> Result<()> {
         let ctx = create_wren_ctx(None);
@@ -3369,6 +6494,66 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_select_star_except() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        // BigQuery supports `* EXCEPT`, but the wildcard is resolved against the view's
+        // expanded columns while planning, so every dialect gets the explicit projection.
+        for data_source in [DataSource::BigQuery, DataSource::MySQL] {
+            let manifest = ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", "int").build())
+                        .column(ColumnBuilder::new("c_name", "varchar").build())
+                        .column(ColumnBuilder::new("c_phone", "varchar").build())
+                        .build(),
+                )
+                .view(
+                    ViewBuilder::new("customer_view")
+                        .statement("select * from wren.test.customer")
+                        .build(),
+                )
+                .data_source(data_source)
+                .build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::default()),
+                Mode::Unparse,
+            )?);
+            for sql in [
+                "select * except(c_name) from customer_view",
+                "select * exclude(c_name) from customer_view",
+                "select * except(c_name) from customer",
+            ] {
+                let actual = transform_sql_with_ctx(
+                    &ctx,
+                    Arc::clone(&analyzed_mdl),
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await?;
+                let projection = actual
+                    .split_once(" FROM ")
+                    .map(|(s, _)| s)
+                    .unwrap_or(&actual);
+                assert!(
+                    projection.contains("c_custkey") && projection.contains("c_phone"),
+                    "unexpected sql: {actual}"
+                );
+                assert!(!projection.contains("c_name"), "unexpected sql: {actual}");
+                assert!(
+                    !actual.contains("EXCEPT") && !actual.contains("EXCLUDE"),
+                    "unexpected sql: {actual}"
+                );
+            }
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_date_diff_bigquery() -> Result<()> {
         let ctx = create_wren_ctx(None);
@@ -3839,6 +7024,179 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_transform_statement() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_items", "array<string>").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        for sql in [
+            "select o_orderkey from orders where o_custkey = 1",
+            "select o_custkey, count(*) from wren.test.orders group by 1 order by 2 desc",
+            "SELECT o.o_orderkey, t.item FROM orders o CROSS JOIN LATERAL unnest(o.o_items) AS t(item)",
+        ] {
+            let expected = transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            let statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+            let actual = transform_statement_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                statement,
+            )
+            .await?;
+            assert_eq!(actual, expected);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lateral() -> Result<()> {
+        let ctx = create_wren_ctx(None);
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_items", "array<string>").build())
+                    .add_row_level_access_control(
+                        "session_order",
+                        vec![SessionProperty::new_required("session_order")],
+                        "o_orderkey = @session_order",
+                    )
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .build(),
+            )
+            .build();
+        let headers = Arc::new(HashMap::from([(
+            "session_order".to_string(),
+            Some("1".to_string()),
+        )]));
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::clone(&headers),
+            Mode::Unparse,
+        )?);
+
+        let sql = "SELECT o.o_orderkey, t.item FROM orders o CROSS JOIN LATERAL unnest(o.o_items) AS t(item)";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::clone(&headers),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT o.o_orderkey, t.item FROM (SELECT orders.o_orderkey, orders.o_items FROM (SELECT orders.o_orderkey, orders.o_items FROM (SELECT __source.o_orderkey AS o_orderkey, __source.o_items AS o_items FROM orders AS __source) AS orders) AS orders WHERE orders.o_orderkey = 1) AS o CROSS JOIN LATERAL (SELECT UNNEST(o.o_items) AS item) AS t (item)");
+
+        // the column of orders referenced only by the lateral subquery is required
+        let sql = "SELECT o.o_orderkey, c.c_name FROM orders o CROSS JOIN LATERAL \
+            (SELECT c_name FROM customer WHERE c_custkey = o.o_custkey) AS c";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::clone(&headers),
+            sql,
+        )
+        .await?;
+        assert_snapshot!(actual, @"SELECT o.o_orderkey, c.c_name FROM (SELECT orders.o_orderkey, orders.o_custkey FROM (SELECT orders.o_orderkey, orders.o_custkey FROM (SELECT __source.o_orderkey AS o_orderkey, __source.o_custkey AS o_custkey FROM orders AS __source) AS orders) AS orders WHERE orders.o_orderkey = 1) AS o CROSS JOIN LATERAL (SELECT customer.c_name FROM (SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, __source.c_name AS c_name FROM customer AS __source) AS customer) AS customer WHERE customer.c_custkey = o.o_custkey) AS c");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_qualify() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+            Mode::Unparse,
+        )?);
+        let execute = |sql: &'static str| {
+            let analyzed_mdl = Arc::clone(&analyzed_mdl);
+            async move {
+                let actual = transform_sql_with_ctx(
+                    &create_wren_ctx(None),
+                    analyzed_mdl,
+                    &[],
+                    Arc::new(HashMap::new()),
+                    sql,
+                )
+                .await?;
+                assert!(
+                    !actual.to_uppercase().contains("QUALIFY ROW_NUMBER"),
+                    "{actual}"
+                );
+                let ctx = create_wren_ctx(None);
+                ctx.register_batch("customer", customer())?;
+                let batches = ctx.sql(&actual).await?.collect().await?;
+                Ok::<_, DataFusionError>(batches_to_string(&batches))
+            }
+        };
+
+        let qualified = execute(
+            "select c.c_custkey, c_name from customer c \
+            qualify row_number() over (order by c_custkey desc) <= 2 order by c.c_custkey",
+        )
+        .await?;
+        let expected = execute(
+            "select c_custkey, c_name from (select c_custkey, c_name, \
+            row_number() over (order by c_custkey desc) as rn from customer) \
+            where rn <= 2 order by c_custkey",
+        )
+        .await?;
+        assert_eq!(qualified, expected);
+        assert_snapshot!(qualified, @r"
+        +-----------+--------+
+        | c_custkey | c_name |
+        +-----------+--------+
+        | 2         | Azki   |
+        | 3         | Ina    |
+        +-----------+--------+
+        ");
+        Ok(())
+    }
+
     /// Return a RecordBatch with made up data about customer
     fn customer() -> RecordBatch {
         let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
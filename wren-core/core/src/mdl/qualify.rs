@@ -0,0 +1,98 @@
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::ast::{
+    ExcludeSelectItem, Expr, GroupByExpr, Ident, OrderByKind, Query, SelectItem, SetExpr,
+    Statement, TableAlias, TableFactor, TableWithJoins, VisitMut, VisitorMut,
+    WildcardAdditionalOptions,
+};
+
+/// The name of the column holding the `QUALIFY` condition and of the subquery computing it
+const QUALIFY_NAME: &str = "__qualify";
+
+/// Rewrite `QUALIFY` to a windowed subquery filtered by the outer `WHERE`.
+///
+/// `QUALIFY` filters the rows by the window functions after they're evaluated, e.g.
+/// `SELECT a, b FROM t QUALIFY row_number() OVER (PARTITION BY a ORDER BY b) = 1`. The
+/// condition is evaluated as a column of the subquery, and the outer query keeps the rows
+/// where it's true, e.g. `SELECT * EXCLUDE (__qualify) FROM (SELECT a, b,
+/// (row_number() OVER (PARTITION BY a ORDER BY b) = 1) AS __qualify FROM t) AS __qualify
+/// WHERE __qualify`.
+///
+/// `DISTINCT` is applied after `QUALIFY`, so it's moved to the outer query with `TOP`,
+/// `ORDER BY` and `LIMIT`. The outer query only sees the selected columns by their names,
+/// so the qualifiers of the `ORDER BY` columns are dropped, and the condition can't
+/// reference the aliases of the projection.
+pub(crate) fn rewrite_qualify(statement: &mut Statement) {
+    let _ = statement.visit(&mut QualifyRewriter);
+}
+
+struct QualifyRewriter;
+
+impl VisitorMut for QualifyRewriter {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        let SetExpr::Select(select) = query.body.as_mut() else {
+            return ControlFlow::Continue(());
+        };
+        let Some(condition) = select.qualify.take() else {
+            return ControlFlow::Continue(());
+        };
+        let mut inner_select = select.clone();
+        let distinct = inner_select.distinct.take();
+        inner_select.top = None;
+        inner_select.projection.push(SelectItem::ExprWithAlias {
+            expr: Expr::Nested(Box::new(condition)),
+            alias: Ident::new(QUALIFY_NAME),
+        });
+        let mut inner_query = query.clone();
+        inner_query.body = Box::new(SetExpr::Select(inner_select));
+        inner_query.with = None;
+        inner_query.order_by = None;
+        inner_query.limit_clause = None;
+        inner_query.fetch = None;
+
+        let SetExpr::Select(select) = query.body.as_mut() else {
+            unreachable!("the body is a select");
+        };
+        select.distinct = distinct;
+        select.projection = vec![SelectItem::Wildcard(WildcardAdditionalOptions {
+            opt_exclude: Some(ExcludeSelectItem::Single(Ident::new(QUALIFY_NAME))),
+            ..Default::default()
+        })];
+        select.from = vec![TableWithJoins {
+            relation: TableFactor::Derived {
+                lateral: false,
+                subquery: Box::new(inner_query),
+                alias: Some(TableAlias {
+                    name: Ident::new(QUALIFY_NAME),
+                    columns: vec![],
+                }),
+            },
+            joins: vec![],
+        }];
+        select.lateral_views = vec![];
+        select.prewhere = None;
+        select.selection = Some(Expr::Identifier(Ident::new(QUALIFY_NAME)));
+        select.group_by = GroupByExpr::Expressions(vec![], vec![]);
+        select.cluster_by = vec![];
+        select.distribute_by = vec![];
+        select.sort_by = vec![];
+        select.having = None;
+        select.named_window = vec![];
+        select.connect_by = None;
+
+        if let Some(order_by) = query.order_by.as_mut() {
+            if let OrderByKind::Expressions(exprs) = &mut order_by.kind {
+                for order_by_expr in exprs.iter_mut() {
+                    if let Expr::CompoundIdentifier(idents) = &order_by_expr.expr {
+                        if let Some(column) = idents.last() {
+                            order_by_expr.expr = Expr::Identifier(column.clone());
+                        }
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use datafusion::catalog::MemoryCatalogProviderList;
+use datafusion::common::{plan_err, Result};
+use datafusion::datasource::ViewTable;
+use datafusion::execution::SessionStateBuilder;
+use datafusion::prelude::SessionContext;
+
+use crate::mdl::context::{apply_wren_on_ctx, Mode};
+use crate::mdl::utils::quoted;
+use crate::mdl::AnalyzedWrenMDL;
+
+impl AnalyzedWrenMDL {
+    /// Register the models, metrics and views as DataFusion views in the context, so they
+    /// can be queried by plain DataFusion SQL, e.g. `ctx.sql("SELECT * FROM customer")`.
+    ///
+    /// The views are registered by their names in the default catalog and schema of the
+    /// context. Each one is the plan of the object with the models expanded for the local
+    /// runtime, so the source tables should be registered by [AnalyzedWrenMDL::analyze_with_tables].
+    /// The access control rules are applied without session properties. The parameterized
+    /// views are skipped because they can't be planned without the arguments.
+    ///
+    /// If a name is already registered in the context, nothing is registered.
+    pub async fn register_into(self: &Arc<Self>, ctx: &SessionContext) -> Result<()> {
+        let wren_mdl = self.wren_mdl();
+        let names: Vec<&str> = wren_mdl
+            .models()
+            .iter()
+            .map(|model| model.name())
+            .chain(wren_mdl.metrics().iter().map(|metric| metric.name()))
+            .chain(
+                wren_mdl
+                    .views()
+                    .iter()
+                    .filter(|view| view.parameters.is_empty())
+                    .map(|view| view.name()),
+            )
+            .collect();
+        for name in names.iter() {
+            if ctx.table_exist(*name)? {
+                return plan_err!(
+                    "The table {name} is already registered in the context"
+                );
+            }
+        }
+
+        // plan in a context with its own catalogs, so the catalog of the MDL isn't
+        // registered into the context of the caller
+        let planning_ctx = SessionContext::new_with_state(
+            SessionStateBuilder::new_from_existing(ctx.state())
+                .with_catalog_list(Arc::new(MemoryCatalogProviderList::new()))
+                .build(),
+        );
+        let wren_ctx = apply_wren_on_ctx(
+            &planning_ctx,
+            Arc::clone(self),
+            Arc::new(HashMap::new()),
+            Mode::LocalRuntime,
+        )
+        .await?;
+        for name in names {
+            let sql = format!(
+                "SELECT * FROM {}.{}.{}",
+                quoted(wren_mdl.catalog()),
+                quoted(wren_mdl.schema()),
+                quoted(name)
+            );
+            let plan = wren_ctx.state().create_logical_plan(&sql).await?;
+            let plan = wren_ctx.state().optimize(&plan)?;
+            ctx.register_table(name, Arc::new(ViewTable::new(plan, None)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use datafusion::arrow::array::{ArrayRef, Int32Array, RecordBatch, StringArray};
+    use datafusion::error::Result;
+    use datafusion::prelude::SessionContext;
+
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, ViewBuilder,
+    };
+    use crate::mdl::AnalyzedWrenMDL;
+
+    #[tokio::test]
+    async fn test_register_into() -> Result<()> {
+        let source = SessionContext::new();
+        source.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "c_custkey",
+                    Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "c_name",
+                    Arc::new(StringArray::from(vec!["Gura", "Ina", "Ame"])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let provider = source.table_provider("customer").await?;
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement(
+                        "select c_name from wren.test.customer where c_custkey > 1",
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_tables(
+            manifest,
+            HashMap::from([("datafusion.public.customer".to_string(), provider)]),
+        )?);
+
+        let ctx = SessionContext::new();
+        analyzed_mdl.register_into(&ctx).await?;
+        let batches = ctx
+            .sql("SELECT c_name FROM customer_view ORDER BY c_name")
+            .await?
+            .collect()
+            .await?;
+        let names: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| {
+                let names = batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                names
+                    .iter()
+                    .flatten()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(names, vec!["Ame", "Ina"]);
+        let count = ctx.sql("SELECT * FROM customer").await?.count().await?;
+        assert_eq!(count, 3);
+
+        match analyzed_mdl.register_into(&ctx).await {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The table customer is already registered in the context"
+            ),
+            Ok(_) => panic!("should be error"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use serde_json::Value;
+
+use crate::mdl::context::SessionPropertiesRef;
+
+/// A typed value of a session property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Display for SessionValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionValue::Null => write!(f, "null"),
+            SessionValue::Bool(b) => write!(f, "{b}"),
+            SessionValue::Int(i) => write!(f, "{i}"),
+            SessionValue::Float(v) => write!(f, "{v}"),
+            SessionValue::Str(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// The session properties with typed values. The keys are case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionProperties {
+    values: HashMap<String, SessionValue>,
+}
+
+impl SessionProperties {
+    /// Parse a JSON object into the session properties, e.g. `{"session_id": 1, "is_admin": true}`.
+    /// Only the scalar values are accepted. A nested array or object is rejected.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: Value = match serde_json::from_str(json) {
+            Ok(value) => value,
+            Err(e) => return plan_err!("Invalid session properties json: {e}"),
+        };
+        let Value::Object(object) = value else {
+            return plan_err!("The session properties should be a JSON object");
+        };
+        let values = object
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::Null => SessionValue::Null,
+                    Value::Bool(b) => SessionValue::Bool(b),
+                    Value::Number(n) => match n.as_i64() {
+                        Some(i) => SessionValue::Int(i),
+                        None => SessionValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+                    },
+                    Value::String(s) => SessionValue::Str(s),
+                    Value::Array(_) | Value::Object(_) => {
+                        return plan_err!(
+                            "The session property {key} should be a scalar value"
+                        )
+                    }
+                };
+                Ok((key.to_lowercase(), value))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { values })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SessionValue> {
+        self.values.get(&key.to_lowercase())
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            SessionValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            SessionValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            SessionValue::Str(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Convert to the string-based properties used by the transformation.
+    /// A null value becomes `None`.
+    pub fn to_properties_ref(&self) -> SessionPropertiesRef {
+        Arc::new(
+            self.values
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        SessionValue::Null => None,
+                        value => Some(value.to_string()),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mdl::session::{SessionProperties, SessionValue};
+    use datafusion::error::Result;
+
+    #[test]
+    fn test_from_json() -> Result<()> {
+        let properties = SessionProperties::from_json(
+            r#"{"Session_Id": 1, "is_admin": true, "region": "tw", "ratio": 0.5, "empty": null}"#,
+        )?;
+        assert_eq!(properties.len(), 5);
+        assert_eq!(properties.get_int("session_id"), Some(1));
+        assert_eq!(properties.get_bool("IS_ADMIN"), Some(true));
+        assert_eq!(properties.get_str("region"), Some("tw"));
+        assert_eq!(properties.get("ratio"), Some(&SessionValue::Float(0.5)));
+        assert_eq!(properties.get("empty"), Some(&SessionValue::Null));
+        // a value of the other type isn't converted
+        assert_eq!(properties.get_int("region"), None);
+        assert_eq!(properties.get_str("session_id"), None);
+        assert_eq!(properties.get_bool("missing"), None);
+
+        let properties_ref = properties.to_properties_ref();
+        assert_eq!(
+            properties_ref.get("session_id"),
+            Some(&Some("1".to_string()))
+        );
+        assert_eq!(
+            properties_ref.get("is_admin"),
+            Some(&Some("true".to_string()))
+        );
+        assert_eq!(properties_ref.get("empty"), Some(&None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json_invalid() {
+        for (json, expected) in [
+            (
+                "[1, 2]",
+                "Error during planning: The session properties should be a JSON object",
+            ),
+            (
+                r#"{"ids": [1, 2]}"#,
+                "Error during planning: The session property ids should be a scalar value",
+            ),
+        ] {
+            match SessionProperties::from_json(json) {
+                Err(e) => assert_eq!(e.to_string(), expected),
+                Ok(_) => panic!("should be error"),
+            }
+        }
+        assert!(SessionProperties::from_json("{").is_err());
+    }
+}
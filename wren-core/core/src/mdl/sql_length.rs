@@ -0,0 +1,113 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use datafusion::common::{plan_datafusion_err, Result};
+use datafusion::error::DataFusionError;
+
+/// The unit used to measure the length of the SQL.
+///
+/// [SqlLengthUnit::Bytes] counts the UTF-8 bytes, so a multibyte character like `測` counts as 3.
+/// [SqlLengthUnit::Chars] counts the characters, which matches the length shown by most UIs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SqlLengthUnit {
+    #[default]
+    Bytes,
+    Chars,
+}
+
+impl SqlLengthUnit {
+    pub fn length(&self, sql: &str) -> usize {
+        match self {
+            SqlLengthUnit::Bytes => sql.len(),
+            SqlLengthUnit::Chars => sql.chars().count(),
+        }
+    }
+}
+
+impl FromStr for SqlLengthUnit {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bytes" => Ok(SqlLengthUnit::Bytes),
+            "chars" => Ok(SqlLengthUnit::Chars),
+            _ => Err(plan_datafusion_err!(
+                "Invalid SQL length unit: {s}. Expected bytes or chars"
+            )),
+        }
+    }
+}
+
+impl Display for SqlLengthUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlLengthUnit::Bytes => write!(f, "bytes"),
+            SqlLengthUnit::Chars => write!(f, "chars"),
+        }
+    }
+}
+
+/// Reject the SQL longer than the limit before it's parsed. It's configured by the engine
+/// through [crate::mdl::AnalyzeOptions::with_sql_length_limit], not by the session
+/// properties, so a request can't lift it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlLengthLimit {
+    max_length: usize,
+    unit: SqlLengthUnit,
+}
+
+impl SqlLengthLimit {
+    pub fn new(max_length: usize, unit: SqlLengthUnit) -> Self {
+        Self { max_length, unit }
+    }
+
+    pub fn check(&self, sql: &str) -> Result<()> {
+        let length = self.unit.length(sql);
+        if length > self.max_length {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "The SQL has {} {}, which exceeds the limit of {} {}",
+                length, self.unit, self.max_length, self.unit
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::error::{DataFusionError, Result};
+
+    use crate::mdl::sql_length::{SqlLengthLimit, SqlLengthUnit};
+
+    #[test]
+    fn test_sql_length_limit() -> Result<()> {
+        // 13 characters but 21 bytes
+        let sql = "select '測試測試'";
+        assert_eq!(SqlLengthUnit::Chars.length(sql), 13);
+        assert_eq!(SqlLengthUnit::Bytes.length(sql), 21);
+
+        SqlLengthLimit::new(15, SqlLengthUnit::Chars).check(sql)?;
+        match SqlLengthLimit::new(15, SqlLengthUnit::Bytes).check(sql) {
+            Err(DataFusionError::ResourcesExhausted(message)) => assert_eq!(
+                message,
+                "The SQL has 21 bytes, which exceeds the limit of 15 bytes"
+            ),
+            other => panic!("expected resources exhausted, but got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unit() -> Result<()> {
+        assert_eq!("bytes".parse::<SqlLengthUnit>()?, SqlLengthUnit::Bytes);
+        assert_eq!("CHARS".parse::<SqlLengthUnit>()?, SqlLengthUnit::Chars);
+        match "words".parse::<SqlLengthUnit>() {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: Invalid SQL length unit: words. Expected bytes or chars"
+            ),
+            Ok(unit) => panic!("expected an error, but got {unit}"),
+        }
+        Ok(())
+    }
+}
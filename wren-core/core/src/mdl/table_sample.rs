@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use datafusion::common::{plan_datafusion_err, plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::parser::Statement as DFStatement;
+use datafusion::sql::sqlparser::ast::{
+    Statement, TableFactor, TableSampleKind, VisitMut, VisitorMut,
+};
+use datafusion::sql::TableReference;
+
+use crate::mdl::dialect::WrenDialect;
+use crate::mdl::manifest::DataSource;
+use crate::mdl::utils::mdl_object_name;
+use crate::mdl::WrenMDL;
+
+/// The `TABLESAMPLE` clauses of the models of a query.
+///
+/// DataFusion drops the clause silently, so the query would scan the whole table. The
+/// clauses are taken out of the statement before planning and attached to the tables of
+/// the models after unparsing, e.g. `FROM customer TABLESAMPLE SYSTEM (10)` becomes
+/// `FROM (SELECT ... FROM customer AS __source TABLESAMPLE SYSTEM (10)) AS customer`. The
+/// clause is kept as written, so it should be in the syntax of the data source.
+///
+/// A model is expanded to a scan of its table, so every scan of the table is sampled. The
+/// query can't sample a model in a reference and read all of it in another one.
+#[derive(Debug, Default)]
+pub(crate) struct TableSamples(Vec<(TableReference, TableSampleKind)>);
+
+impl TableSamples {
+    /// Take the clauses out of the statement. It fails if the data source doesn't accept
+    /// `TABLESAMPLE`, or if the sampled table isn't a model with a table reference.
+    pub(crate) fn take(
+        wren_mdl: &WrenMDL,
+        wren_dialect: &WrenDialect,
+        data_source: &DataSource,
+        statement: &mut DFStatement,
+    ) -> Result<Self> {
+        let DFStatement::Statement(statement) = statement else {
+            return Ok(Self::default());
+        };
+        let mut taker = SampleTaker {
+            wren_mdl,
+            samples: vec![],
+            unsampled: HashSet::new(),
+            error: None,
+        };
+        let _ = statement.visit(&mut taker);
+        if let Some(error) = taker.error {
+            return Err(error);
+        }
+        if let Some((table, _)) = taker
+            .samples
+            .iter()
+            .find(|(table, _)| taker.unsampled.contains(table))
+        {
+            return plan_err!(
+                "The table {table} is sampled by TABLESAMPLE in some references only"
+            );
+        }
+        if !taker.samples.is_empty() && !wren_dialect.supports_table_sample() {
+            return plan_err!(
+                "TABLESAMPLE is unsupported for the data source {data_source}"
+            );
+        }
+        Ok(Self(taker.samples))
+    }
+
+    /// Return true if no model of the query is sampled.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Attach the clauses to the tables of the models in the unparsed statement.
+    pub(crate) fn attach(&self, statement: &mut Statement) -> Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let mut attacher = SampleAttacher {
+            samples: &self.0,
+            attached: vec![false; self.0.len()],
+            error: None,
+        };
+        let _ = statement.visit(&mut attacher);
+        if let Some(error) = attacher.error {
+            return Err(error);
+        }
+        match attacher.attached.iter().position(|attached| !attached) {
+            Some(idx) => plan_err!(
+                "The TABLESAMPLE of the table {} can't be applied",
+                self.0[idx].0
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+struct SampleTaker<'a> {
+    wren_mdl: &'a WrenMDL,
+    samples: Vec<(TableReference, TableSampleKind)>,
+    /// The tables of the models referenced without `TABLESAMPLE`
+    unsampled: HashSet<TableReference>,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for SampleTaker<'_> {
+    type Break = ();
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        let TableFactor::Table { name, sample, .. } = table_factor else {
+            return ControlFlow::Continue(());
+        };
+        let model = mdl_object_name(self.wren_mdl, name)
+            .and_then(|model_name| self.wren_mdl.get_model(model_name));
+        let Some(sample) = sample.take() else {
+            if let Some(model) = model.filter(|model| model.table_reference.is_some()) {
+                self.unsampled
+                    .insert(TableReference::from(model.table_reference()));
+            }
+            return ControlFlow::Continue(());
+        };
+        match model {
+            Some(model) if model.table_reference.is_some() => {
+                self.samples
+                    .push((TableReference::from(model.table_reference()), sample));
+                ControlFlow::Continue(())
+            }
+            _ => {
+                self.error = Some(plan_datafusion_err!(
+                    "TABLESAMPLE is only supported on the models with a table reference: {name}"
+                ));
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+struct SampleAttacher<'a> {
+    samples: &'a [(TableReference, TableSampleKind)],
+    attached: Vec<bool>,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for SampleAttacher<'_> {
+    type Break = ();
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        let TableFactor::Table { name, sample, .. } = table_factor else {
+            return ControlFlow::Continue(());
+        };
+        let table = TableReference::from(name.to_string());
+        let matched: Vec<_> = self
+            .samples
+            .iter()
+            .enumerate()
+            .filter(|(_, (reference, _))| *reference == table)
+            .collect();
+        let Some((_, (_, table_sample))) = matched.first() else {
+            return ControlFlow::Continue(());
+        };
+        if matched.iter().any(|(_, (_, other))| other != table_sample) {
+            self.error = Some(plan_datafusion_err!(
+                "The table {table} is sampled by different TABLESAMPLE clauses"
+            ));
+            return ControlFlow::Break(());
+        }
+        *sample = Some(table_sample.clone());
+        for (idx, _) in matched {
+            self.attached[idx] = true;
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -3,8 +3,11 @@ use datafusion::common::{plan_err, Column, DFSchema};
 use datafusion::error::Result;
 use datafusion::execution::session_state::SessionState;
 use datafusion::logical_expr::Expr;
+use datafusion::scalar::ScalarValue;
 use datafusion::sql::sqlparser::ast::Expr::{CompoundIdentifier, Identifier};
-use datafusion::sql::sqlparser::ast::{visit_expressions, visit_expressions_mut, Ident};
+use datafusion::sql::sqlparser::ast::{
+    self, visit_expressions, visit_expressions_mut, Ident,
+};
 use datafusion::sql::sqlparser::dialect::GenericDialect;
 use datafusion::sql::sqlparser::parser::Parser;
 use petgraph::algo::is_cyclic_directed;
@@ -15,7 +18,7 @@ use std::sync::Arc;
 
 use crate::logical_plan::utils::{from_qualified_name, try_map_data_type};
 use crate::mdl::manifest::Model;
-use crate::mdl::{AnalyzedWrenMDL, ColumnReference, Dataset, SessionStateRef};
+use crate::mdl::{AnalyzedWrenMDL, ColumnReference, Dataset, SessionStateRef, WrenMDL};
 
 pub fn to_expr_queue(column: Column) -> VecDeque<String> {
     column.name.split('.').map(String::from).collect()
@@ -90,7 +93,7 @@ pub fn create_wren_calculated_field_expr(
         column_rf.column.name(),
     );
     let Some(required_fields) = analyzed_wren_mdl
-        .lineage
+        .lineage()?
         .required_fields_map
         .get(&qualified_col)
     else {
@@ -200,6 +203,19 @@ fn qualified_expr(
     Ok(expr.to_string())
 }
 
+/// Parse the SQL and render it in the canonical form of sqlparser, so the SQLs differing
+/// only in the layout share the form, e.g. for a cache key. The keywords are uppercased,
+/// the whitespaces are collapsed and the comments are removed. The identifiers and the
+/// literals are kept as they are. Normalizing the normalized SQL returns it unchanged.
+pub fn normalize_sql(sql: &str) -> Result<String> {
+    let statements = Parser::parse_sql(&GenericDialect {}, sql)?;
+    Ok(statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
 #[inline]
 pub fn quoted_ident(s: &str) -> Ident {
     Ident::with_quote('"', s)
@@ -210,12 +226,98 @@ pub fn quoted(s: &str) -> String {
     format!("\"{s}\"")
 }
 
+/// Get the object name of the MDL referenced by the table name of a SQL, e.g. `orders` of
+/// `wren.test.orders`. The name could be qualified by the schema and catalog of the MDL.
+/// Return `None` if the qualifiers are the ones of another schema or catalog.
+pub(crate) fn mdl_object_name<'a>(
+    wren_mdl: &WrenMDL,
+    name: &'a ast::ObjectName,
+) -> Option<&'a str> {
+    let idents: Vec<_> = name
+        .0
+        .iter()
+        .map(|part| match part {
+            ast::ObjectNamePart::Identifier(ident) => ident.value.as_str(),
+        })
+        .collect();
+    let (name, prefix) = idents.split_last()?;
+    let prefix_matched = match prefix {
+        [] => true,
+        [schema] => *schema == wren_mdl.schema(),
+        [catalog, schema] => {
+            *catalog == wren_mdl.catalog() && *schema == wren_mdl.schema()
+        }
+        _ => false,
+    };
+    prefix_matched.then_some(*name)
+}
+
 /// Transform the column to a datafusion field
 pub fn to_field(column: &wren_core_base::mdl::Column) -> Result<Field> {
     let data_type = try_map_data_type(&column.r#type)?;
     Ok(Field::new(&column.name, data_type, column.not_null))
 }
 
+/// Create the literal expression for the default value of the column.
+/// The default value should be a literal that can be casted to the column type,
+/// e.g. `'unknown'` for a varchar column or `0` for an integer column.
+pub fn create_default_value_expr(
+    column: &wren_core_base::mdl::Column,
+) -> Result<Option<Expr>> {
+    let Some(default_value) = &column.default_value else {
+        return Ok(None);
+    };
+    let data_type = try_map_data_type(&column.r#type)?;
+    let expr = Parser::new(&GenericDialect {})
+        .try_with_sql(default_value)?
+        .parse_expr()?;
+    let value = match &expr {
+        ast::Expr::Value(value) => literal_to_scalar(&value.value, false),
+        ast::Expr::UnaryOp {
+            op: ast::UnaryOperator::Minus,
+            expr,
+        } => match expr.as_ref() {
+            ast::Expr::Value(value) => literal_to_scalar(&value.value, true),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(value) = value else {
+        return plan_err!(
+            "The default value {} of column {} should be a literal",
+            default_value,
+            column.name
+        );
+    };
+    match value.cast_to(&data_type) {
+        Ok(value) => Ok(Some(Expr::Literal(value, None))),
+        Err(_) => plan_err!(
+            "The default value {} of column {} doesn't match the column type {}",
+            default_value,
+            column.name,
+            column.r#type
+        ),
+    }
+}
+
+fn literal_to_scalar(value: &ast::Value, negative: bool) -> Option<ScalarValue> {
+    match value {
+        ast::Value::Number(n, _) => {
+            let n = if negative { format!("-{n}") } else { n.clone() };
+            n.parse::<i64>()
+                .map(|n| ScalarValue::Int64(Some(n)))
+                .or_else(|_| n.parse::<f64>().map(|n| ScalarValue::Float64(Some(n))))
+                .ok()
+        }
+        ast::Value::SingleQuotedString(s) if !negative => {
+            Some(ScalarValue::Utf8(Some(s.clone())))
+        }
+        ast::Value::Boolean(b) if !negative => Some(ScalarValue::Boolean(Some(*b))),
+        ast::Value::Null if !negative => Some(ScalarValue::Null),
+        _ => None,
+    }
+}
+
 /// Transform the column to a datafusion field for a remote table
 pub fn to_remote_field(
     column: &wren_core_base::mdl::Column,
@@ -269,8 +371,32 @@ mod tests {
     use crate::logical_plan::utils::from_qualified_name;
     use crate::mdl::context::Mode;
     use crate::mdl::manifest::Manifest;
+    use crate::mdl::utils::normalize_sql;
     use crate::mdl::AnalyzedWrenMDL;
 
+    #[test]
+    fn test_normalize_sql() -> Result<()> {
+        let normalized = normalize_sql("select a,  b from t where a = 'x  y'")?;
+        assert_eq!(normalized, "SELECT a, b FROM t WHERE a = 'x  y'");
+        assert_eq!(
+            normalize_sql("SELECT a, b\nFROM t -- comment\n  Where a = 'x  y'")?,
+            normalized
+        );
+        assert_eq!(normalize_sql(&normalized)?, normalized);
+
+        let sql = "with c as (select count(*) as n from t group by a) select * from c order by n desc limit 1";
+        let normalized = normalize_sql(sql)?;
+        assert_eq!(normalize_sql(&normalized)?, normalized);
+        // the identifiers and the literals are kept as they are
+        assert_ne!(
+            normalize_sql("select a from t")?,
+            normalize_sql("select A from t")?
+        );
+        assert_ne!(normalize_sql("select 'a'")?, normalize_sql("select 'A'")?);
+        assert!(normalize_sql("select from where").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_create_wren_expr() -> Result<()> {
         let test_data: PathBuf =
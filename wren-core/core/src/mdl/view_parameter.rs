@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use datafusion::common::{plan_datafusion_err, plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, Expr, FunctionArg, FunctionArgExpr, Ident, Query, Statement,
+    TableAlias, TableFactor, UnaryOperator, Value, ValueWithSpan, VisitMut, VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+
+use crate::mdl::manifest::View;
+use crate::mdl::utils::mdl_object_name;
+use crate::mdl::WrenMDL;
+
+/// Build the statement of the view with the passed arguments. The placeholders `$name` of
+/// the parameters are replaced by the arguments or the default values.
+pub(crate) fn view_query_with_arguments(
+    view: &View,
+    arguments: &HashMap<String, Expr>,
+) -> Result<Query> {
+    if let Some(name) = arguments.keys().find(|name| {
+        !view
+            .parameters
+            .iter()
+            .any(|parameter| parameter.name.eq_ignore_ascii_case(name))
+    }) {
+        return plan_err!("The view {} has no parameter {}", view.name, name);
+    }
+    let mut values = HashMap::new();
+    for parameter in view.parameters.iter() {
+        let name = parameter.name.to_lowercase();
+        let value = match (arguments.get(&name), &parameter.default_value) {
+            (Some(argument), _) => argument.clone(),
+            (None, Some(default_value)) => Parser::new(&GenericDialect {})
+                .try_with_sql(default_value)?
+                .parse_expr()?,
+            (None, None) => {
+                return plan_err!(
+                    "The parameter {} of view {} is required",
+                    parameter.name,
+                    view.name
+                )
+            }
+        };
+        values.insert(name, value);
+    }
+
+    let mut statements = Parser::parse_sql(&GenericDialect {}, &view.statement)?;
+    let (Some(Statement::Query(mut query)), true) =
+        (statements.pop(), statements.is_empty())
+    else {
+        return plan_err!(
+            "The statement of view {} should be a single query",
+            view.name
+        );
+    };
+    let _ = visit_expressions_mut(&mut query, |expr| {
+        if let Expr::Value(ValueWithSpan {
+            value: Value::Placeholder(placeholder),
+            ..
+        }) = expr
+        {
+            let name = placeholder.trim_start_matches('$').to_lowercase();
+            if let Some(value) = values.get(&name) {
+                *expr = value.clone();
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(*query)
+}
+
+/// Replace the references to the parameterized views, e.g. `sales_view(threshold => 100)`,
+/// with the subqueries of the view statements built by the arguments.
+pub(crate) fn expand_parameterized_views(
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+) -> Result<()> {
+    let mut expander = ParameterizedViewExpander {
+        wren_mdl,
+        expanding: vec![],
+    };
+    match statement.visit(&mut expander) {
+        ControlFlow::Break(e) => Err(e),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+struct ParameterizedViewExpander<'a> {
+    wren_mdl: &'a WrenMDL,
+    /// The views being expanded from the outermost. `None` for the table factors that
+    /// aren't parameterized views, so that `post_visit_table_factor` can always pop.
+    expanding: Vec<Option<String>>,
+}
+
+impl VisitorMut for ParameterizedViewExpander<'_> {
+    type Break = DataFusionError;
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        let TableFactor::Table {
+            name, alias, args, ..
+        } = table_factor
+        else {
+            self.expanding.push(None);
+            return ControlFlow::Continue(());
+        };
+        let Some(view) = mdl_object_name(self.wren_mdl, name).and_then(|name| {
+            self.wren_mdl
+                .views()
+                .iter()
+                .find(|view| view.name == name && !view.parameters.is_empty())
+        }) else {
+            self.expanding.push(None);
+            return ControlFlow::Continue(());
+        };
+        if self
+            .expanding
+            .iter()
+            .flatten()
+            .any(|name| name == &view.name)
+        {
+            let path = self
+                .expanding
+                .iter()
+                .flatten()
+                .chain(std::iter::once(&view.name))
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return ControlFlow::Break(plan_datafusion_err!(
+                "The view {} references itself: {}",
+                view.name,
+                path
+            ));
+        }
+        let arguments = match args
+            .as_ref()
+            .map(|args| named_arguments(view, &args.args))
+            .transpose()
+        {
+            Ok(arguments) => arguments.unwrap_or_default(),
+            Err(e) => return ControlFlow::Break(e),
+        };
+        let query = match view_query_with_arguments(view, &arguments) {
+            Ok(query) => query,
+            Err(e) => return ControlFlow::Break(e),
+        };
+        let alias = alias.take().unwrap_or_else(|| TableAlias {
+            name: Ident::with_quote('"', &view.name),
+            columns: vec![],
+        });
+        *table_factor = TableFactor::Derived {
+            lateral: false,
+            subquery: Box::new(query),
+            alias: Some(alias),
+        };
+        // The expanded subquery is visited next, so the views it references are expanded
+        // with this view on the stack.
+        self.expanding.push(Some(view.name.clone()));
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_table_factor(
+        &mut self,
+        _: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        self.expanding.pop();
+        ControlFlow::Continue(())
+    }
+}
+
+/// Collect the arguments of the view. Only the named arguments are accepted because
+/// the order of the parameters isn't obvious to the users.
+fn named_arguments(view: &View, args: &[FunctionArg]) -> Result<HashMap<String, Expr>> {
+    args.iter()
+        .map(|arg| {
+            let (name, expr) = match arg {
+                FunctionArg::Named {
+                    name,
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                } => (name, expr),
+                FunctionArg::ExprNamed {
+                    name: Expr::Identifier(name),
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                } => (name, expr),
+                _ => {
+                    return plan_err!(
+                        "The arguments of view {} should be passed by name, e.g. {}(name => value)",
+                        view.name,
+                        view.name
+                    )
+                }
+            };
+            if !is_literal(expr) {
+                return plan_err!(
+                    "The argument {} of view {} should be a literal, but got {}",
+                    name.value,
+                    view.name,
+                    expr
+                );
+            }
+            Ok((name.value.to_lowercase(), expr.clone()))
+        })
+        .collect()
+}
+
+/// The arguments are substituted into the view statement as they are, so only the
+/// literals are accepted. A column or a subquery would be resolved against the view
+/// instead of the caller.
+fn is_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value(ValueWithSpan {
+            value: Value::Placeholder(_),
+            ..
+        }) => false,
+        Expr::Value(_) | Expr::TypedString { .. } => true,
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus | UnaryOperator::Plus,
+            expr,
+        } => matches!(
+            expr.as_ref(),
+            Expr::Value(ValueWithSpan {
+                value: Value::Number(..),
+                ..
+            })
+        ),
+        Expr::Nested(expr) => is_literal(expr),
+        _ => false,
+    }
+}